@@ -0,0 +1,86 @@
+// Material counting and engine lines, shared by the Watch tab's evaluation,
+// the puzzle generator, the position generator and adjudication in Play.
+// chess_engine hands back moves but no score, so material at the end of a
+// line played out by the engine stands in for one.
+
+use chess_engine::search::find_best_move;
+use chess_lib::{Board, Colour, GameState, Move, Piece};
+
+use crate::{background::Work, position_creator::validate::pieces_of};
+
+pub fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight | Piece::Bishop => 300,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King => 0,
+    }
+}
+
+/// White's material minus Black's, in centipawns.
+pub fn material_balance(board: &Board) -> i32 {
+    Piece::ALL_PIECES
+        .iter()
+        .map(|&p| {
+            let white = pieces_of(board, Colour::White, p).len() as i32;
+            let black = pieces_of(board, Colour::Black, p).len() as i32;
+            (white - black) * piece_value(p)
+        })
+        .sum()
+}
+
+/// An engine line played out one search at a time, so it can be run on a
+/// thread or spread over frames.
+#[derive(Debug, Clone)]
+pub struct LineSearch
+{
+    board: Board,
+    line: Vec<Move>,
+    depth: u8,
+    max_plies: usize,
+    /// Set when the engine had no move to give.
+    stuck: bool,
+}
+impl LineSearch
+{
+    /// A line from `position` starting with `first`, or with the engine's
+    /// own choice when there's none, and the engine's choices for both sides after.
+    pub fn new(position: &Board, first: Option<Move>, depth: u8, max_plies: usize) -> Self {
+        let mut board = position.clone();
+        let mut line = Vec::new();
+        if let Some(m) = first {
+            board.make_move_unchecked(m);
+            line.push(m);
+        }
+        Self { board, line, depth, max_plies, stuck: false }
+    }
+    pub fn line(&self) -> &[Move] {
+        &self.line
+    }
+    /// The position at the end of the line so far.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+}
+impl Work for LineSearch
+{
+    /// The moves and the position they lead to.
+    type Output = (Vec<Move>, Board);
+
+    fn step(&mut self) -> Option<Self::Output> {
+        if !self.finished() {
+            match find_best_move(&mut self.board, self.depth) {
+                Some(m) => {
+                    self.board.make_move_unchecked(m);
+                    self.line.push(m);
+                }
+                None => self.stuck = true,
+            }
+        }
+        self.finished().then(|| (self.line.clone(), self.board.clone()))
+    }
+    fn finished(&self) -> bool {
+        self.stuck || self.line.len() >= self.max_plies || self.board.get_state() != GameState::Playing
+    }
+}
@@ -1,20 +1,30 @@
-use chess_lib::Board;
 use eframe::egui;
 use egui::{Id, Modal};
 
-use crate::{play::PlayTab, position_creator::PositionTab, utils::load_atlas};
+use crate::{drill::{catalog::Goal, DrillTab}, online::OnlineTab, play::PlayTab, position_creator::PositionTab, puzzle::PuzzleTab, share::{self, SharedLink}, statistics::StatisticsTab, training::TrainingTab, utils::load_atlas, watch::WatchTab};
 
 pub enum Tab
 {
     Play,
     PositionCreator,
+    Puzzles,
+    Drills,
+    Online,
+    Watch,
     Statistics,
+    Training,
 }
 pub struct ChessApp {
     current_tab: Tab,
 
     play_tab: PlayTab,
     position_tab: PositionTab,
+    puzzle_tab: PuzzleTab,
+    drill_tab: DrillTab,
+    online_tab: OnlineTab,
+    watch_tab: WatchTab,
+    statistics_tab: StatisticsTab,
+    training_tab: TrainingTab,
 
     show_modal: Option<String>,
 
@@ -25,7 +35,7 @@ impl ChessApp
         
         let ctx = &cc.egui_ctx;
 
-        Self {            
+        let mut app = Self {            
             current_tab: Tab::Play,
             play_tab: 
                 PlayTab::new(
@@ -37,7 +47,39 @@ impl ChessApp
                     None,
                     load_atlas(ctx),
                 ),
+                puzzle_tab: PuzzleTab::new(load_atlas(ctx)),
+                drill_tab: DrillTab::new(load_atlas(ctx)),
+                online_tab: OnlineTab::new(load_atlas(ctx)),
+                watch_tab: WatchTab::new(load_atlas(ctx)),
+                statistics_tab: StatisticsTab::new(),
+                training_tab: TrainingTab::new(load_atlas(ctx)),
             show_modal: None,
+        };
+        if let Some(link) = share::page_link() {
+            app.open_link(&link);
+        }
+        app
+    }
+    /// Opens the tab, position or game a share link points at.
+    fn open_link(&mut self, link: &SharedLink) {
+        let tab = match link.tab.as_deref() {
+            Some("editor") => Tab::PositionCreator,
+            Some("puzzles") => Tab::Puzzles,
+            Some("drills") => Tab::Drills,
+            Some("online") => Tab::Online,
+            Some("watch") => Tab::Watch,
+            Some("statistics") => Tab::Statistics,
+            Some("training") => Tab::Training,
+            _ => Tab::Play,
+        };
+        let opened = match tab {
+            Tab::PositionCreator => self.position_tab.open_link(link),
+            _ if link.fen.is_some() || link.moves.is_some() => self.play_tab.open_link(link),
+            _ => Ok(()),
+        };
+        self.current_tab = tab;
+        if let Err(e) = opened {
+            self.show_modal = Some(format!("Couldn't open the link: {}", e));
         }
     }
 }
@@ -47,21 +89,31 @@ impl eframe::App for ChessApp {
         if self.play_tab.should_close || self.position_tab.should_close {
             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
         }
+        let finished = std::mem::take(&mut self.play_tab.finished_games);
+        self.statistics_tab.add_games(finished);
+        if self.position_tab.change_tab && !self.position_tab.problems.is_empty() {
+            let problems: Vec<&str> = self.position_tab.problems.iter().map(|p| p.message.as_str()).collect();
+            self.show_modal = Some(format!("This position can't be played:\n{}", problems.join("\n")));
+            self.position_tab.change_tab = false;
+        }
         if self.position_tab.change_tab {
-            let position = self.position_tab.board.to_fen();
-            match Board::new_from_fen(&position) {
-                Ok(b) => {
-                    self.play_tab.board = b.clone();
-                    self.play_tab.view_board = b;
-                    self.current_tab = Tab::Play;
-                },
-                Err(e) => {
-                    self.show_modal = Some(e);
-                },
-                
+            // The full FEN, so Chess960 rights chess_lib can't play reach the Play tab
+            let position = self.position_tab.fen();
+            self.play_tab.chess960 = self.position_tab.chess960;
+            match self.play_tab.set_position_fen(&position) {
+                Ok(()) => self.current_tab = Tab::Play,
+                Err(e) => self.show_modal = Some(e),
             };
             self.position_tab.change_tab = false;
         }
+        if self.position_tab.drill_requested {
+            let fen = self.position_tab.lib_fen();
+            self.drill_tab.custom_fen = fen.clone();
+            self.drill_tab.abandon();
+            self.drill_tab.add_custom(&fen, Goal::Win, 50);
+            self.current_tab = Tab::Drills;
+            self.position_tab.drill_requested = false;
+        }
         if let Some(s) = &self.show_modal {
             let text = s.clone();
             Modal::new(Id::new("modal"))
@@ -97,15 +149,35 @@ impl eframe::App for ChessApp {
                 if tab_button(ui, "Position Creator", matches!(self.current_tab, Tab::PositionCreator)).clicked() {
                     self.current_tab = Tab::PositionCreator;
                 }
-                // if tab_button(ui, "Statistics", matches!(self.current_tab, Tab::Statistics)).clicked() {
-                //     self.current_tab = Tab::Statistics;
-                // }
+                if tab_button(ui, "Puzzles", matches!(self.current_tab, Tab::Puzzles)).clicked() {
+                    self.current_tab = Tab::Puzzles;
+                }
+                if tab_button(ui, "Drills", matches!(self.current_tab, Tab::Drills)).clicked() {
+                    self.current_tab = Tab::Drills;
+                }
+                if tab_button(ui, "Online", matches!(self.current_tab, Tab::Online)).clicked() {
+                    self.current_tab = Tab::Online;
+                }
+                if tab_button(ui, "Watch", matches!(self.current_tab, Tab::Watch)).clicked() {
+                    self.current_tab = Tab::Watch;
+                }
+                if tab_button(ui, "Statistics", matches!(self.current_tab, Tab::Statistics)).clicked() {
+                    self.current_tab = Tab::Statistics;
+                }
+                if tab_button(ui, "Training", matches!(self.current_tab, Tab::Training)).clicked() {
+                    self.current_tab = Tab::Training;
+                }
             });
         });
         match self.current_tab {
             Tab::Play => self.play_tab.render(ctx),
             Tab::PositionCreator => self.position_tab.render(ctx),
-            Tab::Statistics => (),
+            Tab::Puzzles => self.puzzle_tab.render(ctx),
+            Tab::Drills => self.drill_tab.render(ctx),
+            Tab::Online => self.online_tab.render(ctx),
+            Tab::Watch => self.watch_tab.render(ctx),
+            Tab::Statistics => self.statistics_tab.render(ctx),
+            Tab::Training => self.training_tab.render(ctx),
         }
     }
 }
@@ -0,0 +1,121 @@
+// Attacks for the overlays and drills. Captures come from chess_lib's legal
+// moves and attacked squares from its check detection, so pins and en passant
+// are handled the way the rules handle them. The piece geometry at the top only
+// serves the puzzle theme labels, which look for forks and pins by shape.
+
+use chess_lib::{Board, Colour, Move, MoveList, Piece, Tile};
+
+const KNIGHT_STEPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_DIRS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRS: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+pub fn offset(tile: Tile, dx: i32, dy: i32) -> Option<Tile> {
+    let (x, y) = tile.get_coords();
+    let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+    if !(0..8).contains(&nx) || !(0..8).contains(&ny) {
+        return None;
+    }
+    Tile::new_xy(nx as u8, ny as u8)
+}
+
+pub fn directions(piece: Piece) -> Vec<(i32, i32)> {
+    match piece {
+        Piece::Rook => ROOK_DIRS.to_vec(),
+        Piece::Bishop => BISHOP_DIRS.to_vec(),
+        Piece::Queen => ROOK_DIRS.iter().chain(BISHOP_DIRS.iter()).copied().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Squares the piece on `from` attacks by shape, ignoring pins.
+pub fn attacks(board: &Board, from: Tile) -> Vec<Tile> {
+    let Some((piece, colour)) = board.get_piece_at_tile(from) else {
+        return Vec::new();
+    };
+    match piece {
+        Piece::Pawn => {
+            let dy = if colour.white() { 1 } else { -1 };
+            [-1, 1].iter().filter_map(|&dx| offset(from, dx, dy)).collect()
+        }
+        Piece::Knight => KNIGHT_STEPS.iter().filter_map(|&(dx, dy)| offset(from, dx, dy)).collect(),
+        Piece::King => KING_STEPS.iter().filter_map(|&(dx, dy)| offset(from, dx, dy)).collect(),
+        _ => {
+            let mut tiles = Vec::new();
+            for (dx, dy) in directions(piece) {
+                let mut tile = from;
+                while let Some(next) = offset(tile, dx, dy) {
+                    tiles.push(next);
+                    if board.get_piece_at_tile(next).is_some() {
+                        break;
+                    }
+                    tile = next;
+                }
+            }
+            tiles
+        }
+    }
+}
+
+/// `board` with `colour` to move, read back through a FEN when it is the
+/// other side's turn. En passant is dropped then, as it was never on.
+pub fn with_turn(board: &Board, colour: Colour) -> Option<Board> {
+    if board.turn == colour {
+        return Some(board.clone());
+    }
+    let fen = board.to_fen();
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    fields[1] = if colour.white() { "w" } else { "b" };
+    fields[3] = "-";
+    Board::new_from_fen(&fields.join(" ")).ok()
+}
+
+/// The side to move's legal captures, each with the square of the piece it
+/// takes, which for en passant is beside the move's destination.
+pub fn captures(board: &Board) -> Vec<(Move, Tile)> {
+    let mut moves = MoveList::new();
+    board.generate_legal_moves(board.turn, &mut moves);
+    moves
+        .iter()
+        .filter_map(|&m| {
+            if board.get_piece_at_tile(m.to()).is_some() {
+                return Some((m, m.to()));
+            }
+            let ((fx, fy), (tx, _)) = (m.from().get_coords(), m.to().get_coords());
+            let pawn = board.get_piece_at_tile(m.from()).is_some_and(|(p, _)| p == Piece::Pawn);
+            if pawn && fx != tx { Tile::new_xy(tx, fy).map(|t| (m, t)) } else { None }
+        })
+        .collect()
+}
+
+/// Whether any piece of `by` attacks `tile`, pinned or not, as chess_lib's
+/// check detection sees it: the other side's king is stood on `tile`, with a
+/// knight left on its old square so lines through it stay blocked.
+pub fn attacked(board: &Board, tile: Tile, by: Colour) -> bool {
+    let defender = if by.white() { Colour::Black } else { Colour::White };
+    let king = all_tiles().find(|&t| board.get_piece_at_tile(t) == Some((Piece::King, defender)));
+    let mut probe = board.clone();
+    if let Some((piece, colour)) = board.get_piece_at_tile(tile) {
+        let owner = if colour.white() { &mut probe.white } else { &mut probe.black };
+        owner.remove_piece_type(piece, tile);
+    }
+    let player = if defender.white() { &mut probe.white } else { &mut probe.black };
+    if let Some(king) = king.filter(|&k| k != tile) {
+        player.remove_piece_type(Piece::King, king);
+        player.place_piece(Piece::Knight, king);
+    }
+    player.place_piece(Piece::King, tile);
+    let fen = probe.to_fen();
+    let Some(placement) = fen.split_whitespace().next() else {
+        return false;
+    };
+    let turn = if defender.white() { "w" } else { "b" };
+    Board::new_from_fen(&format!("{} {} - - 0 1", placement, turn)).is_ok_and(|b| b.is_in_check(defender))
+}
+
+pub fn all_tiles() -> impl Iterator<Item = Tile> {
+    (0..8).flat_map(|y| (0..8).filter_map(move |x| Tile::new_xy(x, y)))
+}
@@ -0,0 +1,92 @@
+// Engine work kept off the UI thread. Native builds give it a thread of its
+// own; the web build has no threads, so there it runs a step per frame.
+
+use egui::Context;
+
+/// How often the UI looks for results from a native thread.
+#[cfg(not(target_arch = "wasm32"))]
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Work done in small pieces of about one engine search each.
+pub trait Work: Send + 'static
+{
+    type Output: Send + 'static;
+
+    /// Does the next piece of the work, handing back anything it finished.
+    fn step(&mut self) -> Option<Self::Output>;
+    fn finished(&self) -> bool;
+}
+
+pub struct Background<W: Work>
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    outputs: std::sync::mpsc::Receiver<W::Output>,
+    #[cfg(not(target_arch = "wasm32"))]
+    finished: bool,
+    #[cfg(target_arch = "wasm32")]
+    work: W,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<W: Work> Background<W>
+{
+    pub fn start(mut work: W) -> Self {
+        let (tx, outputs) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            while !work.finished() {
+                let Some(output) = work.step() else {
+                    continue;
+                };
+                // Dropping the `Background` closes the channel, which ends the work here
+                if tx.send(output).is_err() {
+                    return;
+                }
+            }
+        });
+        Self { outputs, finished: false }
+    }
+    /// What was finished since the last call. Keeps the UI repainting until
+    /// the work is done.
+    pub fn poll(&mut self, ctx: &Context) -> Vec<W::Output> {
+        use std::sync::mpsc::TryRecvError;
+
+        let mut outputs = Vec::new();
+        loop {
+            match self.outputs.try_recv() {
+                Ok(output) => outputs.push(output),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        if !self.finished {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        }
+        outputs
+    }
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<W: Work> Background<W>
+{
+    pub fn start(work: W) -> Self {
+        Self { work }
+    }
+    /// Runs one step of the work and gives what it finished.
+    pub fn poll(&mut self, ctx: &Context) -> Vec<W::Output> {
+        if self.work.finished() {
+            return Vec::new();
+        }
+        let output = self.work.step();
+        ctx.request_repaint();
+        output.into_iter().collect()
+    }
+    pub fn finished(&self) -> bool {
+        self.work.finished()
+    }
+}
@@ -0,0 +1,178 @@
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+// Draws board diagrams without opening the GUI.
+//
+//     cargo run --bin diagram -- [options] [FEN...]
+//
+// Each FEN argument is one diagram; --fens and --pgn add positions from files,
+// a PGN game giving its final position (or the whole game with --format gif). A single
+// diagram is written to --out, several go into the --out directory as
+// diagram-001.png, diagram-002.png and so on.
+
+#[allow(dead_code)]
+#[path = "../base64.rs"]
+mod base64;
+#[path = "../diagram.rs"]
+mod diagram;
+#[allow(dead_code)]
+#[path = "../notation.rs"]
+mod notation;
+#[allow(dead_code)]
+#[path = "../pgn.rs"]
+mod pgn;
+
+use std::{fs, path::PathBuf, process};
+
+use chess_lib::Board;
+use diagram::{raster, svg, DiagramOptions};
+
+const USAGE: &str = "Usage: diagram [options] [FEN...]
+
+Inputs:
+  --fens FILE       one FEN per line
+  --pgn FILE        every game in the file
+
+Output:
+  --out PATH        file for one diagram, directory for several (default: diagram.<format>)
+  --format FORMAT   png, svg or gif (default: png); gif animates --pgn games
+  --size N          board size in pixels (default: 480)
+  --delay MS        GIF delay per move (default: 800)
+  --flip            Black at the bottom
+  --no-coords       leave out the coordinates
+  --no-highlight    don't highlight the last move
+  --arrow e2e4      draw an arrow, may be repeated
+  --mark e4         highlight a square, may be repeated";
+
+struct Args
+{
+    boards: Vec<Board>,
+    /// Whether any board came from a FEN, which has no moves to animate.
+    from_fen: bool,
+    out: Option<PathBuf>,
+    format: String,
+    delay: u32,
+    options: DiagramOptions,
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn read(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| fail(&format!("Can't read {}: {}", path, e)))
+}
+
+fn parse_fen(fen: &str) -> Board {
+    Board::new_from_fen(fen.trim()).unwrap_or_else(|e| fail(&format!("Bad FEN '{}': {}", fen.trim(), e)))
+}
+
+/// Replays a game's moves, stopping with a warning at the first one that doesn't fit.
+fn game_board(game: &pgn::PgnGame) -> Board {
+    let mut board = game.tag("FEN").map(parse_fen).unwrap_or_else(Board::new);
+    for san in &game.moves {
+        match notation::parse_move(&board, san) {
+            Some(m) => board.make_move_unchecked(m),
+            None => {
+                eprintln!("Stopping at illegal move {} in {}", san, game.tag("Event").unwrap_or("a game"));
+                break;
+            }
+        }
+    }
+    board
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        boards: Vec::new(),
+        from_fen: false,
+        out: None,
+        format: "png".to_string(),
+        delay: 800,
+        options: DiagramOptions::default(),
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().unwrap_or_else(|| fail(&format!("{} needs a value\n\n{}", arg, USAGE)));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--fens" => {
+                let text = read(&value());
+                args.boards.extend(text.lines().filter(|l| !l.trim().is_empty()).map(parse_fen));
+                args.from_fen = true;
+            }
+            "--pgn" => {
+                let text = read(&value());
+                args.boards.extend(pgn::parse_games(&text).iter().map(game_board));
+            }
+            "--out" | "-o" => args.out = Some(PathBuf::from(value())),
+            "--format" => args.format = value().to_lowercase(),
+            "--size" => args.options.size = value().parse().unwrap_or_else(|_| fail("--size needs a number")),
+            "--delay" => args.delay = value().parse().unwrap_or_else(|_| fail("--delay needs a number")),
+            "--flip" => args.options.flipped = true,
+            "--no-coords" => args.options.coordinates = false,
+            "--no-highlight" => args.options.highlight_last_move = false,
+            "--arrow" => {
+                let arrows = diagram::parse_arrows(&value()).unwrap_or_else(|e| fail(&e));
+                args.options.arrows.extend(arrows);
+            }
+            "--mark" => {
+                let squares = diagram::parse_squares(&value()).unwrap_or_else(|e| fail(&e));
+                args.options.highlights.extend(squares);
+            }
+            _ if arg.starts_with("--") => fail(&format!("Unknown option {}\n\n{}", arg, USAGE)),
+            _ => {
+                args.boards.push(parse_fen(&arg));
+                args.from_fen = true;
+            }
+        }
+    }
+    if !matches!(args.format.as_str(), "png" | "svg" | "gif") {
+        fail(&format!("Unknown format {}, use png, svg or gif", args.format));
+    }
+    if args.format == "gif" && args.from_fen {
+        fail("A GIF animates the moves of a --pgn game, and a FEN has none");
+    }
+    args
+}
+
+fn render(board: &Board, args: &Args) -> Result<Vec<u8>, String> {
+    match args.format.as_str() {
+        "svg" => Ok(svg::board_svg(board, &args.options).into_bytes()),
+        "gif" => raster::game_gif(&diagram::game_positions(board), &args.options, args.delay),
+        _ => raster::board_png(board, &args.options),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let args = parse_args();
+    if args.boards.is_empty() {
+        fail(USAGE);
+    }
+
+    let paths: Vec<PathBuf> = if args.boards.len() == 1 {
+        vec![args.out.clone().unwrap_or(PathBuf::from(format!("diagram.{}", args.format)))]
+    } else {
+        let dir = args.out.clone().unwrap_or(PathBuf::from("."));
+        if let Err(e) = fs::create_dir_all(&dir) {
+            fail(&format!("Can't create {}: {}", dir.display(), e));
+        }
+        (1..=args.boards.len()).map(|i| dir.join(format!("diagram-{:03}.{}", i, args.format))).collect()
+    };
+
+    for (board, path) in args.boards.iter().zip(&paths) {
+        let bytes = render(board, &args).unwrap_or_else(|e| fail(&e));
+        if let Err(e) = fs::write(path, bytes) {
+            fail(&format!("Can't write {}: {}", path.display(), e));
+        }
+        println!("{}", path.display());
+    }
+}
+
+// Like the relay, the tool is native only
+#[cfg(target_arch = "wasm32")]
+fn main() {}
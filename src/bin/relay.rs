@@ -0,0 +1,41 @@
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+// Relay server for online play between two copies of the GUI.
+//
+//     cargo run --bin relay -- [address]
+//
+// Listens on 0.0.0.0:7878 by default. Native clients connect over TCP, the web
+// build over WebSocket (ws://host:7878), both on the same port.
+
+#[allow(dead_code)]
+#[path = "../base64.rs"]
+mod base64;
+#[allow(dead_code)]
+#[path = "../notation.rs"]
+mod notation;
+#[allow(dead_code)]
+#[path = "../net/protocol.rs"]
+mod protocol;
+#[path = "../net/server.rs"]
+mod server;
+#[path = "../net/websocket.rs"]
+mod websocket;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let addr = std::env::args().nth(1).unwrap_or(format!("0.0.0.0:{}", protocol::DEFAULT_PORT));
+    match server::listen(addr.as_str()) {
+        Ok(listener) => {
+            println!("Relay listening on {}", addr);
+            server::serve(listener);
+        }
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// The relay is native only; this keeps `trunk build` happy
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,186 @@
+use chess_lib::Piece;
+
+use crate::notation::piece_char;
+
+/// The standard start position's number.
+pub const STANDARD: u16 = 518;
+
+/// Back rank of Chess960 start position `n` (0–959) in the standard
+/// numbering, where 518 is the normal RNBQKBNR.
+pub fn back_rank(n: u16) -> [Piece; 8] {
+    let mut rank: [Option<Piece>; 8] = [None; 8];
+    let mut n = (n % 960) as usize;
+
+    // Light-squared bishop on b/d/f/h, dark-squared bishop on a/c/e/g
+    rank[(n % 4) * 2 + 1] = Some(Piece::Bishop);
+    n /= 4;
+    rank[(n % 4) * 2] = Some(Piece::Bishop);
+    n /= 4;
+
+    let place_nth_empty = |rank: &mut [Option<Piece>; 8], nth: usize, piece: Piece| {
+        let file = (0..8).filter(|&f| rank[f].is_none()).nth(nth).expect("enough empty files");
+        rank[file] = Some(piece);
+    };
+
+    place_nth_empty(&mut rank, n % 6, Piece::Queen);
+    n /= 6;
+
+    const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+    let (k1, k2) = KNIGHTS[n];
+    // Place the second knight first so the first one's index is unaffected
+    place_nth_empty(&mut rank, k2, Piece::Knight);
+    place_nth_empty(&mut rank, k1, Piece::Knight);
+
+    // The king always sits between the two rooks
+    for piece in [Piece::Rook, Piece::King, Piece::Rook] {
+        place_nth_empty(&mut rank, 0, piece);
+    }
+
+    rank.map(|p| p.expect("every file filled"))
+}
+
+/// Files of the king and the two rooks, as (king, queenside rook, kingside rook).
+pub fn king_and_rooks(rank: &[Piece; 8]) -> (u8, u8, u8) {
+    let king = rank.iter().position(|p| *p == Piece::King).unwrap_or(4) as u8;
+    let long = rank.iter().position(|p| *p == Piece::Rook).unwrap_or(0) as u8;
+    let short = rank.iter().rposition(|p| *p == Piece::Rook).unwrap_or(7) as u8;
+    (king, long, short)
+}
+
+/// Whether chess_lib's orthodox castling (king e-file, rooks a/h) also
+/// matches the Chess960 rules for this back rank.
+pub fn orthodox_castling(rank: &[Piece; 8]) -> bool {
+    king_and_rooks(rank) == (4, 0, 7)
+}
+
+/// FEN for start position `n`, with its castling rights in Shredder-FEN.
+pub fn start_fen(n: u16) -> String {
+    let rank = back_rank(n);
+    let white: String = rank.iter().map(|p| piece_char(*p)).collect();
+    let black = white.to_ascii_lowercase();
+    let (_, long, short) = king_and_rooks(&rank);
+    let castling = castling_field(&[Some(short), Some(long), Some(short), Some(long)]);
+    format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {} - 0 1", black, white, castling)
+}
+
+/// One char per file of a FEN rank, `.` for an empty square.
+fn expand_rank(rank: &str) -> Vec<char> {
+    rank.chars()
+        .flat_map(|c| match c.to_digit(10) {
+            Some(n) => vec!['.'; n as usize],
+            None => vec![c],
+        })
+        .collect()
+}
+
+/// White's and Black's back ranks in a FEN.
+fn back_ranks(fen: &str) -> [Vec<char>; 2] {
+    let ranks: Vec<&str> = fen.split_whitespace().next().unwrap_or_default().split('/').collect();
+    [
+        ranks.last().map(|r| expand_rank(r)).unwrap_or_default(),
+        ranks.first().map(|r| expand_rank(r)).unwrap_or_default(),
+    ]
+}
+
+/// Files of the kings standing on their back ranks, White then Black.
+pub fn king_files(fen: &str) -> [Option<u8>; 2] {
+    let [white, black] = back_ranks(fen);
+    [
+        white.iter().position(|&c| c == 'K').map(|f| f as u8),
+        black.iter().position(|&c| c == 'k').map(|f| f as u8),
+    ]
+}
+
+/// The castling rook files a FEN's castling field names, in the order White
+/// short, White long, Black short, Black long. Reads both `KQkq`, meaning the
+/// outermost rook on that side of the king, and Shredder-FEN file letters.
+pub fn castling_files(fen: &str) -> [Option<u8>; 4] {
+    let ranks = back_ranks(fen);
+    let kings = king_files(fen);
+    let mut files = [None; 4];
+    for c in fen.split_whitespace().nth(2).unwrap_or("-").chars() {
+        let side = c.is_ascii_lowercase() as usize;
+        let Some(king) = kings[side] else {
+            continue;
+        };
+        let rank = &ranks[side];
+        let rook = if side == 0 { 'R' } else { 'r' };
+        let (kingside, file) = match c.to_ascii_lowercase() {
+            'k' => (true, rank.iter().rposition(|&p| p == rook).filter(|&f| f as u8 > king)),
+            'q' => (false, rank.iter().position(|&p| p == rook).filter(|&f| (f as u8) < king)),
+            letter @ 'a'..='h' => {
+                let f = (letter as u8 - b'a') as usize;
+                (f as u8 > king, (rank.get(f) == Some(&rook)).then_some(f))
+            }
+            _ => continue,
+        };
+        files[side * 2 + !kingside as usize] = file.map(|f| f as u8);
+    }
+    files
+}
+
+/// A Shredder-FEN castling field for rook files in `castling_files` order.
+pub fn castling_field(files: &[Option<u8>; 4]) -> String {
+    let field: String = files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, file)| {
+            let c = (b'a' + (*file)?) as char;
+            Some(if i < 2 { c.to_ascii_uppercase() } else { c })
+        })
+        .collect();
+    if field.is_empty() { "-".to_string() } else { field }
+}
+
+/// `fen` with its castling field replaced.
+pub fn with_castling(fen: &str, castling: &str) -> String {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    if let Some(field) = fields.get_mut(2) {
+        *field = castling;
+    }
+    fields.join(" ")
+}
+
+/// Whether right `i` of `castling_files` is one chess_lib can play: the king
+/// on the e-file and the rook on the a or h-file.
+fn orthodox_right(kings: &[Option<u8>; 2], i: usize, file: u8) -> bool {
+    kings[i / 2] == Some(4) && file == [7, 0][i % 2]
+}
+
+/// Whether chess_lib can play every castling right in `fen`.
+pub fn orthodox_rights(fen: &str) -> bool {
+    let kings = king_files(fen);
+    castling_files(fen).iter().enumerate().all(|(i, file)| file.is_none_or(|f| orthodox_right(&kings, i, f)))
+}
+
+/// Rewrites a FEN's castling field in Shredder-FEN form, naming the
+/// castling rook's file (`HAha` for the standard position).
+pub fn to_shredder_fen(fen: &str) -> String {
+    if fen.split_whitespace().count() < 3 {
+        return fen.to_string();
+    }
+    with_castling(fen, &castling_field(&castling_files(fen)))
+}
+
+/// Whether a FEN's castling field only makes sense in Chess960: it names rook
+/// files (Shredder-FEN), or its rights have the king or rook off e, a and h.
+pub fn is_chess960_fen(fen: &str) -> bool {
+    let shredder = fen.split_whitespace().nth(2).is_some_and(|c| c.chars().any(|c| matches!(c.to_ascii_lowercase(), 'a'..='h')));
+    shredder || !orthodox_rights(fen)
+}
+
+/// The FEN as chess_lib reads it: orthodox rights as `KQkq`, any others left out.
+pub fn lib_fen(fen: &str) -> String {
+    if fen.split_whitespace().count() < 3 {
+        return fen.to_string();
+    }
+    let kings = king_files(fen);
+    let castling: String = castling_files(fen)
+        .iter()
+        .zip("KQkq".chars())
+        .enumerate()
+        .filter(|(i, (file, _))| file.is_some_and(|f| orthodox_right(&kings, *i, f)))
+        .map(|(_, (_, c))| c)
+        .collect();
+    with_castling(fen, if castling.is_empty() { "-" } else { &castling })
+}
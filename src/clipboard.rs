@@ -0,0 +1,124 @@
+// Copying goes through egui's `Context::copy_text`, which reaches the system
+// clipboard natively and the browser's in wasm. egui only hands over text on
+// Ctrl+V though, so the Paste buttons read the clipboard themselves.
+
+use chess_lib::Board;
+use egui::{Context, Event};
+
+use crate::{
+    chess960, notation,
+    pgn::{self, PgnGame},
+};
+
+pub enum Pasted
+{
+    /// The position as chess_lib reads it, and the FEN as pasted, which may
+    /// hold Chess960 castling rights chess_lib doesn't know.
+    Fen(Board, String),
+    Pgn(PgnGame),
+}
+
+/// Tells a FEN from PGN: a FEN is a single line starting with the eight
+/// ranks of a piece placement, anything else is read as PGN.
+pub fn classify(text: &str) -> Result<Pasted, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("The clipboard is empty".to_string());
+    }
+    let placement = text.split_whitespace().next().unwrap_or_default();
+    let is_fen = !text.contains('\n')
+        && placement.matches('/').count() == 7
+        && placement.chars().all(|c| c == '/' || c.is_ascii_digit() || "pnbrqkPNBRQK".contains(c));
+    if is_fen {
+        return Board::new_from_fen(&chess960::lib_fen(text))
+            .map(|board| Pasted::Fen(board, text.to_string()))
+            .map_err(|e| format!("Invalid FEN: {}", e));
+    }
+    match pgn::parse_games(text).into_iter().next() {
+        Some(game) if !game.moves.is_empty() || !game.tags.is_empty() => Ok(Pasted::Pgn(game)),
+        _ => Err("The clipboard holds neither a FEN nor a PGN game".to_string()),
+    }
+}
+
+/// The position a game starts from: its FEN tag, else the standard one.
+/// Shredder-FEN castling is read as the `KQkq` rights chess_lib can play.
+pub fn start_board(game: &PgnGame) -> Result<Board, String> {
+    match game.tag("FEN") {
+        Some(fen) => Board::new_from_fen(&chess960::lib_fen(fen)).map_err(|e| format!("Bad FEN tag: {}", e)),
+        None => Ok(Board::new()),
+    }
+}
+
+/// The position at the end of `game`'s moves.
+pub fn final_board(game: &PgnGame) -> Result<Board, String> {
+    let mut board = start_board(game)?;
+    for san in &game.moves {
+        let m = notation::parse_move(&board, san).ok_or(format!("Illegal move {}", san))?;
+        board.make_move_unchecked(m);
+    }
+    Ok(board)
+}
+
+/// Text pasted with Ctrl+V this frame, unless a text field is taking it.
+pub fn pasted_text(ctx: &Context) -> Option<String> {
+    if ctx.wants_keyboard_input() {
+        return None;
+    }
+    ctx.input(|i| {
+        i.events.iter().find_map(|e| match e {
+            Event::Paste(text) => Some(text.clone()),
+            _ => None,
+        })
+    })
+}
+
+/// Ctrl+C this frame with no text field to copy from.
+pub fn copy_pressed(ctx: &Context) -> bool {
+    !ctx.wants_keyboard_input() && ctx.input(|i| i.events.iter().any(|e| matches!(e, Event::Copy)))
+}
+
+/// Reads the clipboard for a Paste button. Browsers answer asynchronously,
+/// so the text turns up in `take` on a later frame.
+#[derive(Default)]
+pub struct ClipboardReader
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    ready: Option<Result<String, String>>,
+    #[cfg(target_arch = "wasm32")]
+    ready: std::rc::Rc<std::cell::RefCell<Option<Result<String, String>>>>,
+}
+
+impl ClipboardReader
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn request(&mut self, _ctx: &Context) {
+        let text = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text());
+        self.ready = Some(text.map_err(|e| format!("Can't read the clipboard: {}", e)));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn take(&mut self) -> Option<Result<String, String>> {
+        self.ready.take()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn request(&mut self, ctx: &Context) {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let promise = window.navigator().clipboard().read_text();
+        let ready = self.ready.clone();
+        let ctx = ctx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let text = match wasm_bindgen_futures::JsFuture::from(promise).await {
+                Ok(value) => value.as_string().ok_or("The clipboard doesn't hold text".to_string()),
+                Err(_) => Err("The browser didn't allow reading the clipboard".to_string()),
+            };
+            *ready.borrow_mut() = Some(text);
+            ctx.request_repaint();
+        });
+    }
+    #[cfg(target_arch = "wasm32")]
+    pub fn take(&mut self) -> Option<Result<String, String>> {
+        self.ready.borrow_mut().take()
+    }
+}
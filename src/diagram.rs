@@ -0,0 +1,166 @@
+// Board diagrams drawn without egui, so the Play tab's export and the `diagram`
+// command line tool share them. Only chess_lib, `image` and the bundled piece
+// art are needed.
+
+use chess_lib::{Board, Colour, Piece, Tile};
+
+use crate::notation;
+
+// Explicit paths so `src/bin/diagram.rs` can pull this file in with `#[path]`
+#[path = "diagram/raster.rs"]
+pub mod raster;
+#[path = "diagram/svg.rs"]
+pub mod svg;
+
+pub const LIGHT_SQUARE: [u8; 3] = [240, 217, 181];
+pub const DARK_SQUARE: [u8; 3] = [181, 136, 99];
+/// The square of a king in check.
+pub const CHECK_SQUARE: [u8; 3] = [255, 0, 0];
+/// Last move and marked squares, drawn over the square colour.
+pub const HIGHLIGHT: [u8; 4] = [255, 235, 59, 110];
+pub const ARROW: [u8; 4] = [21, 120, 27, 190];
+
+#[derive(Debug, Clone)]
+pub struct DiagramOptions
+{
+    /// Width and height of the board in pixels.
+    pub size: u32,
+    pub flipped: bool,
+    /// File letters and rank numbers along the board's edges.
+    pub coordinates: bool,
+    pub highlight_last_move: bool,
+    /// Extra squares to highlight.
+    pub highlights: Vec<Tile>,
+    pub arrows: Vec<(Tile, Tile)>,
+}
+impl Default for DiagramOptions
+{
+    fn default() -> Self {
+        Self {
+            size: 480,
+            flipped: false,
+            coordinates: true,
+            highlight_last_move: true,
+            highlights: Vec::new(),
+            arrows: Vec::new(),
+        }
+    }
+}
+
+/// Row and column of a piece in `PieceAtlas.png`: pawn, knight, bishop on the
+/// first row, rook, queen, king on the second, then the same for Black.
+pub fn atlas_cell(piece: Piece, colour: Colour) -> (u32, u32) {
+    let (row, col) = match piece {
+        Piece::Pawn => (0, 0),
+        Piece::Knight => (0, 1),
+        Piece::Bishop => (0, 2),
+        Piece::Rook => (1, 0),
+        Piece::Queen => (1, 1),
+        Piece::King => (1, 2),
+    };
+    if colour.white() { (row, col) } else { (row + 2, col) }
+}
+
+/// Every piece on the board with its square.
+pub fn pieces(board: &Board) -> Vec<(Tile, Piece, Colour)> {
+    let mut out = Vec::new();
+    for (colour, player) in [(Colour::White, &board.white), (Colour::Black, &board.black)] {
+        for (i, bb) in player.bb.iter().enumerate() {
+            let piece = Piece::from_index(i);
+            out.extend(bb.iter().map(|t| (t, piece, colour)));
+        }
+    }
+    out
+}
+
+/// Column and row of `tile` counted from the diagram's top-left corner.
+pub fn grid_position(tile: Tile, flipped: bool) -> (u32, u32) {
+    let (x, y) = tile.get_coords();
+    let (x, y) = (x as u32, y as u32);
+    if flipped { (7 - x, y) } else { (x, 7 - y) }
+}
+
+pub fn is_light(tile: Tile) -> bool {
+    let (x, y) = tile.get_coords();
+    (x + y) % 2 == 1
+}
+
+/// Squares drawn highlighted: the last move's and the extra ones.
+pub fn highlighted(board: &Board, options: &DiagramOptions) -> Vec<Tile> {
+    let mut tiles = options.highlights.clone();
+    if let Some(h) = board.history.last().filter(|_| options.highlight_last_move) {
+        tiles.push(h.last_move.from());
+        tiles.push(h.last_move.to());
+    }
+    tiles
+}
+
+/// The king's square when the side to move is in check.
+pub fn checked_king(board: &Board) -> Option<Tile> {
+    let player = if board.turn.white() { &board.white } else { &board.black };
+    board.is_in_check(board.turn).then(|| player.king_tile())
+}
+
+/// The outline of an arrow between two square centres, given in squares.
+/// Seven points: along one side of the shaft, round the head, back down the other.
+pub fn arrow_outline(from: (f32, f32), to: (f32, f32)) -> Vec<(f32, f32)> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt().max(0.01);
+    let (ux, uy) = (dx / length, dy / length);
+    let (nx, ny) = (-uy, ux);
+
+    let shaft = 0.08;
+    let head_width = 0.24;
+    let head_length = 0.4_f32.min(length);
+    let base = (to.0 - ux * head_length, to.1 - uy * head_length);
+    let side = |p: (f32, f32), w: f32| (p.0 + nx * w, p.1 + ny * w);
+    vec![
+        side(from, shaft),
+        side(base, shaft),
+        side(base, head_width),
+        to,
+        side(base, -head_width),
+        side(base, -shaft),
+        side(from, -shaft),
+    ]
+}
+
+/// Centre of `tile` in squares from the diagram's top-left corner.
+pub fn square_centre(tile: Tile, flipped: bool) -> (f32, f32) {
+    let (col, row) = grid_position(tile, flipped);
+    (col as f32 + 0.5, row as f32 + 0.5)
+}
+
+/// Parses arrows written as square pairs: `e2e4 g1f3`.
+pub fn parse_arrows(text: &str) -> Result<Vec<(Tile, Tile)>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let from = s.get(..2).and_then(notation::parse_tile);
+            let to = s.get(2..).and_then(notation::parse_tile);
+            from.zip(to).ok_or(format!("'{}' is not an arrow like e2e4", s))
+        })
+        .collect()
+}
+
+/// Parses a list of squares: `e4 d5`.
+pub fn parse_squares(text: &str) -> Result<Vec<Tile>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| notation::parse_tile(s).ok_or(format!("'{}' is not a square", s)))
+        .collect()
+}
+
+/// The game's positions from the start, one per ply, for animations.
+pub fn game_positions(board: &Board) -> Vec<Board> {
+    let mut start = board.clone();
+    for _ in 0..board.history.len() {
+        start.undo_move();
+    }
+    let mut positions = vec![start.clone()];
+    for h in &board.history {
+        start.make_move_unchecked(h.last_move);
+        positions.push(start.clone());
+    }
+    positions
+}
@@ -0,0 +1,231 @@
+use std::io::Cursor;
+
+use chess_lib::{Board, Colour, Piece, Tile};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    Delay, Frame, ImageFormat, Rgba, RgbaImage,
+};
+
+use super::{
+    arrow_outline, atlas_cell, checked_king, grid_position, highlighted, is_light, pieces,
+    square_centre, DiagramOptions, ARROW, CHECK_SQUARE, DARK_SQUARE, HIGHLIGHT, LIGHT_SQUARE,
+};
+
+const ATLAS: &[u8] = include_bytes!("../../assets/PieceAtlas.png");
+
+/// 3x5 pixel glyphs for the coordinates, one row per entry, high bit on the left.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        'a' => [0b000, 0b011, 0b101, 0b101, 0b011],
+        'b' => [0b100, 0b110, 0b101, 0b101, 0b110],
+        'c' => [0b000, 0b011, 0b100, 0b100, 0b011],
+        'd' => [0b001, 0b011, 0b101, 0b101, 0b011],
+        'e' => [0b000, 0b010, 0b111, 0b100, 0b011],
+        'f' => [0b001, 0b010, 0b111, 0b010, 0b010],
+        'g' => [0b011, 0b101, 0b011, 0b001, 0b110],
+        'h' => [0b100, 0b110, 0b101, 0b101, 0b101],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b110, 0b101, 0b010],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b010, 0b101, 0b010, 0b101, 0b010],
+        _ => [0; 5],
+    }
+}
+
+/// Piece sprites cut from the atlas and scaled to one square, reused across frames.
+struct Sprites
+{
+    images: Vec<((Piece, Colour), RgbaImage)>,
+}
+impl Sprites
+{
+    fn new(square: u32) -> Result<Self, String> {
+        let atlas = image::load_from_memory(ATLAS).map_err(|e| format!("Can't read the piece atlas: {}", e))?;
+        let atlas = atlas.to_rgba8();
+        let cell = atlas.width() / 3;
+        let mut images = Vec::new();
+        for colour in [Colour::White, Colour::Black] {
+            for piece in Piece::ALL_PIECES {
+                let (row, col) = atlas_cell(piece, colour);
+                let sprite = imageops::crop_imm(&atlas, col * cell, row * cell, cell, cell).to_image();
+                images.push(((piece, colour), imageops::resize(&sprite, square, square, FilterType::Triangle)));
+            }
+        }
+        Ok(Self { images })
+    }
+    fn get(&self, piece: Piece, colour: Colour) -> Option<&RgbaImage> {
+        self.images.iter().find(|(k, _)| *k == (piece, colour)).map(|(_, image)| image)
+    }
+}
+
+fn blend(pixel: &mut Rgba<u8>, colour: [u8; 4]) {
+    let a = colour[3] as f32 / 255.0;
+    for i in 0..3 {
+        pixel[i] = (pixel[i] as f32 * (1.0 - a) + colour[i] as f32 * a).round() as u8;
+    }
+}
+
+fn fill_square(image: &mut RgbaImage, tile: Tile, options: &DiagramOptions, square: u32, colour: [u8; 4]) {
+    let (col, row) = grid_position(tile, options.flipped);
+    for y in row * square..(row + 1) * square {
+        for x in col * square..(col + 1) * square {
+            blend(image.get_pixel_mut(x, y), colour);
+        }
+    }
+}
+
+/// Even-odd test against a closed outline.
+fn inside(point: (f32, f32), outline: &[(f32, f32)]) -> bool {
+    let mut inside = false;
+    let mut j = outline.len() - 1;
+    for i in 0..outline.len() {
+        let (a, b) = (outline[i], outline[j]);
+        if (a.1 > point.1) != (b.1 > point.1) && point.0 < (b.0 - a.0) * (point.1 - a.1) / (b.1 - a.1) + a.0 {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn fill_outline(image: &mut RgbaImage, outline: &[(f32, f32)], colour: [u8; 4]) {
+    let (w, h) = (image.width() as f32, image.height() as f32);
+    let min_x = outline.iter().map(|p| p.0).fold(w, f32::min).max(0.0) as u32;
+    let max_x = outline.iter().map(|p| p.0).fold(0.0, f32::max).min(w - 1.0) as u32;
+    let min_y = outline.iter().map(|p| p.1).fold(h, f32::min).max(0.0) as u32;
+    let max_y = outline.iter().map(|p| p.1).fold(0.0, f32::max).min(h - 1.0) as u32;
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            if inside((x as f32 + 0.5, y as f32 + 0.5), outline) {
+                blend(image.get_pixel_mut(x, y), colour);
+            }
+        }
+    }
+}
+
+fn draw_text(image: &mut RgbaImage, text: &str, x: u32, y: u32, scale: u32, colour: [u8; 3]) {
+    let ink = [colour[0], colour[1], colour[2], 255];
+    for (n, c) in text.chars().enumerate() {
+        let left = x + n as u32 * 4 * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits >> (2 - col) & 1 == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (left + col * scale + dx, y + row as u32 * scale + dy);
+                        if px < image.width() && py < image.height() {
+                            blend(image.get_pixel_mut(px, py), ink);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn draw(board: &Board, options: &DiagramOptions, sprites: &Sprites) -> RgbaImage {
+    let square = (options.size / 8).max(8);
+    let size = square * 8;
+    let mut image = RgbaImage::from_pixel(size, size, Rgba([0, 0, 0, 255]));
+
+    let check = checked_king(board);
+    let marked = highlighted(board, options);
+    for rank in 0..8 {
+        for file in 0..8 {
+            let Some(tile) = Tile::new_xy(file, rank) else {
+                continue;
+            };
+            let rgb = if check == Some(tile) {
+                CHECK_SQUARE
+            } else if is_light(tile) {
+                LIGHT_SQUARE
+            } else {
+                DARK_SQUARE
+            };
+            fill_square(&mut image, tile, options, square, [rgb[0], rgb[1], rgb[2], 255]);
+            if marked.contains(&tile) {
+                fill_square(&mut image, tile, options, square, HIGHLIGHT);
+            }
+        }
+    }
+
+    if options.coordinates {
+        let scale = (square / 30).max(1);
+        let margin = scale * 2;
+        for i in 0..8u8 {
+            let file = if options.flipped { 7 - i } else { i };
+            let rank = if options.flipped { i } else { 7 - i };
+            let ink = |tile: Option<Tile>| if tile.is_some_and(is_light) { DARK_SQUARE } else { LIGHT_SQUARE };
+
+            // File letters in the bottom-right corner of the bottom row
+            let bottom = Tile::new_xy(file, if options.flipped { 7 } else { 0 });
+            let x = (i as u32 + 1) * square - margin - 3 * scale;
+            draw_text(&mut image, &((b'a' + file) as char).to_string(), x, size - margin - 5 * scale, scale, ink(bottom));
+
+            // Rank numbers in the top-left corner of the left column
+            let left = Tile::new_xy(if options.flipped { 7 } else { 0 }, rank);
+            draw_text(&mut image, &(rank + 1).to_string(), margin, i as u32 * square + margin, scale, ink(left));
+        }
+    }
+
+    for (tile, piece, colour) in pieces(board) {
+        let (col, row) = grid_position(tile, options.flipped);
+        if let Some(sprite) = sprites.get(piece, colour) {
+            imageops::overlay(&mut image, sprite, (col * square) as i64, (row * square) as i64);
+        }
+    }
+
+    for &(from, to) in &options.arrows {
+        let outline = arrow_outline(square_centre(from, options.flipped), square_centre(to, options.flipped));
+        let scaled: Vec<(f32, f32)> = outline.iter().map(|(x, y)| (x * square as f32, y * square as f32)).collect();
+        fill_outline(&mut image, &scaled, ARROW);
+    }
+    image
+}
+
+/// The position drawn at `options.size`, rounded down to whole squares.
+pub fn board_image(board: &Board, options: &DiagramOptions) -> Result<RgbaImage, String> {
+    let sprites = Sprites::new((options.size / 8).max(8))?;
+    Ok(draw(board, options, &sprites))
+}
+
+pub fn board_png(board: &Board, options: &DiagramOptions) -> Result<Vec<u8>, String> {
+    let image = board_image(board, options)?;
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).map_err(|e| format!("Can't encode PNG: {}", e))?;
+    Ok(bytes.into_inner())
+}
+
+/// The game's `positions` as a looping GIF, `delay_ms` per ply. The
+/// options' own arrows are left out since they belong to one position.
+pub fn game_gif(positions: &[Board], options: &DiagramOptions, delay_ms: u32) -> Result<Vec<u8>, String> {
+    let sprites = Sprites::new((options.size / 8).max(8))?;
+    let frame_options = DiagramOptions { arrows: Vec::new(), ..options.clone() };
+    let error = |e: image::ImageError| format!("Can't encode GIF: {}", e);
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+        encoder.set_repeat(Repeat::Infinite).map_err(error)?;
+        let last = positions.len().saturating_sub(1);
+        for (i, position) in positions.iter().enumerate() {
+            // Linger on the final position before looping
+            let delay = if i == last { delay_ms * 3 } else { delay_ms };
+            let frame = Frame::from_parts(
+                draw(position, &frame_options, &sprites),
+                0,
+                0,
+                Delay::from_numer_denom_ms(delay, 1),
+            );
+            encoder.encode_frame(frame).map_err(error)?;
+        }
+    }
+    Ok(bytes)
+}
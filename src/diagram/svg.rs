@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use chess_lib::{Board, Colour, Piece};
+
+use super::{
+    arrow_outline, checked_king, grid_position, highlighted, is_light, pieces, square_centre, DiagramOptions, ARROW,
+    CHECK_SQUARE, DARK_SQUARE, HIGHLIGHT, LIGHT_SQUARE,
+};
+use crate::base64;
+
+fn piece_svg(piece: Piece, colour: Colour) -> &'static str {
+    match (piece, colour) {
+        (Piece::Pawn, Colour::White) => include_str!("../../assets/wP.svg"),
+        (Piece::Knight, Colour::White) => include_str!("../../assets/wN.svg"),
+        (Piece::Bishop, Colour::White) => include_str!("../../assets/wB.svg"),
+        (Piece::Rook, Colour::White) => include_str!("../../assets/wR.svg"),
+        (Piece::Queen, Colour::White) => include_str!("../../assets/wQ.svg"),
+        (Piece::King, Colour::White) => include_str!("../../assets/wK.svg"),
+        (Piece::Pawn, Colour::Black) => include_str!("../../assets/bP.svg"),
+        (Piece::Knight, Colour::Black) => include_str!("../../assets/bN.svg"),
+        (Piece::Bishop, Colour::Black) => include_str!("../../assets/bB.svg"),
+        (Piece::Rook, Colour::Black) => include_str!("../../assets/bR.svg"),
+        (Piece::Queen, Colour::Black) => include_str!("../../assets/bQ.svg"),
+        (Piece::King, Colour::Black) => include_str!("../../assets/bK.svg"),
+    }
+}
+
+fn hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+fn opacity(rgba: [u8; 4]) -> f32 {
+    rgba[3] as f32 / 255.0
+}
+
+/// The position as a standalone SVG document. Pieces are embedded as data URIs
+/// so their gradient ids can't clash.
+pub fn board_svg(board: &Board, options: &DiagramOptions) -> String {
+    let size = options.size as f32;
+    let sq = size / 8.0;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#
+    );
+
+    let check = checked_king(board);
+    let marked = highlighted(board, options);
+    for rank in 0..8 {
+        for file in 0..8 {
+            let Some(tile) = chess_lib::Tile::new_xy(file, rank) else {
+                continue;
+            };
+            let (col, row) = grid_position(tile, options.flipped);
+            let (x, y) = (col as f32 * sq, row as f32 * sq);
+            let colour = if check == Some(tile) {
+                CHECK_SQUARE
+            } else if is_light(tile) {
+                LIGHT_SQUARE
+            } else {
+                DARK_SQUARE
+            };
+            let _ = writeln!(out, r#"<rect x="{x}" y="{y}" width="{sq}" height="{sq}" fill="{}"/>"#, hex(colour));
+            if marked.contains(&tile) {
+                let rgb = [HIGHLIGHT[0], HIGHLIGHT[1], HIGHLIGHT[2]];
+                let _ = writeln!(
+                    out,
+                    r#"<rect x="{x}" y="{y}" width="{sq}" height="{sq}" fill="{}" fill-opacity="{:.2}"/>"#,
+                    hex(rgb),
+                    opacity(HIGHLIGHT)
+                );
+            }
+        }
+    }
+
+    if options.coordinates {
+        let font = sq * 0.2;
+        for i in 0..8u8 {
+            // Files along the bottom edge, ranks up the left edge
+            let file = if options.flipped { 7 - i } else { i };
+            let rank = if options.flipped { i } else { 7 - i };
+            let file_on_light = (file + if options.flipped { 7 } else { 0 }) % 2 == 1;
+            let rank_on_light = (rank + if options.flipped { 7 } else { 0 }) % 2 == 1;
+            let ink = |light: bool| hex(if light { DARK_SQUARE } else { LIGHT_SQUARE });
+            let _ = writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-weight="bold" font-size="{font:.1}" fill="{}" text-anchor="end">{}</text>"#,
+                (i as f32 + 1.0) * sq - font * 0.25,
+                size - font * 0.3,
+                ink(file_on_light),
+                (b'a' + file) as char
+            );
+            let _ = writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-weight="bold" font-size="{font:.1}" fill="{}">{}</text>"#,
+                font * 0.25,
+                i as f32 * sq + font,
+                ink(rank_on_light),
+                rank + 1
+            );
+        }
+    }
+
+    for (tile, piece, colour) in pieces(board) {
+        let (col, row) = grid_position(tile, options.flipped);
+        let _ = writeln!(
+            out,
+            r#"<image x="{}" y="{}" width="{sq}" height="{sq}" href="data:image/svg+xml;base64,{}"/>"#,
+            col as f32 * sq,
+            row as f32 * sq,
+            base64::encode(piece_svg(piece, colour).as_bytes())
+        );
+    }
+
+    let rgb = [ARROW[0], ARROW[1], ARROW[2]];
+    for &(from, to) in &options.arrows {
+        let outline = arrow_outline(square_centre(from, options.flipped), square_centre(to, options.flipped));
+        let points: Vec<String> = outline.iter().map(|(x, y)| format!("{:.1},{:.1}", x * sq, y * sq)).collect();
+        let _ = writeln!(
+            out,
+            r#"<polygon points="{}" fill="{}" fill-opacity="{:.2}"/>"#,
+            points.join(" "),
+            hex(rgb),
+            opacity(ARROW)
+        );
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use chess_lib::{Board, Colour, GameState};
+use egui::{Context, TextureHandle};
+
+use crate::{
+    notation,
+    play::{state::Engine, PlayTab},
+    storage,
+};
+
+pub mod catalog;
+mod render;
+use catalog::{bundled, Drill, Goal};
+
+const RESULTS_FILE: &str = "drills.txt";
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum DrillStatus
+{
+    Idle,
+    Running,
+    Passed,
+    Failed(String),
+}
+
+pub struct DrillTab
+{
+    pub play: PlayTab,
+    pub drills: Vec<Drill>,
+    pub current: Option<usize>,
+    pub status: DrillStatus,
+    player: Colour,
+    /// Passed and failed attempts by drill key.
+    pub results: HashMap<String, (u32, u32)>,
+
+    pub custom_fen: String,
+    pub custom_goal: Goal,
+    pub custom_limit: u32,
+    pub message: Option<String>,
+}
+
+impl DrillTab
+{
+    pub fn new(atlas: TextureHandle) -> Self {
+        let mut play = PlayTab::new(None, atlas);
+        play.embedded = true;
+        play.input_locked = true;
+
+        Self {
+            play,
+            drills: bundled(),
+            current: None,
+            status: DrillStatus::Idle,
+            player: Colour::White,
+            results: load_results(),
+
+            custom_fen: String::new(),
+            custom_goal: Goal::Win,
+            custom_limit: 50,
+            message: None,
+        }
+    }
+    /// Adds a drill for `fen`, e.g. from the Position Creator, and starts it.
+    pub fn add_custom(&mut self, fen: &str, goal: Goal, move_limit: u32) {
+        let drill = Drill::custom(fen, goal, move_limit);
+        let idx = match self.drills.iter().position(|d| d.key() == drill.key()) {
+            Some(idx) => {
+                self.drills[idx] = drill;
+                idx
+            }
+            None => {
+                self.drills.push(drill);
+                self.drills.len() - 1
+            }
+        };
+        self.start(idx);
+    }
+    pub fn drill(&self) -> Option<&Drill> {
+        self.current.and_then(|i| self.drills.get(i))
+    }
+    pub fn start(&mut self, idx: usize) {
+        let Some(drill) = self.drills.get(idx) else {
+            return;
+        };
+        let board = match drill_position(drill) {
+            Ok(b) => b,
+            Err(e) => {
+                self.message = Some(e);
+                return;
+            }
+        };
+        self.current = Some(idx);
+        self.player = board.turn;
+        self.play.set_position(board);
+        self.play.flipped = self.player == Colour::Black;
+        self.play.engine_plays = if self.player.white() { Engine::Black } else { Engine::White };
+        self.play.input_locked = false;
+        self.status = DrillStatus::Running;
+        self.message = None;
+    }
+    pub fn give_up(&mut self) {
+        if self.status == DrillStatus::Running {
+            self.finish(Err("Gave up".to_string()));
+        }
+    }
+    /// Leaves the running drill without counting it as passed or failed.
+    pub fn abandon(&mut self) {
+        self.play.engine_plays = Engine::Neither;
+        self.play.input_locked = true;
+        self.status = DrillStatus::Idle;
+    }
+    /// Moves the player has made since the drill started. The player always moves first.
+    pub fn player_moves(&self) -> u32 {
+        (self.play.board.history.len() as u32).div_ceil(2)
+    }
+
+    fn finish(&mut self, result: Result<(), String>) {
+        self.play.engine_plays = Engine::Neither;
+        self.play.input_locked = true;
+        let Some(key) = self.drill().map(|d| d.key()) else {
+            return;
+        };
+        let entry = self.results.entry(key).or_default();
+        match result {
+            Ok(()) => {
+                entry.0 += 1;
+                self.status = DrillStatus::Passed;
+            }
+            Err(reason) => {
+                entry.1 += 1;
+                self.status = DrillStatus::Failed(reason);
+            }
+        }
+        save_results(&self.results);
+    }
+    /// Checks the goal against the game state and the move counter.
+    fn check_goal(&mut self) {
+        if self.status != DrillStatus::Running {
+            return;
+        }
+        let Some(drill) = self.drill() else {
+            return;
+        };
+        let (goal, limit) = (drill.goal, drill.move_limit);
+        let moves = self.player_moves();
+        let players_turn = self.play.board.turn == self.player;
+
+        let result = match self.play.board.get_state() {
+            GameState::Checkmate(loser) if loser == self.player => Err("You were checkmated".to_string()),
+            GameState::Checkmate(_) => match goal {
+                Goal::MateIn(n) if moves > n => Err(format!("Mate took {} moves", moves)),
+                _ => Ok(()),
+            },
+            GameState::Playing => {
+                let limit = match goal {
+                    Goal::MateIn(n) => n,
+                    _ => limit,
+                };
+                match goal {
+                    Goal::Draw if moves >= limit && players_turn => Ok(()),
+                    Goal::Win | Goal::MateIn(_) if moves >= limit && !players_turn => {
+                        Err(format!("No mate within {} moves", limit))
+                    }
+                    _ => return,
+                }
+            }
+            state => match goal {
+                Goal::Draw => Ok(()),
+                _ => Err(format!("Drawn: {}", crate::statistics::record::termination_label(state))),
+            },
+        };
+        self.finish(result);
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        egui::SidePanel::right("drill_panel")
+            .resizable(false)
+            .min_width(260.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("scroll_drill")
+                    .show(ui, |ui| {
+                        self.render_panel(ui);
+                    });
+            });
+        if self.status == DrillStatus::Running {
+            self.play.run_engine(ctx);
+        }
+        self.play.render_board(ctx);
+        self.check_goal();
+    }
+}
+
+/// The drill's starting board, with any opening moves played.
+fn drill_position(drill: &Drill) -> Result<Board, String> {
+    let mut board = match &drill.fen {
+        Some(fen) => Board::new_from_fen(fen)?,
+        None => Board::new(),
+    };
+    for san in &drill.moves {
+        let m = notation::parse_move(&board, san).ok_or(format!("Illegal move {} in drill {}", san, drill.name))?;
+        board.make_move_unchecked(m);
+    }
+    // Start the drill with a clean history so only the player's moves are counted
+    Board::new_from_fen(&board.to_fen())
+}
+
+fn load_results() -> HashMap<String, (u32, u32)> {
+    let Some(text) = storage::load(RESULTS_FILE) else {
+        return HashMap::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let (key, counts) = line.rsplit_once('=')?;
+            let (passed, failed) = counts.split_once(',')?;
+            Some((key.to_string(), (passed.parse().ok()?, failed.parse().ok()?)))
+        })
+        .collect()
+}
+
+fn save_results(results: &HashMap<String, (u32, u32)>) {
+    let lines: Vec<String> = results.iter().map(|(k, (p, f))| format!("{}={},{}", k, p, f)).collect();
+    storage::save(RESULTS_FILE, &lines.join("\n"));
+}
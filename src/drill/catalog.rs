@@ -0,0 +1,76 @@
+/// What the player has to achieve in a drill.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Goal
+{
+    /// Checkmate the engine within the move limit.
+    Win,
+    /// Don't lose: reach a draw or survive the move limit.
+    Draw,
+    /// Checkmate within this many moves.
+    MateIn(u32),
+}
+impl Goal
+{
+    pub fn label(&self) -> String {
+        match self {
+            Goal::Win => "Win".to_string(),
+            Goal::Draw => "Draw".to_string(),
+            Goal::MateIn(n) => format!("Mate in {}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Drill
+{
+    pub name: String,
+    pub category: String,
+    /// Starting position, the standard one when `None`.
+    pub fen: Option<String>,
+    /// SAN moves played from `fen` to reach the drill position, used for openings.
+    pub moves: Vec<String>,
+    pub goal: Goal,
+    /// Player moves allowed for `Win`, moves to hold for `Draw`.
+    pub move_limit: u32,
+}
+impl Drill
+{
+    fn new(category: &str, name: &str, fen: Option<&str>, moves: &str, goal: Goal, move_limit: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            category: category.to_string(),
+            fen: fen.map(|f| f.to_string()),
+            moves: moves.split_whitespace().map(|m| m.to_string()).collect(),
+            goal,
+            move_limit,
+        }
+    }
+    /// A drill made from any position, the player taking the side to move.
+    pub fn custom(fen: &str, goal: Goal, move_limit: u32) -> Self {
+        Self::new("Custom", fen, Some(fen), "", goal, move_limit)
+    }
+    /// Key used for the pass/fail record.
+    pub fn key(&self) -> String {
+        if self.category == "Custom" {
+            format!("custom:{}", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// The drills that ship with the app. The player takes the side to move.
+pub fn bundled() -> Vec<Drill> {
+    vec![
+        Drill::new("Endgame", "KQ vs K", Some("8/8/8/4k3/8/8/8/4K2Q w - - 0 1"), "", Goal::MateIn(10), 10),
+        Drill::new("Endgame", "KR vs K", Some("8/8/8/4k3/8/8/8/R3K3 w - - 0 1"), "", Goal::MateIn(16), 16),
+        Drill::new("Endgame", "Two bishops", Some("8/8/8/4k3/8/8/8/2B1KB2 w - - 0 1"), "", Goal::MateIn(19), 19),
+        Drill::new("Endgame", "Bishop and knight", Some("8/8/8/4k3/8/8/8/1N2KB2 w - - 0 1"), "", Goal::MateIn(33), 33),
+        Drill::new("Endgame", "King and pawn on the sixth", Some("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), "", Goal::Win, 20),
+        Drill::new("Endgame", "Lucena position", Some("1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1"), "", Goal::Win, 40),
+        Drill::new("Endgame", "Philidor position", Some("4k3/R7/1r6/4PK2/8/8/8/8 b - - 0 1"), "", Goal::Draw, 30),
+        Drill::new("Opening", "Fried Liver, defend as Black", None, "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Nxd5 Nxf7 Kxf7 Qf3+ Ke6 Nc3", Goal::Draw, 20),
+        Drill::new("Opening", "Legal's trap, punish 4...g6", None, "e4 e5 Nf3 d6 Bc4 Bg4 Nc3 g6", Goal::Win, 40),
+        Drill::new("Opening", "Englund Gambit trap as Black", None, "d4 e5 dxe5 Nc6 Nf3 Qe7 Bf4", Goal::Win, 40),
+    ]
+}
@@ -0,0 +1,130 @@
+use egui::{Color32, ComboBox, DragValue, RichText, Slider, Ui};
+
+use crate::drill::{catalog::Goal, DrillStatus, DrillTab};
+
+impl DrillTab
+{
+    pub fn render_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Drills");
+        ui.add_space(8.0);
+
+        self.render_current(ui);
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Engine depth");
+            ui.add(Slider::new(&mut self.play.engine_depth, 1..=8));
+        });
+        ui.separator();
+
+        self.render_list(ui);
+        ui.separator();
+
+        self.render_custom(ui);
+        if let Some(message) = &self.message {
+            ui.label(RichText::new(message).color(Color32::from_rgb(200, 80, 80)));
+        }
+    }
+
+    fn render_current(&mut self, ui: &mut Ui) {
+        let Some(drill) = self.drill() else {
+            ui.label("Pick a drill below");
+            return;
+        };
+        ui.label(RichText::new(&drill.name).strong());
+        let limit = match drill.goal {
+            Goal::MateIn(n) => n,
+            _ => drill.move_limit,
+        };
+        let goal = match drill.goal {
+            Goal::Draw => format!("Hold the draw for {} moves", limit),
+            Goal::Win => format!("Win within {} moves", limit),
+            Goal::MateIn(n) => format!("Mate in {}", n),
+        };
+        ui.label(goal);
+        ui.label(format!("Move {} / {}", self.player_moves(), limit));
+
+        match &self.status {
+            DrillStatus::Passed => {
+                ui.label(RichText::new("Passed!").color(Color32::from_rgb(90, 170, 90)));
+            }
+            DrillStatus::Failed(reason) => {
+                ui.label(RichText::new(format!("Failed: {}", reason)).color(Color32::from_rgb(200, 80, 80)));
+            }
+            DrillStatus::Running | DrillStatus::Idle => (),
+        }
+        ui.horizontal(|ui| {
+            let restart = ui.button("Restart").clicked();
+            if let Some(idx) = self.current.filter(|_| restart) {
+                // Leaving a drill half way counts as a failed attempt
+                self.give_up();
+                self.start(idx);
+            }
+            if ui.add_enabled(self.status == DrillStatus::Running, egui::Button::new("Give up")).clicked() {
+                self.give_up();
+            }
+        });
+    }
+
+    fn render_list(&mut self, ui: &mut Ui) {
+        let mut start = None;
+        let mut category = "";
+        egui::Grid::new("drill_list").striped(true).show(ui, |ui| {
+            for (i, drill) in self.drills.iter().enumerate() {
+                if drill.category != category {
+                    category = &drill.category;
+                    ui.label(RichText::new(category).strong());
+                    ui.end_row();
+                }
+                let (passed, failed) = self.results.get(&drill.key()).copied().unwrap_or_default();
+                let selected = self.current == Some(i);
+                let name = if drill.category == "Custom" { "Custom position" } else { drill.name.as_str() };
+                if ui.selectable_label(selected, name).on_hover_text(&drill.name).clicked() {
+                    start = Some(i);
+                }
+                ui.label(drill.goal.label());
+                ui.label(
+                    RichText::new(format!("{} passed, {} failed", passed, failed))
+                        .weak(),
+                );
+                ui.end_row();
+            }
+        });
+        if let Some(idx) = start {
+            self.give_up();
+            self.start(idx);
+        }
+    }
+
+    fn render_custom(&mut self, ui: &mut Ui) {
+        ui.label(RichText::new("Custom drill").strong());
+        ui.add(egui::TextEdit::singleline(&mut self.custom_fen).hint_text("FEN"));
+        ui.horizontal(|ui| {
+            let mate_in = match self.custom_goal {
+                Goal::MateIn(n) => n,
+                _ => 3,
+            };
+            ComboBox::from_id_salt("custom_drill_goal")
+                .selected_text(self.custom_goal.label())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.custom_goal, Goal::Win, "Win");
+                    ui.selectable_value(&mut self.custom_goal, Goal::Draw, "Draw");
+                    ui.selectable_value(&mut self.custom_goal, Goal::MateIn(mate_in), "Mate in N");
+                });
+            match &mut self.custom_goal {
+                Goal::MateIn(n) => {
+                    ui.add(DragValue::new(n).range(1..=50));
+                }
+                _ => {
+                    ui.label("Moves");
+                    ui.add(DragValue::new(&mut self.custom_limit).range(1..=200));
+                }
+            }
+        });
+        if ui.button("Start custom drill").clicked() {
+            let fen = self.custom_fen.trim().to_string();
+            self.give_up();
+            self.add_custom(&fen, self.custom_goal, self.custom_limit);
+        }
+    }
+}
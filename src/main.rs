@@ -1,6 +1,25 @@
+pub mod analysis;
 pub mod app;
+pub mod attacks;
+pub mod background;
+pub mod base64;
+pub mod chess960;
+pub mod clipboard;
+pub mod diagram;
+pub mod drill;
+pub mod net;
+pub mod notation;
+pub mod online;
+pub mod pgn;
 pub mod play;
 pub mod position_creator;
+pub mod puzzle;
+pub mod recognize;
+pub mod share;
+pub mod statistics;
+pub mod storage;
+pub mod training;
+pub mod watch;
 
 pub mod utils;
 use app::ChessApp;
@@ -0,0 +1,9 @@
+// Online play: the message protocol, the client connection used by the Online
+// tab, and the relay server that the `relay` binary and in-app hosting share.
+
+pub mod client;
+pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+#[cfg(not(target_arch = "wasm32"))]
+mod websocket;
@@ -0,0 +1,151 @@
+use crate::net::protocol::{ClientMsg, ServerMsg};
+
+pub enum NetEvent
+{
+    Message(ServerMsg),
+    Closed(String),
+}
+
+// Native builds speak the line protocol over plain TCP, reading on a background
+// thread so the UI never blocks on the network.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Connection
+{
+    stream: std::net::TcpStream,
+    events: std::sync::mpsc::Receiver<NetEvent>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Connection
+{
+    pub fn connect(host: &str, port: u16) -> Result<Self, String> {
+        use std::{
+            io::{BufRead, BufReader},
+            net::{TcpStream, ToSocketAddrs},
+            sync::mpsc,
+            thread,
+            time::Duration,
+        };
+
+        let addr = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Can't resolve {}: {}", host, e))?
+            .next()
+            .ok_or(format!("Can't resolve {}", host))?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(3))
+            .map_err(|e| format!("Can't connect to {}:{}: {}", host, port, e))?;
+        stream.set_nodelay(true).ok();
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+
+        let (tx, events) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        let _ = tx.send(NetEvent::Closed(e.to_string()));
+                        return;
+                    }
+                };
+                let Some(msg) = ServerMsg::parse(&line) else {
+                    continue;
+                };
+                if tx.send(NetEvent::Message(msg)).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(NetEvent::Closed("The server closed the connection".to_string()));
+        });
+        Ok(Self { stream, events })
+    }
+    /// Write errors surface as a `Closed` event from the reader.
+    pub fn send(&mut self, msg: &ClientMsg) {
+        use std::io::Write;
+        let _ = writeln!(self.stream, "{}", msg.encode());
+    }
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for Connection
+{
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+// The web build can only use WebSockets. Callbacks queue events for `poll`,
+// and messages sent before the socket opens wait in `pending`.
+#[cfg(target_arch = "wasm32")]
+pub struct Connection
+{
+    socket: web_sys::WebSocket,
+    events: std::rc::Rc<std::cell::RefCell<std::collections::VecDeque<NetEvent>>>,
+    pending: Vec<String>,
+    _on_message: web_sys::wasm_bindgen::closure::Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _on_close: web_sys::wasm_bindgen::closure::Closure<dyn FnMut(web_sys::wasm_bindgen::JsValue)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Connection
+{
+    pub fn connect(host: &str, port: u16) -> Result<Self, String> {
+        use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+        use web_sys::wasm_bindgen::{closure::Closure, JsCast, JsValue};
+        use web_sys::{MessageEvent, WebSocket};
+
+        let socket = WebSocket::new(&format!("ws://{}:{}/", host, port))
+            .map_err(|e| format!("Can't connect to {}:{}: {:?}", host, port, e))?;
+        let events = Rc::new(RefCell::new(VecDeque::new()));
+
+        let queue = events.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+            if let Some(text) = e.data().as_string() {
+                for line in text.lines() {
+                    if let Some(msg) = ServerMsg::parse(line) {
+                        queue.borrow_mut().push_back(NetEvent::Message(msg));
+                    }
+                }
+            }
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let queue = events.clone();
+        let on_close = Closure::<dyn FnMut(JsValue)>::new(move |_: JsValue| {
+            queue.borrow_mut().push_back(NetEvent::Closed("The connection closed".to_string()));
+        });
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Self { socket, events, pending: Vec::new(), _on_message: on_message, _on_close: on_close })
+    }
+    pub fn send(&mut self, msg: &ClientMsg) {
+        self.pending.push(msg.encode());
+        self.flush();
+    }
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        self.flush();
+        self.events.borrow_mut().drain(..).collect()
+    }
+    fn flush(&mut self) {
+        if self.socket.ready_state() != web_sys::WebSocket::OPEN {
+            return;
+        }
+        for line in self.pending.drain(..) {
+            if self.socket.send_with_str(&line).is_err() {
+                log::error!("Failed to send {}", line);
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for Connection
+{
+    fn drop(&mut self) {
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
@@ -0,0 +1,217 @@
+// Line-based messages between the GUI and the relay server. One message per
+// line (or per WebSocket text frame), fields separated by spaces, so names and
+// room ids have their whitespace replaced before sending.
+
+use chess_lib::Colour;
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ClientMsg
+{
+    /// Joins or creates `room`. `token` reclaims a seat after a dropped connection,
+    /// the clock settings only matter to whoever creates the room.
+    Hello { room: String, token: Option<String>, minutes: u32, increment: u32, name: String },
+    Move(String),
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    Resign,
+    /// Asks for the full move list again, e.g. after a move didn't apply.
+    Sync,
+    /// Follows `room` read-only: the server answers with `Start`, `Sync` and every
+    /// later move, but ignores anything else a spectator sends.
+    Watch { room: String },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ServerMsg
+{
+    Welcome { colour: Colour, token: String },
+    Start { white: String, black: String, increment_ms: i64 },
+    /// All moves so far in UCI with the clocks, sent after `Start` and on request.
+    Sync { white_ms: i64, black_ms: i64, moves: Vec<String> },
+    Move { uci: String, white_ms: i64, black_ms: i64 },
+    DrawOffered,
+    DrawDeclined,
+    /// `result` is a PGN result, `reason` a single word such as `checkmate` or `timeout`.
+    GameOver { result: String, reason: String },
+    OpponentLeft,
+    OpponentBack,
+    Error(String),
+}
+
+/// Makes `s` safe to send as a single field.
+pub fn field(s: &str) -> String {
+    let joined = s.split_whitespace().collect::<Vec<_>>().join("_");
+    if joined.is_empty() { "-".to_string() } else { joined }
+}
+
+fn colour_char(colour: Colour) -> &'static str {
+    if colour.white() { "w" } else { "b" }
+}
+
+impl ClientMsg
+{
+    pub fn encode(&self) -> String {
+        match self {
+            ClientMsg::Hello { room, token, minutes, increment, name } => format!(
+                "HELLO {} {} {} {} {}",
+                field(room),
+                token.as_deref().unwrap_or("-"),
+                minutes,
+                increment,
+                field(name)
+            ),
+            ClientMsg::Move(uci) => format!("MOVE {}", uci),
+            ClientMsg::OfferDraw => "DRAW_OFFER".to_string(),
+            ClientMsg::AcceptDraw => "DRAW_ACCEPT".to_string(),
+            ClientMsg::DeclineDraw => "DRAW_DECLINE".to_string(),
+            ClientMsg::Resign => "RESIGN".to_string(),
+            ClientMsg::Sync => "SYNC".to_string(),
+            ClientMsg::Watch { room } => format!("WATCH {}", field(room)),
+        }
+    }
+    pub fn parse(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        Some(match parts.as_slice() {
+            ["HELLO", room, token, minutes, increment, name] => ClientMsg::Hello {
+                room: room.to_string(),
+                token: (*token != "-").then(|| token.to_string()),
+                minutes: minutes.parse().ok()?,
+                increment: increment.parse().ok()?,
+                name: name.to_string(),
+            },
+            ["MOVE", uci] => ClientMsg::Move(uci.to_string()),
+            ["DRAW_OFFER"] => ClientMsg::OfferDraw,
+            ["DRAW_ACCEPT"] => ClientMsg::AcceptDraw,
+            ["DRAW_DECLINE"] => ClientMsg::DeclineDraw,
+            ["RESIGN"] => ClientMsg::Resign,
+            ["SYNC"] => ClientMsg::Sync,
+            ["WATCH", room] => ClientMsg::Watch { room: room.to_string() },
+            _ => return None,
+        })
+    }
+}
+
+impl ServerMsg
+{
+    pub fn encode(&self) -> String {
+        match self {
+            ServerMsg::Welcome { colour, token } => format!("WELCOME {} {}", colour_char(*colour), token),
+            ServerMsg::Start { white, black, increment_ms } => {
+                format!("START {} {} {}", field(white), field(black), increment_ms)
+            }
+            ServerMsg::Sync { white_ms, black_ms, moves } => {
+                let mut line = format!("SYNC {} {}", white_ms, black_ms);
+                for m in moves {
+                    line.push(' ');
+                    line.push_str(m);
+                }
+                line
+            }
+            ServerMsg::Move { uci, white_ms, black_ms } => format!("MOVE {} {} {}", uci, white_ms, black_ms),
+            ServerMsg::DrawOffered => "DRAW_OFFERED".to_string(),
+            ServerMsg::DrawDeclined => "DRAW_DECLINED".to_string(),
+            ServerMsg::GameOver { result, reason } => format!("GAMEOVER {} {}", result, reason),
+            ServerMsg::OpponentLeft => "OPPONENT_LEFT".to_string(),
+            ServerMsg::OpponentBack => "OPPONENT_BACK".to_string(),
+            ServerMsg::Error(text) => format!("ERROR {}", text),
+        }
+    }
+    pub fn parse(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        Some(match parts.as_slice() {
+            ["WELCOME", colour, token] => ServerMsg::Welcome {
+                colour: match *colour {
+                    "w" => Colour::White,
+                    "b" => Colour::Black,
+                    _ => return None,
+                },
+                token: token.to_string(),
+            },
+            ["START", white, black, increment] => ServerMsg::Start {
+                white: white.to_string(),
+                black: black.to_string(),
+                increment_ms: increment.parse().ok()?,
+            },
+            ["SYNC", white_ms, black_ms, moves @ ..] => ServerMsg::Sync {
+                white_ms: white_ms.parse().ok()?,
+                black_ms: black_ms.parse().ok()?,
+                moves: moves.iter().map(|m| m.to_string()).collect(),
+            },
+            ["MOVE", uci, white_ms, black_ms] => ServerMsg::Move {
+                uci: uci.to_string(),
+                white_ms: white_ms.parse().ok()?,
+                black_ms: black_ms.parse().ok()?,
+            },
+            ["DRAW_OFFERED"] => ServerMsg::DrawOffered,
+            ["DRAW_DECLINED"] => ServerMsg::DrawDeclined,
+            ["GAMEOVER", result, reason] => ServerMsg::GameOver { result: result.to_string(), reason: reason.to_string() },
+            ["OPPONENT_LEFT"] => ServerMsg::OpponentLeft,
+            ["OPPONENT_BACK"] => ServerMsg::OpponentBack,
+            ["ERROR", ..] => ServerMsg::Error(line.trim()["ERROR".len()..].trim().to_string()),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn client_messages_round_trip() {
+        let messages = [
+            ClientMsg::Hello { room: "lobby".to_string(), token: None, minutes: 5, increment: 3, name: "Ann".to_string() },
+            ClientMsg::Hello {
+                room: "r1".to_string(),
+                token: Some("abc".to_string()),
+                minutes: 0,
+                increment: 0,
+                name: "-".to_string(),
+            },
+            ClientMsg::Move("e7e8q".to_string()),
+            ClientMsg::OfferDraw,
+            ClientMsg::AcceptDraw,
+            ClientMsg::DeclineDraw,
+            ClientMsg::Resign,
+            ClientMsg::Sync,
+            ClientMsg::Watch { room: "lobby".to_string() },
+        ];
+        for msg in messages {
+            assert_eq!(ClientMsg::parse(&msg.encode()), Some(msg));
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let messages = [
+            ServerMsg::Welcome { colour: Colour::Black, token: "abc".to_string() },
+            ServerMsg::Start { white: "Ann".to_string(), black: "Bo".to_string(), increment_ms: 2000 },
+            ServerMsg::Sync { white_ms: 60_000, black_ms: 59_500, moves: Vec::new() },
+            ServerMsg::Sync { white_ms: 1, black_ms: 2, moves: vec!["e2e4".to_string(), "e7e5".to_string()] },
+            ServerMsg::Move { uci: "g1f3".to_string(), white_ms: 100, black_ms: 200 },
+            ServerMsg::DrawOffered,
+            ServerMsg::DrawDeclined,
+            ServerMsg::GameOver { result: "1/2-1/2".to_string(), reason: "agreement".to_string() },
+            ServerMsg::OpponentLeft,
+            ServerMsg::OpponentBack,
+            ServerMsg::Error("Room is full".to_string()),
+        ];
+        for msg in messages {
+            assert_eq!(ServerMsg::parse(&msg.encode()), Some(msg));
+        }
+    }
+
+    #[test]
+    fn names_with_spaces_become_one_field() {
+        let msg =
+            ClientMsg::Hello { room: "my room".to_string(), token: None, minutes: 5, increment: 0, name: "Ann Lee".to_string() };
+        let Some(ClientMsg::Hello { room, name, .. }) = ClientMsg::parse(&msg.encode()) else {
+            panic!("didn't parse {}", msg.encode());
+        };
+        assert_eq!((room.as_str(), name.as_str()), ("my_room", "Ann_Lee"));
+    }
+}
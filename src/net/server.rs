@@ -0,0 +1,440 @@
+// Relay server: pairs two players per room, validates their moves with
+// chess_lib and keeps the authoritative clocks. Any number of spectators can
+// follow a room and get the same updates as the players. Plain TCP clients send
+// newline-separated messages, browsers connect over WebSocket on the same port.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chess_lib::{Board, Colour, GameState};
+
+use super::{
+    protocol::{ClientMsg, ServerMsg},
+    websocket,
+};
+use crate::notation;
+
+type Rooms = Arc<Mutex<HashMap<String, Room>>>;
+
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(1);
+
+struct Seat
+{
+    name: String,
+    token: String,
+    /// Identifies the connection holding the seat, so a stale connection
+    /// closing late doesn't drop a player who has already reconnected.
+    connection: u64,
+    tx: Option<Sender<String>>,
+}
+
+struct Room
+{
+    board: Board,
+    moves: Vec<String>,
+    /// White's seat first.
+    seats: [Option<Seat>; 2],
+    /// Time left in milliseconds as of `last_tick`.
+    clocks: [i64; 2],
+    increment_ms: i64,
+    last_tick: Instant,
+    started: bool,
+    result: Option<(String, String)>,
+    /// Seat that has a draw offer standing.
+    draw_offer: Option<usize>,
+    /// Connection ids and outgoing channels of everyone watching.
+    spectators: Vec<(u64, Sender<String>)>,
+}
+
+impl Room
+{
+    fn new(minutes: u32, increment: u32) -> Self {
+        let ms = minutes.max(1) as i64 * 60_000;
+        Self {
+            board: Board::new(),
+            moves: Vec::new(),
+            seats: [None, None],
+            clocks: [ms, ms],
+            increment_ms: increment as i64 * 1000,
+            last_tick: Instant::now(),
+            started: false,
+            result: None,
+            draw_offer: None,
+            spectators: Vec::new(),
+        }
+    }
+    fn turn(&self) -> usize {
+        if self.board.turn.white() { 0 } else { 1 }
+    }
+    /// Clocks with the running side's thinking time taken off.
+    fn clocks_now(&self) -> [i64; 2] {
+        let mut clocks = self.clocks;
+        if self.started && self.result.is_none() {
+            clocks[self.turn()] -= self.last_tick.elapsed().as_millis() as i64;
+        }
+        clocks
+    }
+    fn send(&self, seat: usize, msg: &ServerMsg) {
+        if let Some(tx) = self.seats[seat].as_ref().and_then(|s| s.tx.as_ref()) {
+            let _ = tx.send(msg.encode());
+        }
+    }
+    fn broadcast(&self, msg: &ServerMsg) {
+        self.send(0, msg);
+        self.send(1, msg);
+        let line = msg.encode();
+        for (_, tx) in &self.spectators {
+            let _ = tx.send(line.clone());
+        }
+    }
+    fn sync_msg(&self) -> ServerMsg {
+        let [white_ms, black_ms] = self.clocks_now();
+        ServerMsg::Sync { white_ms, black_ms, moves: self.moves.clone() }
+    }
+    fn start_msg(&self) -> ServerMsg {
+        let name = |i: usize| self.seats[i].as_ref().map(|s| s.name.clone()).unwrap_or_default();
+        ServerMsg::Start { white: name(0), black: name(1), increment_ms: self.increment_ms }
+    }
+    fn finish(&mut self, result: &str, reason: &str) {
+        self.clocks = self.clocks_now();
+        self.result = Some((result.to_string(), reason.to_string()));
+        self.broadcast(&ServerMsg::GameOver { result: result.to_string(), reason: reason.to_string() });
+    }
+    fn win_for(&mut self, seat: usize, reason: &str) {
+        self.finish(if seat == 0 { "1-0" } else { "0-1" }, reason);
+    }
+    fn play(&mut self, seat: usize, uci: &str) -> Result<(), String> {
+        if !self.started || self.result.is_some() {
+            return Err("The game isn't running".to_string());
+        }
+        if seat != self.turn() {
+            return Err("Not your turn".to_string());
+        }
+        let clocks = self.clocks_now();
+        if clocks[seat] <= 0 {
+            self.win_for(1 - seat, "timeout");
+            return Ok(());
+        }
+        let m = notation::parse_move(&self.board, uci).ok_or(format!("Illegal move {}", uci))?;
+        self.board.make_move_unchecked(m);
+        self.moves.push(uci.to_string());
+        self.clocks = clocks;
+        self.clocks[seat] += self.increment_ms;
+        self.last_tick = Instant::now();
+        self.draw_offer = None;
+
+        let [white_ms, black_ms] = self.clocks;
+        self.broadcast(&ServerMsg::Move { uci: uci.to_string(), white_ms, black_ms });
+
+        match self.board.get_state() {
+            GameState::Playing => (),
+            GameState::Checkmate(loser) => self.win_for(if loser == Colour::White { 1 } else { 0 }, "checkmate"),
+            GameState::Stalemate(_) => self.finish("1/2-1/2", "stalemate"),
+            GameState::InsufficientMaterial => self.finish("1/2-1/2", "insufficient_material"),
+            GameState::FiftyMoveRule => self.finish("1/2-1/2", "fifty_moves"),
+            GameState::ThreeRepetition => self.finish("1/2-1/2", "repetition"),
+        }
+        Ok(())
+    }
+}
+
+/// Binds the relay's listening socket.
+pub fn listen(addr: impl ToSocketAddrs) -> io::Result<TcpListener> {
+    TcpListener::bind(addr)
+}
+
+/// Accepts connections until the listener fails. Blocks, so run it on its own thread
+/// when hosting from inside the app.
+pub fn serve(listener: TcpListener) {
+    let rooms: Rooms = Arc::new(Mutex::new(HashMap::new()));
+
+    let ticker_rooms = rooms.clone();
+    thread::spawn(move || check_flags(ticker_rooms));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let rooms = rooms.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, rooms) {
+                        eprintln!("Connection ended: {}", e);
+                    }
+                });
+            }
+            Err(e) => eprintln!("Failed to accept connection: {}", e),
+        }
+    }
+}
+
+/// Ends games where the side to move has run out of time.
+fn check_flags(rooms: Rooms) {
+    loop {
+        thread::sleep(Duration::from_millis(100));
+        let Ok(mut rooms) = rooms.lock() else {
+            return;
+        };
+        for room in rooms.values_mut() {
+            if room.started && room.result.is_none() {
+                let turn = room.turn();
+                if room.clocks_now()[turn] <= 0 {
+                    room.win_for(1 - turn, "timeout");
+                    room.clocks[turn] = 0;
+                }
+            }
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream, rooms: Rooms) -> io::Result<()> {
+    stream.set_nodelay(true).ok();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream.try_clone()?;
+
+    let mut first = String::new();
+    if reader.read_line(&mut first)? == 0 {
+        return Ok(());
+    }
+    let is_websocket = first.starts_with("GET ");
+    if is_websocket {
+        websocket::accept(&mut reader, &mut writer, first.trim())?;
+        first.clear();
+    }
+
+    // Everything going out goes through one writer thread
+    let (tx, rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for line in rx {
+            let sent = if is_websocket {
+                websocket::write_text(&mut writer, &line)
+            } else {
+                writeln!(writer, "{}", line)
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(std::net::Shutdown::Both);
+    });
+
+    let connection = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+    let mut seat: Option<(String, usize)> = None;
+    let mut watching: Option<String> = None;
+    let mut pending = Some(first).filter(|l| !l.trim().is_empty());
+
+    loop {
+        let line = match pending.take() {
+            Some(line) => line,
+            None if is_websocket => match websocket::read_text(&mut reader)? {
+                Some(text) => text,
+                None => break,
+            },
+            None => {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    break;
+                }
+                line
+            }
+        };
+        for line in line.lines() {
+            let Some(msg) = ClientMsg::parse(line) else {
+                let _ = tx.send(ServerMsg::Error(format!("Unknown message: {}", line.trim())).encode());
+                continue;
+            };
+            let Ok(mut rooms) = rooms.lock() else {
+                return Ok(());
+            };
+            if let Some(room_id) = &watching {
+                match (rooms.get(room_id), msg) {
+                    (Some(room), ClientMsg::Sync) => {
+                        let _ = tx.send(room.sync_msg().encode());
+                    }
+                    (None, _) => {
+                        let _ = tx.send(ServerMsg::Error("The room has closed".to_string()).encode());
+                    }
+                    _ => {
+                        let _ = tx.send(ServerMsg::Error("Spectators can't play".to_string()).encode());
+                    }
+                }
+                continue;
+            }
+            match (&seat, msg) {
+                (None, ClientMsg::Watch { room }) => match rooms.get_mut(&room) {
+                    Some(r) => {
+                        watch(r, connection, &tx);
+                        watching = Some(room);
+                    }
+                    None => {
+                        let _ = tx.send(ServerMsg::Error(format!("No room called {}", room)).encode());
+                        return Ok(());
+                    }
+                },
+                (None, ClientMsg::Hello { room, token, minutes, increment, name }) => {
+                    match join(&mut rooms, &room, token, minutes, increment, name, connection, &tx) {
+                        Ok(index) => seat = Some((room, index)),
+                        Err(e) => {
+                            let _ = tx.send(ServerMsg::Error(e).encode());
+                            return Ok(());
+                        }
+                    }
+                }
+                (None, _) => {
+                    let _ = tx.send(ServerMsg::Error("Say HELLO first".to_string()).encode());
+                }
+                (Some((room_id, index)), msg) => {
+                    if let Some(room) = rooms.get_mut(room_id) {
+                        handle_message(room, *index, msg, &tx);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(room_id) = watching {
+        let Ok(mut rooms) = rooms.lock() else {
+            return Ok(());
+        };
+        if let Some(room) = rooms.get_mut(&room_id) {
+            room.spectators.retain(|(id, _)| *id != connection);
+        }
+        return Ok(());
+    }
+
+    // Keep the seat for a reconnect, but let the opponent know
+    if let Some((room_id, index)) = seat {
+        let Ok(mut rooms) = rooms.lock() else {
+            return Ok(());
+        };
+        let Some(room) = rooms.get_mut(&room_id) else {
+            return Ok(());
+        };
+        if room.seats[index].as_ref().is_some_and(|s| s.connection == connection) {
+            if room.started {
+                if let Some(s) = room.seats[index].as_mut() {
+                    s.tx = None;
+                }
+                room.send(1 - index, &ServerMsg::OpponentLeft);
+            } else {
+                room.seats[index] = None;
+            }
+        }
+        let empty = room.seats.iter().all(|s| s.as_ref().is_none_or(|s| s.tx.is_none()));
+        if empty && (room.result.is_some() || !room.started) {
+            rooms.remove(&room_id);
+        }
+    }
+    Ok(())
+}
+
+/// Seats a player, either reclaiming a seat by token or taking a free one.
+#[allow(clippy::too_many_arguments)]
+fn join(
+    rooms: &mut HashMap<String, Room>,
+    room_id: &str,
+    token: Option<String>,
+    minutes: u32,
+    increment: u32,
+    name: String,
+    connection: u64,
+    tx: &Sender<String>,
+) -> Result<usize, String> {
+    let room = rooms.entry(room_id.to_string()).or_insert_with(|| Room::new(minutes, increment));
+
+    let reclaimed = token.and_then(|t| room.seats.iter().position(|s| s.as_ref().is_some_and(|s| s.token == t)));
+    if let Some(index) = reclaimed {
+        if let Some(s) = room.seats[index].as_mut() {
+            s.tx = Some(tx.clone());
+            s.connection = connection;
+        }
+        let token = room.seats[index].as_ref().map(|s| s.token.clone()).unwrap_or_default();
+        let colour = if index == 0 { Colour::White } else { Colour::Black };
+        room.send(index, &ServerMsg::Welcome { colour, token });
+        room.send(index, &room.start_msg());
+        room.send(index, &room.sync_msg());
+        if let Some((result, reason)) = room.result.clone() {
+            room.send(index, &ServerMsg::GameOver { result, reason });
+        } else if room.draw_offer == Some(1 - index) {
+            room.send(index, &ServerMsg::DrawOffered);
+        }
+        room.send(1 - index, &ServerMsg::OpponentBack);
+        return Ok(index);
+    }
+
+    let index = room.seats.iter().position(|s| s.is_none()).ok_or("Room is full".to_string())?;
+    let token = format!("{:016x}", rand::random::<u64>());
+    room.seats[index] = Some(Seat { name, token: token.clone(), connection, tx: Some(tx.clone()) });
+    let colour = if index == 0 { Colour::White } else { Colour::Black };
+    room.send(index, &ServerMsg::Welcome { colour, token });
+
+    if room.seats.iter().all(|s| s.is_some()) && !room.started {
+        room.started = true;
+        room.last_tick = Instant::now();
+        room.broadcast(&room.start_msg());
+        room.broadcast(&room.sync_msg());
+    }
+    Ok(index)
+}
+
+/// Adds a spectator and catches them up on the game so far.
+fn watch(room: &mut Room, connection: u64, tx: &Sender<String>) {
+    room.spectators.push((connection, tx.clone()));
+    if room.started {
+        let _ = tx.send(room.start_msg().encode());
+        let _ = tx.send(room.sync_msg().encode());
+    }
+    if let Some((result, reason)) = room.result.clone() {
+        let _ = tx.send(ServerMsg::GameOver { result, reason }.encode());
+    }
+}
+
+fn handle_message(room: &mut Room, seat: usize, msg: ClientMsg, tx: &Sender<String>) {
+    let error = |text: String| {
+        let _ = tx.send(ServerMsg::Error(text).encode());
+    };
+    match msg {
+        ClientMsg::Hello { .. } | ClientMsg::Watch { .. } => error("Already in a room".to_string()),
+        ClientMsg::Move(uci) => {
+            if let Err(e) = room.play(seat, &uci) {
+                error(e);
+                // Put the client back on the server's position
+                let _ = tx.send(room.sync_msg().encode());
+            }
+        }
+        ClientMsg::Sync => {
+            let _ = tx.send(room.sync_msg().encode());
+        }
+        _ if !room.started || room.result.is_some() => error("The game isn't running".to_string()),
+        ClientMsg::OfferDraw => {
+            if room.draw_offer == Some(1 - seat) {
+                room.finish("1/2-1/2", "agreement");
+            } else {
+                room.draw_offer = Some(seat);
+                room.send(1 - seat, &ServerMsg::DrawOffered);
+            }
+        }
+        ClientMsg::AcceptDraw => {
+            if room.draw_offer == Some(1 - seat) {
+                room.finish("1/2-1/2", "agreement");
+            } else {
+                error("There is no draw offer".to_string());
+            }
+        }
+        ClientMsg::DeclineDraw => {
+            if room.draw_offer == Some(1 - seat) {
+                room.draw_offer = None;
+                room.send(1 - seat, &ServerMsg::DrawDeclined);
+            }
+        }
+        ClientMsg::Resign => room.win_for(1 - seat, "resignation"),
+    }
+}
@@ -0,0 +1,177 @@
+// Just enough of RFC 6455 for the relay server to talk to browsers: the
+// opening handshake and unfragmented-or-continued text frames.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::base64;
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+
+/// Reads the rest of the HTTP upgrade request and answers it.
+/// `request_line` is the `GET ...` line the caller already read.
+pub fn accept(reader: &mut impl BufRead, writer: &mut impl Write, request_line: &str) -> io::Result<()> {
+    if !request_line.starts_with("GET ") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket request"));
+    }
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        let header = line.split_once(':').filter(|(name, _)| name.trim().eq_ignore_ascii_case("Sec-WebSocket-Key"));
+        if let Some((_, value)) = header {
+            key = Some(value.trim().to_string());
+        }
+    }
+    let key = key.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Sec-WebSocket-Key"))?;
+    let accept = base64::encode(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    writer.flush()
+}
+
+/// Reads the next text message, `None` once the client closes.
+/// Pings aren't answered; browsers don't send them unprompted.
+pub fn read_text(reader: &mut impl Read) -> io::Result<Option<String>> {
+    let mut message = Vec::new();
+    loop {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut b = [0u8; 2];
+                reader.read_exact(&mut b)?;
+                u16::from_be_bytes(b) as u64
+            }
+            127 => {
+                let mut b = [0u8; 8];
+                reader.read_exact(&mut b)?;
+                u64::from_be_bytes(b)
+            }
+            n => n as u64,
+        };
+        if len > 1 << 20 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+        }
+        let mut mask = [0u8; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload)?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        match opcode {
+            OP_CLOSE => return Ok(None),
+            OP_TEXT | OP_CONTINUATION => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    return String::from_utf8(message)
+                        .map(Some)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+            }
+            // Ping, pong and binary frames carry nothing for us
+            _ => (),
+        }
+    }
+}
+
+/// Writes `text` as a single unmasked text frame, as servers must.
+pub fn write_text(writer: &mut impl Write, text: &str) -> io::Result<()> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x80 | OP_TEXT];
+    match payload.len() {
+        n if n < 126 => frame.push(n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (hv, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *hv = hv.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 20];
+    for (chunk, v) in out.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&v.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn handshake_accept_matches_rfc_example() {
+        // The worked example in RFC 6455, section 1.3
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let accept = base64::encode(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+        assert_eq!(accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+}
@@ -0,0 +1,263 @@
+use chess_lib::{Board, Move, MoveList, Piece, Tile};
+
+/// A legal move along with its SAN and UCI spellings.
+#[derive(Clone, Debug)]
+pub struct NotatedMove {
+    pub mv: Move,
+    pub san: String,
+    pub uci: String,
+}
+
+pub fn tile_name(tile: Tile) -> String {
+    let (x, y) = tile.get_coords();
+    format!("{}{}", (b'a' + x) as char, y + 1)
+}
+
+pub fn parse_tile(s: &str) -> Option<Tile> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let file = bytes[0].to_ascii_lowercase();
+    let rank = bytes[1];
+    if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+        return None;
+    }
+    Tile::new_xy(file - b'a', rank - b'1')
+}
+
+pub fn piece_from_char(c: char) -> Option<Piece> {
+    match c.to_ascii_uppercase() {
+        'P' => Some(Piece::Pawn),
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+pub fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+/// The promotion piece of a move, read back from its SAN (`e8=Q`).
+pub fn promotion_from_san(san: &str) -> Option<Piece> {
+    let idx = san.find('=')?;
+    san[idx + 1..].chars().next().and_then(piece_from_char)
+}
+
+/// Every legal move for the side to move, spelled the way chess_lib writes it
+/// into the move history.
+pub fn legal_moves(board: &Board) -> Vec<NotatedMove> {
+    let mut moves = MoveList::new();
+    board.generate_legal_moves(board.turn, &mut moves);
+
+    let mut notated = Vec::with_capacity(moves.len());
+    for &m in moves.iter() {
+        let mut after = board.clone();
+        after.make_move_unchecked(m);
+        let san = match after.history.last() {
+            Some(h) => h.san_string.clone(),
+            None => continue,
+        };
+
+        let mut uci = format!("{}{}", tile_name(m.from()), tile_name(m.to()));
+        if let Some(p) = promotion_from_san(&san) {
+            uci.push(piece_char(p).to_ascii_lowercase());
+        }
+
+        notated.push(NotatedMove { mv: m, san, uci });
+    }
+    notated
+}
+
+/// The UCI spelling of a legal move in `board`.
+pub fn move_uci(board: &Board, m: Move) -> Option<String> {
+    legal_moves(board).into_iter().find(|n| n.mv == m).map(|n| n.uci)
+}
+
+/// Strips check/annotation marks and normalises castling zeros and `=`, so
+/// `Nf3+`, `e8Q` and `0-0` compare equal to what chess_lib emits.
+fn normalise_san(s: &str) -> String {
+    s.trim()
+        .chars()
+        .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | '='))
+        .map(|c| if c == '0' { 'O' } else { c })
+        .collect()
+}
+
+/// Parses a move in SAN (`Nf3`, `exd5`, `O-O`, `e8=Q`) or UCI (`g1f3`, `e7e8q`)
+/// and returns it only if it is legal in `board`.
+pub fn parse_move(board: &Board, text: &str) -> Option<Move> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let moves = legal_moves(board);
+
+    let lower = text.to_ascii_lowercase();
+    if let Some(m) = moves.iter().find(|m| m.uci == lower) {
+        return Some(m.mv);
+    }
+
+    let wanted = normalise_san(text);
+    if let Some(m) = moves.iter().find(|m| normalise_san(&m.san) == wanted) {
+        return Some(m.mv);
+    }
+
+    // Over-specified or loosely written SAN such as `Ngf3` or `exd5` without
+    // the capture mark: match on piece, destination, origin hints and promotion.
+    let (piece, from_file, from_rank, to, promotion) = split_san(&wanted)?;
+    let mut candidates = moves.iter().filter(|m| {
+        let from = m.mv.from();
+        let (fx, fy) = from.get_coords();
+        m.mv.to() == to
+            && board.get_piece_at_tile(from).map(|(p, _)| p) == Some(piece)
+            && from_file.is_none_or(|f| f == fx)
+            && from_rank.is_none_or(|r| r == fy)
+            && promotion_from_san(&m.san) == promotion
+    });
+
+    let first = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+    Some(first.mv)
+}
+
+type SanParts = (Piece, Option<u8>, Option<u8>, Tile, Option<Piece>);
+
+fn split_san(san: &str) -> Option<SanParts> {
+    let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != '-').collect();
+
+    let piece = match chars.first() {
+        Some(c) if c.is_ascii_uppercase() => {
+            let p = piece_from_char(*c)?;
+            chars.remove(0);
+            p
+        }
+        _ => Piece::Pawn,
+    };
+
+    let mut promotion = None;
+    let promotion_char = chars
+        .last()
+        .filter(|c| piece == Piece::Pawn && c.is_ascii_alphabetic() && !('a'..='h').contains(*c));
+    if let Some(&c) = promotion_char {
+        promotion = Some(piece_from_char(c)?);
+        chars.pop();
+    }
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let dest: String = chars[chars.len() - 2..].iter().collect();
+    let to = parse_tile(&dest)?;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in &chars[..chars.len() - 2] {
+        match c {
+            'a'..='h' => from_file = Some(*c as u8 - b'a'),
+            '1'..='8' => from_rank = Some(*c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    Some((piece, from_file, from_rank, to, promotion))
+}
+
+/// Legal moves whose SAN or UCI starts with `prefix`, for autocompletion.
+pub fn completions(board: &Board, prefix: &str) -> Vec<NotatedMove> {
+    let prefix = prefix.trim();
+    let san_prefix = normalise_san(prefix);
+    let uci_prefix = prefix.to_ascii_lowercase();
+
+    legal_moves(board)
+        .into_iter()
+        .filter(|m| normalise_san(&m.san).starts_with(&san_prefix) || m.uci.starts_with(&uci_prefix))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// The move `text` stands for in `fen`, in UCI.
+    fn parsed(fen: &str, text: &str) -> Option<String> {
+        let board = Board::new_from_fen(fen).unwrap();
+        parse_move(&board, text).and_then(|m| move_uci(&board, m))
+    }
+
+    fn tile(name: &str) -> Tile {
+        parse_tile(name).unwrap()
+    }
+
+    #[test]
+    fn splits_disambiguated_san() {
+        assert_eq!(split_san("Nbd2"), Some((Piece::Knight, Some(1), None, tile("d2"), None)));
+        assert_eq!(split_san("R1a3"), Some((Piece::Rook, None, Some(0), tile("a3"), None)));
+        assert_eq!(split_san("Qh4e1"), Some((Piece::Queen, Some(7), Some(3), tile("e1"), None)));
+        assert_eq!(split_san("exd5"), Some((Piece::Pawn, Some(4), None, tile("d5"), None)));
+        assert_eq!(split_san("e8Q"), Some((Piece::Pawn, None, None, tile("e8"), Some(Piece::Queen))));
+        assert_eq!(split_san("Nz3"), None);
+    }
+
+    #[test]
+    fn disambiguates_by_file_and_rank() {
+        let knights = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        assert_eq!(parsed(knights, "Nbd2").as_deref(), Some("b1d2"));
+        assert_eq!(parsed(knights, "Nfd2").as_deref(), Some("f1d2"));
+        let rooks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(parsed(rooks, "R1a3").as_deref(), Some("a1a3"));
+        assert_eq!(parsed(rooks, "R5a3").as_deref(), Some("a5a3"));
+    }
+
+    #[test]
+    fn rejects_ambiguous_moves() {
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "Nd2"), None);
+        assert_eq!(parsed("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "Ra3"), None);
+    }
+
+    #[test]
+    fn reads_captures_without_the_x() {
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(parsed(fen, "exd5").as_deref(), Some("e4d5"));
+        assert_eq!(parsed(fen, "ed5").as_deref(), Some("e4d5"));
+    }
+
+    #[test]
+    fn reads_promotions_every_way() {
+        let fen = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+        for text in ["e8=Q", "e8Q", "e7e8q", "e8=Q+"] {
+            assert_eq!(parsed(fen, text).as_deref(), Some("e7e8q"), "{}", text);
+        }
+        assert_eq!(parsed(fen, "e8=N").as_deref(), Some("e7e8n"));
+    }
+
+    #[test]
+    fn reads_castling_with_zeros_and_marks() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(parsed(fen, "0-0").as_deref(), Some("e1g1"));
+        assert_eq!(parsed(fen, "O-O-O+").as_deref(), Some("e1c1"));
+        assert_eq!(parsed(fen, "0-0-0").as_deref(), Some("e1c1"));
+    }
+
+    #[test]
+    fn refuses_illegal_moves() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(parsed(fen, "e2e5"), None);
+        assert_eq!(parsed(fen, "Nf6"), None);
+        assert_eq!(parsed(fen, ""), None);
+    }
+}
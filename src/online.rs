@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use chess_lib::{Board, Colour};
+use egui::{Context, TextureHandle};
+use instant::Instant;
+
+use crate::{
+    net::{
+        client::{Connection, NetEvent},
+        protocol::{ClientMsg, ServerMsg, DEFAULT_PORT},
+    },
+    notation,
+    play::PlayTab,
+};
+
+mod render;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+pub struct OnlineTab
+{
+    pub play: PlayTab,
+    connection: Option<Connection>,
+
+    pub host: String,
+    pub port: u16,
+    pub room: String,
+    pub name: String,
+    pub minutes: u32,
+    pub increment: u32,
+    /// Port of the relay this app is hosting, if any.
+    pub hosting: Option<u16>,
+
+    pub colour: Option<Colour>,
+    token: Option<String>,
+    pub names: [String; 2],
+    /// White's and Black's time in milliseconds as of `clock_time`.
+    clocks: [i64; 2],
+    clock_time: Instant,
+    pub started: bool,
+    pub result: Option<(String, String)>,
+    /// Moves in `play.board` that the server already knows about.
+    confirmed: usize,
+    /// Our move waiting for the server's echo.
+    sent: bool,
+    pub draw_offered: bool,
+    pub draw_sent: bool,
+    pub opponent_connected: bool,
+    pub status: String,
+    reconnect_at: Option<Instant>,
+}
+
+impl OnlineTab
+{
+    pub fn new(atlas: TextureHandle) -> Self {
+        let mut play = PlayTab::new(None, atlas);
+        play.embedded = true;
+        play.input_locked = true;
+
+        Self {
+            play,
+            connection: None,
+
+            host: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            room: "lobby".to_string(),
+            name: "Player".to_string(),
+            minutes: 5,
+            increment: 3,
+            hosting: None,
+
+            colour: None,
+            token: None,
+            names: [String::new(), String::new()],
+            clocks: [0, 0],
+            clock_time: Instant::now(),
+            started: false,
+            result: None,
+            confirmed: 0,
+            sent: false,
+            draw_offered: false,
+            draw_sent: false,
+            opponent_connected: true,
+            status: "Not connected".to_string(),
+            reconnect_at: None,
+        }
+    }
+    pub fn connected(&self) -> bool {
+        self.connection.is_some()
+    }
+    /// Joins the room, starting a fresh game unless we hold a seat token.
+    pub fn connect(&mut self) {
+        match Connection::connect(&self.host, self.port) {
+            Ok(mut connection) => {
+                connection.send(&ClientMsg::Hello {
+                    room: self.room.clone(),
+                    token: self.token.clone(),
+                    minutes: self.minutes,
+                    increment: self.increment,
+                    name: self.name.clone(),
+                });
+                self.connection = Some(connection);
+                self.reconnect_at = None;
+                self.status = "Waiting for an opponent".to_string();
+            }
+            Err(e) => {
+                self.status = e;
+                if self.token.is_some() {
+                    self.reconnect_at = Some(Instant::now() + RECONNECT_DELAY);
+                }
+            }
+        }
+    }
+    /// Leaves the room for good; the seat can't be reclaimed afterwards.
+    pub fn disconnect(&mut self) {
+        self.connection = None;
+        self.token = None;
+        self.colour = None;
+        self.started = false;
+        self.reconnect_at = None;
+        self.play.input_locked = true;
+        self.status = "Not connected".to_string();
+    }
+    /// Runs a relay inside the app and joins it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn host_game(&mut self) {
+        use crate::net::server;
+
+        if self.hosting != Some(self.port) {
+            match server::listen(("0.0.0.0", self.port)) {
+                Ok(listener) => {
+                    std::thread::spawn(move || server::serve(listener));
+                    self.hosting = Some(self.port);
+                }
+                Err(e) => {
+                    self.status = format!("Can't host on port {}: {}", self.port, e);
+                    return;
+                }
+            }
+        }
+        self.host = "127.0.0.1".to_string();
+        self.connect();
+    }
+    pub fn send(&mut self, msg: ClientMsg) {
+        if let Some(connection) = &mut self.connection {
+            connection.send(&msg);
+        }
+    }
+    pub fn offer_draw(&mut self) {
+        self.send(ClientMsg::OfferDraw);
+        self.draw_sent = true;
+    }
+    pub fn answer_draw(&mut self, accept: bool) {
+        self.send(if accept { ClientMsg::AcceptDraw } else { ClientMsg::DeclineDraw });
+        self.draw_offered = false;
+    }
+    pub fn resign(&mut self) {
+        self.send(ClientMsg::Resign);
+    }
+    /// Time left for White and Black, counting down the side to move.
+    pub fn clocks(&self) -> [i64; 2] {
+        let mut clocks = self.clocks;
+        if self.started && self.result.is_none() {
+            let turn = if self.play.board.turn.white() { 0 } else { 1 };
+            clocks[turn] -= self.clock_time.elapsed().as_millis() as i64;
+        }
+        clocks.map(|c| c.max(0))
+    }
+
+    fn set_clocks(&mut self, white_ms: i64, black_ms: i64) {
+        self.clocks = [white_ms, black_ms];
+        self.clock_time = Instant::now();
+    }
+    /// Replays the server's move list, validating each move locally.
+    fn sync(&mut self, moves: &[String]) {
+        self.play.set_position(Board::new());
+        for uci in moves {
+            match notation::parse_move(&self.play.board, uci) {
+                Some(m) => self.play.play_move(m),
+                None => {
+                    self.status = format!("The server sent an illegal move {}", uci);
+                    break;
+                }
+            }
+        }
+        self.play.view_board = self.play.board.clone();
+        self.confirmed = self.play.board.history.len();
+        self.sent = false;
+    }
+    fn handle_message(&mut self, msg: ServerMsg) {
+        match msg {
+            ServerMsg::Welcome { colour, token } => {
+                self.colour = Some(colour);
+                self.token = Some(token);
+                self.play.flipped = colour == Colour::Black;
+            }
+            ServerMsg::Start { white, black, increment_ms: _ } => {
+                self.names = [white, black];
+                self.started = true;
+                self.result = None;
+                self.draw_offered = false;
+                self.draw_sent = false;
+                self.opponent_connected = true;
+                self.status = "Game in progress".to_string();
+            }
+            ServerMsg::Sync { white_ms, black_ms, moves } => {
+                self.sync(&moves);
+                self.set_clocks(white_ms, black_ms);
+            }
+            ServerMsg::Move { uci, white_ms, black_ms } => {
+                self.set_clocks(white_ms, black_ms);
+                self.draw_offered = false;
+                self.draw_sent = false;
+                if self.sent && self.play.board.history.len() == self.confirmed + 1 {
+                    // The echo of our own move
+                    self.sent = false;
+                    self.confirmed += 1;
+                    return;
+                }
+                match notation::parse_move(&self.play.board, &uci) {
+                    Some(m) => {
+                        self.play.play_move(m);
+                        self.play.view_board = self.play.board.clone();
+                        self.confirmed += 1;
+                    }
+                    None => {
+                        self.status = format!("Move {} doesn't fit our board, resyncing", uci);
+                        self.send(ClientMsg::Sync);
+                    }
+                }
+            }
+            ServerMsg::DrawOffered => self.draw_offered = true,
+            ServerMsg::DrawDeclined => {
+                self.draw_sent = false;
+                self.status = "Draw offer declined".to_string();
+            }
+            ServerMsg::GameOver { result, reason } => {
+                self.status = format!("Game over: {} ({})", result, reason.replace('_', " "));
+                self.result = Some((result, reason));
+                self.draw_offered = false;
+            }
+            ServerMsg::OpponentLeft => {
+                self.opponent_connected = false;
+                self.status = "Opponent disconnected, their clock keeps running".to_string();
+            }
+            ServerMsg::OpponentBack => {
+                self.opponent_connected = true;
+                self.status = "Opponent reconnected".to_string();
+            }
+            ServerMsg::Error(text) => self.status = text,
+        }
+    }
+    fn update(&mut self, ctx: &Context) {
+        let events = self.connection.as_mut().map(|c| c.poll()).unwrap_or_default();
+        for event in events {
+            match event {
+                NetEvent::Message(msg) => self.handle_message(msg),
+                NetEvent::Closed(reason) => {
+                    self.connection = None;
+                    // Try to get the seat back while the game is still on
+                    if self.token.is_some() && self.result.is_none() {
+                        self.status = format!("{}, reconnecting", reason);
+                        self.reconnect_at = Some(Instant::now() + RECONNECT_DELAY);
+                    } else {
+                        self.status = reason;
+                    }
+                    break;
+                }
+            }
+        }
+        if self.reconnect_at.is_some_and(|t| Instant::now() >= t) {
+            self.connect();
+        }
+
+        // Send the move the player just made on the board
+        let history = self.play.board.history.len();
+        if !self.sent && history == self.confirmed + 1 {
+            let mut before = self.play.board.clone();
+            before.undo_move();
+            let last = self.play.board.history.last().map(|h| h.last_move);
+            if let Some(uci) = last.and_then(|m| notation::move_uci(&before, m)) {
+                self.send(ClientMsg::Move(uci));
+                self.sent = true;
+            }
+        }
+
+        let our_turn = self.colour == Some(self.play.board.turn);
+        self.play.input_locked =
+            !(self.connected() && self.started && self.result.is_none() && our_turn && !self.sent);
+
+        if self.connected() || self.reconnect_at.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        egui::SidePanel::right("online_panel")
+            .resizable(false)
+            .min_width(260.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("scroll_online")
+                    .show(ui, |ui| {
+                        self.render_panel(ui);
+                    });
+            });
+        self.play.render_board(ctx);
+        self.update(ctx);
+    }
+}
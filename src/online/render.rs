@@ -0,0 +1,129 @@
+use chess_lib::Colour;
+use egui::{Color32, DragValue, Grid, RichText, Ui};
+
+use crate::online::OnlineTab;
+
+fn format_clock(ms: i64) -> String {
+    let secs = ms / 1000;
+    if ms < 10_000 {
+        format!("0:{:02}.{}", secs, (ms % 1000) / 100)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl OnlineTab
+{
+    pub fn render_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Online");
+        ui.add_space(8.0);
+
+        if self.connected() || self.colour.is_some() {
+            self.render_game(ui);
+        } else {
+            self.render_connect(ui);
+        }
+        ui.add_space(8.0);
+        ui.label(RichText::new(&self.status).weak());
+    }
+
+    fn render_connect(&mut self, ui: &mut Ui) {
+        Grid::new("online_connect").num_columns(2).show(ui, |ui| {
+            ui.label("Server");
+            ui.text_edit_singleline(&mut self.host);
+            ui.end_row();
+            ui.label("Port");
+            ui.add(DragValue::new(&mut self.port));
+            ui.end_row();
+            ui.label("Room");
+            ui.text_edit_singleline(&mut self.room);
+            ui.end_row();
+            ui.label("Your name");
+            ui.text_edit_singleline(&mut self.name);
+            ui.end_row();
+            ui.label("Minutes");
+            ui.add(DragValue::new(&mut self.minutes).range(1..=180));
+            ui.end_row();
+            ui.label("Increment (s)");
+            ui.add(DragValue::new(&mut self.increment).range(0..=60));
+            ui.end_row();
+        });
+        ui.label(
+            RichText::new("The first player to join a room plays White and sets the clock")
+                .weak(),
+        );
+        ui.add_space(8.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("Connect").clicked() {
+                self.connect();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Host game").on_hover_text("Run the relay inside this app and join it").clicked() {
+                self.host_game();
+            }
+        });
+        if let Some(port) = self.hosting {
+            ui.label(format!("Hosting on port {}", port));
+        }
+    }
+
+    fn render_clock(&self, ui: &mut Ui, colour: Colour) {
+        let index = if colour.white() { 0 } else { 1 };
+        let ms = self.clocks()[index];
+        let running = self.started && self.result.is_none() && self.play.board.turn == colour;
+        let name = if self.names[index].is_empty() { "Waiting…" } else { self.names[index].as_str() };
+
+        ui.horizontal(|ui| {
+            let mut text = RichText::new(format_clock(ms)).monospace().size(24.0);
+            if running {
+                text = text.strong();
+            }
+            if ms < 10_000 {
+                text = text.color(Color32::from_rgb(200, 80, 80));
+            }
+            ui.label(text);
+            ui.label(name);
+        });
+    }
+
+    fn render_game(&mut self, ui: &mut Ui) {
+        let ours = self.colour.unwrap_or(Colour::White);
+        let theirs = if ours.white() { Colour::Black } else { Colour::White };
+
+        self.render_clock(ui, theirs);
+        if self.started && !self.opponent_connected {
+            ui.label(RichText::new("Disconnected").color(Color32::from_rgb(200, 80, 80)));
+        }
+        ui.add_space(4.0);
+        self.render_clock(ui, ours);
+        ui.add_space(8.0);
+
+        let running = self.connected() && self.started && self.result.is_none();
+        if self.draw_offered && running {
+            ui.label("Your opponent offers a draw");
+            ui.horizontal(|ui| {
+                if ui.button("Accept").clicked() {
+                    self.answer_draw(true);
+                }
+                if ui.button("Decline").clicked() {
+                    self.answer_draw(false);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            if ui.add_enabled(running && !self.draw_sent, egui::Button::new("Offer draw")).clicked() {
+                self.offer_draw();
+            }
+            if ui.add_enabled(running, egui::Button::new("Resign")).clicked() {
+                self.resign();
+            }
+        });
+        ui.add_space(8.0);
+
+        let leave = if self.result.is_some() { "Leave room" } else { "Disconnect" };
+        if ui.button(leave).on_hover_text("Leaves for good, the seat can't be taken back").clicked() {
+            self.disconnect();
+        }
+    }
+}
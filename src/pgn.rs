@@ -0,0 +1,184 @@
+// Helpers for editing the PGN text chess_lib produces.
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Sets tag `name` to `value`, replacing an existing tag or adding it after the others.
+pub fn set_tag(pgn: &str, name: &str, value: &str) -> String {
+    let tag_line = format!("[{} \"{}\"]", name, escape(value));
+    let prefix = format!("[{} ", name);
+
+    let mut lines: Vec<String> = pgn.lines().map(|l| l.to_string()).collect();
+    if let Some(line) = lines.iter_mut().find(|l| l.trim_start().starts_with(&prefix)) {
+        *line = tag_line;
+    } else {
+        let insert_at = lines
+            .iter()
+            .rposition(|l| l.trim_start().starts_with('['))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        lines.insert(insert_at, tag_line);
+        // Keep the blank line between the tags and the moves
+        if insert_at == 0 && lines.len() > 1 && !lines[1].trim().is_empty() {
+            lines.insert(1, String::new());
+        }
+    }
+
+    let mut out = lines.join("\n");
+    if pgn.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Sets the `Result` tag and the result closing the movetext, with `comment`
+/// written just before it.
+pub fn set_result(pgn: &str, result: &str, comment: Option<&str>) -> String {
+    let text = set_tag(pgn, "Result", result);
+    let mut body = text.trim_end();
+    if let Some(rest) = ["1-0", "0-1", "1/2-1/2", "*"].iter().find_map(|r| body.strip_suffix(r)) {
+        body = rest.trim_end();
+    }
+    let mut out = body.to_string();
+    // No moves: the result still goes after the blank line under the tags
+    let no_moves = out.lines().last().is_none_or(|l| l.trim_start().starts_with('['));
+    out.push_str(if no_moves { "\n\n" } else { " " });
+    if let Some(comment) = comment {
+        out.push_str(&format!("{{{}}} ", comment.replace('}', ")")));
+    }
+    out.push_str(result);
+    out.push('\n');
+    out
+}
+
+/// The value of tag `name`, if present.
+pub fn get_tag(pgn: &str, name: &str) -> Option<String> {
+    let prefix = format!("[{} \"", name);
+    pgn.lines().find_map(|l| {
+        let rest = l.trim().strip_prefix(&prefix)?;
+        let value = rest.strip_suffix("\"]")?;
+        Some(value.replace("\\\"", "\"").replace("\\\\", "\\"))
+    })
+}
+
+/// A game read from PGN text.
+#[derive(Debug, Clone, Default)]
+pub struct PgnGame
+{
+    pub tags: Vec<(String, String)>,
+    /// Main line moves in SAN, without numbers, comments or variations.
+    pub moves: Vec<String>,
+    /// The comment after each move in `moves`, empty where there is none.
+    pub comments: Vec<String>,
+}
+impl PgnGame
+{
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Splits PGN text into games. A tag line after movetext starts the next game.
+pub fn parse_games(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut movetext = String::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(tag) = parse_tag_line(line) {
+            if !movetext.trim().is_empty() {
+                (game.moves, game.comments) = movetext_moves(&movetext);
+                games.push(std::mem::take(&mut game));
+                movetext.clear();
+            }
+            game.tags.push(tag);
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    if !movetext.trim().is_empty() || !game.tags.is_empty() {
+        (game.moves, game.comments) = movetext_moves(&movetext);
+        games.push(game);
+    }
+    games
+}
+
+fn parse_tag_line(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, rest) = inner.split_once(' ')?;
+    let value = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Main line moves and the comment following each one, empty where there is none.
+fn movetext_moves(text: &str) -> (Vec<String>, Vec<String>) {
+    let mut moves = Vec::new();
+    let mut comments: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut comment = String::new();
+    let mut depth = 0usize;
+    let mut in_comment = false;
+    let mut in_line_comment = false;
+
+    // A trailing space flushes the last token
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if in_line_comment {
+            in_line_comment = c != '\n';
+            continue;
+        }
+        if in_comment {
+            if c != '}' {
+                comment.push(c);
+                continue;
+            }
+            in_comment = false;
+            // Comments inside variations belong to the variation
+            if let Some(last) = comments.last_mut().filter(|_| depth == 0) {
+                if !last.is_empty() {
+                    last.push(' ');
+                }
+                last.push_str(comment.trim());
+            }
+            comment.clear();
+            continue;
+        }
+        if !(c.is_whitespace() || matches!(c, '{' | ';' | '(' | ')')) {
+            token.push(c);
+            continue;
+        }
+        if depth == 0 && !token.is_empty() {
+            if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                break;
+            }
+            // `12.`, `12...` and `12.e4`, skipping NAGs like `$1`
+            let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            if !san.is_empty() && !san.starts_with('$') {
+                moves.push(san.to_string());
+                comments.push(String::new());
+            }
+        }
+        token.clear();
+        match c {
+            '{' => in_comment = true,
+            ';' => in_line_comment = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    (moves, comments)
+}
+
+/// Reads a `[%clk 1:23:45]` command out of a move comment, in milliseconds.
+pub fn comment_clock(comment: &str) -> Option<i64> {
+    let rest = &comment[comment.find("[%clk")? + "[%clk".len()..];
+    let time = rest[..rest.find(']')?].trim();
+    let mut ms = 0.0;
+    for part in time.split(':') {
+        ms = ms * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some((ms * 1000.0) as i64)
+}
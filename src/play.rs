@@ -1,18 +1,33 @@
 use std::ops::RangeInclusive;
 
 use chess_engine::search::find_best_move;
-use chess_lib::{Board, MoveList, Tile};
+use chess_lib::{Board, Move, Tile};
 use egui::{Context, Layout, RichText, Slider, TextureHandle, Ui, Vec2};
-use rand::{rngs::ThreadRng, Rng};
+use rand::rngs::ThreadRng;
 use instant::Instant;
 
+mod assist;
+mod castling;
+mod clipboard;
+mod ending;
+pub mod export;
+pub mod game_info;
 mod helper;
 mod render;
-mod state;
-use state::PlayState;
-
-use crate::play::state::Engine;
+pub mod state;
+mod visibility;
+use assist::Assist;
+use castling::{Castling960, GameMove};
+use export::ExportSettings;
+use game_info::GameInfo;
+use state::{PlayState, Termination};
+use visibility::Visibility;
+
+use crate::{chess960, clipboard::ClipboardReader, play::state::Engine, statistics::record::GameRecord};
 mod input;
+mod keymap;
+use keymap::{Action, Keymap};
+mod premove;
 
 pub struct PlayTab
 {
@@ -23,22 +38,77 @@ pub struct PlayTab
     
     pub flipped: bool,
     pub selected: Option<Tile>,
+    pub cursor: Option<Tile>,
+    /// Undone moves with their SAN, most recently undone last.
+    pub redo_stack: Vec<(GameMove, String)>,
+    /// Moves queued by the human during the engine's turn, as (from, to).
+    pub premoves: Vec<(Tile, Tile)>,
+    pub premove_selected: Option<Tile>,
+    pub move_text: String,
+    pub move_text_error: Option<String>,
 
     pub engine_plays: Engine,
     pub auto_queen: bool,
     last_frame_time: Instant,
     engine_timer: f32,
     pub seconds_per_move: f32,
+    pub engine_depth: u8,
 
     pub split_ratio: f32,
 
     pub atlas: TextureHandle,
     pub board_size: f32,
+    /// Blindfold and other partial views of the board.
+    pub visibility: Visibility,
+    /// Puts the cursor in the move entry box on the next frame.
+    focus_move_entry: bool,
+
+    /// FEN of the starting position when it isn't the standard one.
+    pub start_fen: Option<String>,
+    pub chess960: bool,
+    pub chess960_number: u16,
+    /// Why the last new Chess960 game couldn't be set up.
+    pub chess960_error: Option<String>,
+    /// The moves and castling rights of a Chess960 game chess_lib can't castle in.
+    castle960: Option<Castling960>,
 
     pub state: PlayState,
+    /// Set when the game ended by resignation, agreement or a claim, which the board can't tell.
+    termination: Option<Termination>,
+    /// The side with a draw offer standing, in games between two humans.
+    pub draw_offer: Option<chess_lib::Colour>,
+    /// The answer to the last draw offer.
+    pub draw_note: Option<String>,
     pub show_popup: bool,
     pub should_close: bool,
-    
+
+    pub keymap: Keymap,
+    pub show_keybindings: bool,
+    /// Tags written when the game is saved.
+    pub game_info: GameInfo,
+    pub show_game_info: bool,
+    /// Image and GIF export choices.
+    pub export: ExportSettings,
+    pub show_export: bool,
+    clipboard: ClipboardReader,
+    /// What the last copy or paste did.
+    pub clipboard_note: Option<String>,
+    /// Hints, threats and the hanging pieces overlay.
+    pub assist: Assist,
+    pub rebinding: Option<Action>,
+    pub pending_confirm: Option<Action>,
+
+    /// Games finished since the app last collected them for the statistics tab.
+    pub finished_games: Vec<GameRecord>,
+    /// Set once the game is in `finished_games`, or ended while being loaded.
+    /// Only a new game clears it: the statistics keep the first result, so
+    /// undoing the last move and playing it again doesn't count the game twice.
+    game_recorded: bool,
+
+    /// Set when another tab drives this board: no shortcuts, game-over popup or game recording.
+    pub embedded: bool,
+    /// Ignores board and move entry input, e.g. while a puzzle reply is pending.
+    pub input_locked: bool,
 }
 impl PlayTab
 {
@@ -55,31 +125,168 @@ impl PlayTab
 
             flipped: false,
             selected: None,
-            
+            cursor: None,
+            redo_stack: Vec::new(),
+            premoves: Vec::new(),
+            premove_selected: None,
+            move_text: String::new(),
+            move_text_error: None,
+
             engine_plays: Engine::Neither,
             auto_queen: false,
             last_frame_time: Instant::now(),
             engine_timer: 0.0,
             seconds_per_move: 1.0,
+            engine_depth: 6,
 
             split_ratio: 0.5,
 
             atlas: atlas,
             board_size: 400.0,
-
-            state: PlayState::Playing(chess_lib::GameState::Playing),
+            visibility: Visibility::Normal,
+            focus_move_entry: false,
+
+            start_fen: position.map(|f| f.to_string()),
+            chess960: false,
+            chess960_number: chess960::STANDARD,
+            chess960_error: None,
+            castle960: None,
+
+            state: PlayState::Playing,
+            termination: None,
+            draw_offer: None,
+            draw_note: None,
             show_popup: true,
             should_close: false,
 
+            keymap: Keymap::load(),
+            show_keybindings: false,
+            game_info: GameInfo::default(),
+            show_game_info: false,
+            export: ExportSettings::default(),
+            show_export: false,
+            clipboard: ClipboardReader::default(),
+            clipboard_note: None,
+            assist: Assist::default(),
+            rebinding: None,
+            pending_confirm: None,
+
+            finished_games: Vec::new(),
+            game_recorded: false,
+
+            embedded: false,
+            input_locked: false,
         }
 
     }
     pub fn reset(&mut self) {
-        self.board = Board::new();
-        self.view_board = Board::new();
-
-        self.state = PlayState::Playing(chess_lib::GameState::Playing);
+        if self.chess960 {
+            let fen = chess960::start_fen(self.chess960_number);
+            self.chess960_error = match self.set_position_fen(&fen) {
+                // Keep the FEN tag even for 518 so the Variant tag is complete
+                Ok(()) => {
+                    self.start_fen = Some(self.board.to_fen());
+                    None
+                }
+                Err(e) => Some(format!("Can't set up start position {}: {}", self.chess960_number, e)),
+            };
+        } else {
+            self.chess960_error = None;
+            self.set_position(Board::new());
+        }
+    }
+    /// Starts a new game from `board`.
+    pub fn set_position(&mut self, board: Board) {
+        let fen = board.to_fen();
+        self.start_fen = if fen == Board::new().to_fen() { None } else { Some(fen) };
+
+        self.board = board.clone();
+        self.view_board = board;
+        self.selected = None;
+        self.redo_stack.clear();
+        self.clear_premoves();
+        self.castle960 = None;
+
+        self.termination = None;
+        self.draw_offer = None;
+        self.draw_note = None;
+        self.state = self.position_state();
         self.show_popup = true;
+        self.engine_timer = 0.0;
+        self.game_recorded = false;
+        self.assist.hint = None;
+        self.assist.hints_used = [0; 2];
+    }
+    /// Plays an already legal move and brings the rest of the tab state up to date.
+    pub fn play_move(&mut self, m: Move) {
+        self.board.make_move_unchecked(m);
+        self.finish_move();
+    }
+    /// Bookkeeping after a move has been applied to `board`, however it was made.
+    /// Replaying the next redo move keeps the rest of the redo stack.
+    pub fn finish_move(&mut self) {
+        if let Some(castling) = &mut self.castle960 {
+            castling.record(&self.board);
+        }
+        let last = self.last_game_move();
+        if last.is_some() && self.redo_stack.last().map(|(m, _)| *m) == last {
+            self.redo_stack.pop();
+        } else {
+            self.redo_stack.clear();
+        }
+        // An offer lapses once the opponent moves instead of answering
+        if self.draw_offer == Some(self.board.turn) {
+            self.draw_offer = None;
+        }
+        self.draw_note = None;
+        self.selected = None;
+        self.state = self.position_state();
+        self.engine_timer = 0.0;
+        self.record_game();
+    }
+    /// Takes back a single ply, keeping it for redo.
+    pub fn undo(&mut self) {
+        let entry = if self.app_castling() {
+            match self.undo_app_move() {
+                Some(entry) => entry,
+                None => return,
+            }
+        } else {
+            let Some(h) = self.board.history.last() else {
+                return;
+            };
+            let entry = (GameMove::Lib(h.last_move), h.san_string.clone());
+            self.board.undo_move();
+            entry
+        };
+        self.redo_stack.push(entry);
+        self.clear_premoves();
+
+        self.view_board = self.board.clone();
+        self.selected = None;
+        self.termination = None;
+        self.draw_offer = None;
+        self.state = self.position_state();
+        self.engine_timer = 0.0;
+    }
+    pub fn redo(&mut self) {
+        match self.redo_stack.last() {
+            Some(&(GameMove::Lib(m), _)) => self.play_move(m),
+            Some(&(GameMove::Castle { kingside }, _)) => {
+                self.castle(kingside);
+            }
+            None => return,
+        }
+        self.view_board = self.board.clone();
+    }
+    /// Undoes back to the human's last turn, so the engine doesn't immediately replay.
+    pub fn take_back(&mut self) {
+        self.undo();
+        if self.engine_plays == Engine::White || self.engine_plays == Engine::Black {
+            while self.engine_turn() && self.ply_count() > 0 {
+                self.undo();
+            }
+        }
     }
     pub fn flip(&mut self) {
         self.flipped = !self.flipped;
@@ -98,13 +305,12 @@ impl PlayTab
         }
         false
     }
-    pub fn render(&mut self, ctx: &Context) {
-        let old_engine_plays = self.engine_plays;
-
+    /// Plays the engine's move once its thinking time is up, then any queued premove.
+    pub fn run_engine(&mut self, ctx: &Context) {
         let now = Instant::now();
         let dt = now.duration_since(self.last_frame_time).as_secs_f32();
         self.last_frame_time = now;
-        if let PlayState::Playing(_) = self.state {
+        if self.state == PlayState::Playing {
 
             self.engine_timer += dt;
 
@@ -112,24 +318,28 @@ impl PlayTab
                 if self.engine_timer >= self.seconds_per_move {
                     self.engine_timer = 0.0;
     
-                    if let Some(m) = find_best_move(&mut self.board, 6) {
-                        self.board.make_move_unchecked(m);
-                        self.state = PlayState::Playing(self.board.get_state());
+                    if let Some(m) = find_best_move(&mut self.board, self.engine_depth) {
+                        self.play_move(m);
+                        self.play_premove();
                     }
                 } else {
                     ctx.request_repaint();
                 }
             }
         }
-
-        // Modify view board to correct position
+    }
+    /// Moves `view_board` to the position being viewed in the move history.
+    pub fn sync_view_board(&mut self) {
         if let PlayState::Viewing(pos) = self.state {
             let curr_pos = self.view_board.history.len();
-            if self.board.history.len() == pos {
-                self.state = PlayState::Playing(self.board.get_state());
+            if self.ply_count() == pos {
+                self.state = self.position_state();
             }
             
-            if curr_pos > pos {
+            if self.app_castling() {
+                // chess_lib's history starts over at each castle, so replay the game instead
+                self.view_board = self.position_at(pos);
+            } else if curr_pos > pos {
                 let delta = curr_pos - pos;
                 for _ in 0..delta {
                     self.view_board.undo_move();
@@ -144,12 +354,28 @@ impl PlayTab
             }
             
         }
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        let old_engine_plays = self.engine_plays;
+
+        self.run_engine(ctx);
+
+        if !self.embedded {
+            self.handle_clipboard(ctx);
+        }
+        self.sync_view_board();
+        self.update_threat();
         // Render history first as render_board can modify history part way through a frame
         self.render_panels(ctx);
         self.render_board(ctx);
         // self.render_game_over(ctx);
+        self.render_keybindings(ctx);
+        self.render_game_info(ctx);
+        self.render_export(ctx);
+        self.render_confirm(ctx);
 
         if self.engine_plays != old_engine_plays {
+            self.clear_premoves();
             self.engine_timer = 0.0;
             self.last_frame_time = Instant::now();
         }
@@ -203,12 +429,15 @@ impl PlayTab
         }
     }
 
-    fn render_moves_list(&mut self, ui: &mut egui::Ui, is_portrait: bool) {
+    pub fn render_moves_list(&mut self, ui: &mut egui::Ui, is_portrait: bool) {
         let button_size = egui::Vec2::new(80.0, 30.0);
         let spacing = 8.0;
 
-        let moves = &self.board.history;
-        let move_count = moves.len();
+        let moves = self.move_sans();
+        let played_count = moves.len();
+        // Undone moves are listed greyed out after the played ones
+        let move_count = played_count + self.redo_stack.len();
+        let mut redo_to = None;
 
         let (rows, cols) = if is_portrait {
             let rows = ((ui.available_height() + spacing) / (button_size.y + spacing))
@@ -233,12 +462,22 @@ impl PlayTab
                         row * cols + col
                     };
 
-                    if idx < move_count {
-                        let h = &moves[idx];
+                    if idx >= played_count && idx < move_count {
+                        let (_, san) = &self.redo_stack[move_count - 1 - idx];
+                        let button = egui::Button::new(RichText::new(format!("{}. {}", idx + 1, san)).weak())
+                            .min_size(button_size);
+
+                        if ui.add(button).on_hover_text("Redo up to here").clicked() {
+                            redo_to = Some(idx);
+                        }
+
+                        ui.add_space(spacing);
+                    } else if idx < move_count {
+                        let san = &moves[idx];
                         let is_current =
                             matches!(self.state, PlayState::Viewing(pos) if pos == idx + 1);
 
-                        let button = egui::Button::new(format!("{}. {}", idx + 1, h.san_string))
+                        let button = egui::Button::new(format!("{}. {}", idx + 1, san))
                             .min_size(button_size);
 
                         let button = if is_current {
@@ -258,6 +497,12 @@ impl PlayTab
 
             ui.add_space(spacing);
         }
+
+        if let Some(idx) = redo_to {
+            for _ in played_count..=idx {
+                self.redo();
+            }
+        }
     }
 
     pub fn render_panels(&mut self, ctx: &egui::Context) {
@@ -372,36 +617,80 @@ impl PlayTab
 
                 ui.add_space(8.0);
 
+                ui.label("Engine depth");
+                let slider = Slider::new(&mut self.engine_depth, RangeInclusive::new(1, 8));
+                ui.add(slider);
+
+                ui.add_space(8.0);
+
                 if ui.button("Flip Board").clicked() {
                     self.flip();
                 }
 
                 ui.add_space(8.0);
 
+                if ui.button("Keybindings").clicked() {
+                    self.show_keybindings = true;
+                }
+
+                ui.add_space(8.0);
+
                 if ui.button("New Game").clicked() {
-                    self.reset();
+                    self.request_action(Action::Reset);
                 }
 
                 ui.add_space(8.0);
 
-                if ui.button("Undo Move").clicked() {
-                    self.board.undo_move();
+                self.render_game_actions(ui);
+
+                ui.add_space(8.0);
+
+                self.render_assist_settings(ui);
+
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Game info").on_hover_text("Players, event and other PGN tags").clicked() {
+                        self.show_game_info = true;
+                    }
+                    if ui.button("Save game").clicked() {
+                        self.save_game();
+                    }
+                    if ui.button("Export").on_hover_text("Board image or animated GIF of the game").clicked() {
+                        self.show_export = true;
+                    }
+                });
+                self.render_clipboard_buttons(ui);
+
+                ui.add_space(8.0);
+
+                self.render_chess960_settings(ui);
+
+                ui.add_space(8.0);
+
+                self.render_visibility_settings(ui);
+
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Undo Move").clicked() {
+                        self.undo();
+                    }
+                    if ui.add_enabled(!self.redo_stack.is_empty(), egui::Button::new("Redo Move")).clicked() {
+                        self.redo();
+                    }
+                });
+
+                ui.add_space(8.0);
+
+                if ui.button("Take back").on_hover_text("Undo to your last turn when playing the engine").clicked() {
+                    self.take_back();
                 }
 
                 ui.add_space(8.0);
 
                 if ui.button("Make random move").clicked() {
-                    if !self.engine_turn() {
-                        let mut moves = MoveList::new();
-                        self.board.generate_legal_moves(self.board.turn, &mut moves);
-                        if !moves.is_empty() {
-                            let random_index = self.rand.random_range(0..moves.len());
-                            self.board.make_move_unchecked(moves[random_index]);
-                        }
-                        self.selected = None;
-                        self.state = PlayState::Playing(chess_lib::GameState::Playing);
-                        self.engine_timer = 0.0;
-                    }
+                    self.make_random();
                 }
         });
     }
@@ -432,8 +721,17 @@ impl PlayTab
                 let board = if let PlayState::Viewing(_) = self.state { &self.view_board } else { &self.board };
 
                 self.render_tiles(&painter, origin, board);
-                self.render_pieces(&painter, origin, board);
-                self.render_moves(&painter, origin, board);
+                self.render_premoves(&painter, origin);
+                let hovered = self.hovered_tile(response.rect, response.hover_pos());
+                self.render_pieces(&painter, origin, board, hovered);
+                if self.visibility.board_input() {
+                    self.render_moves(&painter, origin, board);
+                }
+                // Hint squares, threat arrows and hanging rings would give the hidden pieces away
+                if !self.visibility.hides_position() {
+                    self.render_assist(&painter, origin, board);
+                }
+                self.render_cursor(&painter, origin);
 
                 self.handle_play_state(ui, ctx, response, origin);
                 self.render_game_over(ctx);
@@ -449,13 +747,15 @@ impl PlayTab
 
             ui.vertical_centered(|ui| {
                 let font_size = self.board_size * 0.02;
-                let board = if let PlayState::Viewing(_) = self.state { &self.view_board } else { &self.board };
-                let fen_text = RichText::new(format!("FEN: {}", board.to_fen()))
+                let fen = if self.visibility.hides_position() { "hidden".to_string() } else { self.shown_fen() };
+                let fen_text = RichText::new(format!("FEN: {}", fen))
                     .monospace()
                     .size(font_size);
                 ui.label(fen_text);
 
                 ui.add_space(8.0);
+
+                self.render_move_entry(ui);
             });
         });
     }
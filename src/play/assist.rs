@@ -0,0 +1,180 @@
+use chess_engine::search::find_best_move;
+use chess_lib::{Board, Colour, Move, MoveList, Piece, Tile};
+use egui::{Color32, Painter, Pos2, RichText, Stroke, Ui};
+
+use crate::{
+    attacks::{captures, with_turn},
+    notation::tile_name,
+    play::{state::PlayState, PlayTab},
+};
+
+const HINT: Color32 = Color32::from_rgba_premultiplied(40, 110, 40, 120);
+const THREAT: Color32 = Color32::from_rgba_premultiplied(180, 20, 20, 200);
+const HANGING: Color32 = Color32::from_rgb(255, 140, 0);
+
+/// The engine's move for the position `fen`, revealed a square at a time.
+#[derive(Debug, Clone)]
+pub struct Hint
+{
+    fen: String,
+    pub best: Move,
+    pub show_destination: bool,
+}
+
+/// Learning aids: hints, the opponent's threat and undefended pieces.
+#[derive(Debug, Clone, Default)]
+pub struct Assist
+{
+    pub hint: Option<Hint>,
+    pub show_threats: bool,
+    /// The opponent's best move if it were their turn, for the position `.0`.
+    threat: Option<(String, Option<Move>)>,
+    pub show_hanging: bool,
+    /// Hints asked for by White and Black this game.
+    pub hints_used: [u32; 2],
+    pub note: Option<String>,
+}
+
+/// Pieces of either side that the opponent can take without a recapture on
+/// the same square, kings aside. chess_lib's legal moves decide both, so a
+/// pinned piece neither takes nor defends, and en passant counts.
+pub fn hanging_pieces(board: &Board) -> Vec<Tile> {
+    let mut hanging = Vec::new();
+    for by in [Colour::White, Colour::Black] {
+        let Some(position) = with_turn(board, by) else {
+            continue;
+        };
+        for (m, target) in captures(&position) {
+            if hanging.contains(&target) || position.get_piece_at_tile(target).is_some_and(|(p, _)| p == Piece::King) {
+                continue;
+            }
+            let mut after = position.clone();
+            after.make_move_unchecked(m);
+            let mut replies = MoveList::new();
+            after.generate_legal_moves(after.turn, &mut replies);
+            if !replies.iter().any(|r| r.to() == m.to()) {
+                hanging.push(target);
+            }
+        }
+    }
+    hanging
+}
+
+/// What the side not to move would play if it were their turn. Nothing while
+/// in check, since the threat is already on the board.
+fn threat_move(board: &Board, depth: u8) -> Option<Move> {
+    if board.is_in_check(board.turn) {
+        return None;
+    }
+    let mut swapped = with_turn(board, if board.turn.white() { Colour::Black } else { Colour::White })?;
+    find_best_move(&mut swapped, depth)
+}
+
+fn draw_arrow(painter: &Painter, from: Pos2, to: Pos2, width: f32, colour: Color32) {
+    let dir = (to - from).normalized();
+    let normal = dir.rot90();
+    let head = width * 2.5;
+    let base = to - dir * head;
+    painter.line_segment([from, base], Stroke::new(width, colour));
+    painter.add(egui::Shape::convex_polygon(
+        vec![to, base + normal * head * 0.6, base - normal * head * 0.6],
+        colour,
+        Stroke::NONE,
+    ));
+}
+
+impl PlayTab {
+    /// First press marks the piece the engine would move, a second press in
+    /// the same position its destination. Each new position's hint is counted.
+    pub fn hint(&mut self) {
+        if self.state != PlayState::Playing || self.engine_turn() {
+            self.assist.note = Some("Hints are for your own moves".to_string());
+            return;
+        }
+        let fen = self.board.to_fen();
+        if let Some(hint) = self.assist.hint.as_mut().filter(|h| h.fen == fen) {
+            hint.show_destination = true;
+        } else {
+            let Some(best) = find_best_move(&mut self.board.clone(), self.engine_depth) else {
+                self.assist.note = Some("No move to hint".to_string());
+                return;
+            };
+            self.assist.hint = Some(Hint { fen, best, show_destination: false });
+            self.assist.hints_used[self.board.turn.black() as usize] += 1;
+        }
+        // The squares aren't drawn over a hidden board, so spell the hint out
+        self.assist.note = self.assist.hint.as_ref().filter(|_| self.visibility.hides_position()).map(|h| {
+            if h.show_destination {
+                format!("Hint: {} to {}", tile_name(h.best.from()), tile_name(h.best.to()))
+            } else {
+                format!("Hint: the piece on {}", tile_name(h.best.from()))
+            }
+        });
+    }
+    /// Works out the threat once per position while threats are shown.
+    pub fn update_threat(&mut self) {
+        if !self.assist.show_threats {
+            return;
+        }
+        let fen = self.board.to_fen();
+        if self.assist.threat.as_ref().is_some_and(|(f, _)| *f == fen) {
+            return;
+        }
+        self.assist.threat = Some((fen, threat_move(&self.board, self.engine_depth)));
+    }
+    pub fn render_assist(&self, painter: &Painter, origin: Pos2, board: &Board) {
+        let square = self.board_size / 8.0;
+        let centre = |t: Tile| {
+            let (x, y) = t.get_coords();
+            self.tile_to_screen(x as f32, y as f32, origin).center()
+        };
+
+        if self.assist.show_hanging {
+            for t in hanging_pieces(board) {
+                painter.circle_stroke(centre(t), square * 0.45, Stroke::new(square * 0.06, HANGING));
+            }
+        }
+
+        // Hints and threats belong to the live position, not one being browsed
+        if let PlayState::Viewing(_) = self.state {
+            return;
+        }
+        let fen = board.to_fen();
+        if let Some(hint) = self.assist.hint.as_ref().filter(|h| h.fen == fen) {
+            let mut tiles = vec![hint.best.from()];
+            if hint.show_destination {
+                tiles.push(hint.best.to());
+            }
+            for t in tiles {
+                let (x, y) = t.get_coords();
+                painter.rect_filled(self.tile_to_screen(x as f32, y as f32, origin), 0.0, HINT);
+            }
+        }
+        if let Some((_, Some(threat))) = self.assist.threat.as_ref().filter(|(f, _)| self.assist.show_threats && *f == fen) {
+            draw_arrow(painter, centre(threat.from()), centre(threat.to()), square * 0.12, THREAT);
+        }
+    }
+    pub fn render_assist_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let destination = self.assist.hint.as_ref().is_some_and(|h| h.fen == self.board.to_fen());
+            let label = if destination { "Hint: where to" } else { "Hint" };
+            if ui.button(label).on_hover_text("The piece to move, then on a second press where it goes").clicked() {
+                self.hint();
+            }
+            let [white, black] = self.assist.hints_used;
+            if white + black > 0 {
+                ui.label(RichText::new(format!("Used: White {}, Black {}", white, black)).weak());
+            }
+        });
+        ui.checkbox(&mut self.assist.show_threats, "Show threats")
+            .on_hover_text("The opponent's best move if it were their turn");
+        ui.checkbox(&mut self.assist.show_hanging, "Hanging pieces")
+            .on_hover_text("Attacked pieces with no defender");
+        if self.visibility.hides_position() && (self.assist.show_threats || self.assist.show_hanging) {
+            ui.label(RichText::new("Overlays are off while the board is hidden").weak());
+        }
+        if let Some(note) = &self.assist.note {
+            ui.label(RichText::new(note).weak());
+        }
+    }
+}
@@ -0,0 +1,538 @@
+use chess_lib::{Board, Colour, GameState, Move, Piece, Tile};
+
+use crate::{chess960, diagram, notation, play::PlayTab};
+
+/// A move of the game: one of chess_lib's, or a castle the app plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMove
+{
+    Lib(Move),
+    Castle { kingside: bool },
+}
+
+/// Chess960 games from start positions chess_lib can't castle in, where the
+/// king or a castling rook is off the e, a and h-files. chess_lib's board holds
+/// no castling rights in these games and only ever sees its own moves. The app
+/// keeps the rights and the game's moves, castles included, and gets to any
+/// position by replaying them from the start.
+#[derive(Clone)]
+pub struct Castling960
+{
+    /// Castling rook files: White short, White long, Black short, Black long.
+    rooks: [Option<u8>; 4],
+    /// King files at the start, White then Black.
+    kings: [Option<u8>; 2],
+    /// The position the game started from.
+    start: Board,
+    /// Every move of the game with its SAN, oldest first.
+    moves: Vec<(GameMove, String)>,
+}
+
+fn right_index(colour: Colour, kingside: bool) -> usize {
+    colour.black() as usize * 2 + !kingside as usize
+}
+
+fn back_rank(colour: Colour) -> u8 {
+    if colour.white() { 0 } else { 7 }
+}
+
+fn opponent(colour: Colour) -> Colour {
+    if colour.white() { Colour::Black } else { Colour::White }
+}
+
+/// `O-O` as kingside, `O-O-O` as queenside, with zeros or check marks.
+fn castle_san(text: &str) -> Option<bool> {
+    let text: String = text.trim().trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+    match text.as_str() {
+        "O-O" => Some(true),
+        "O-O-O" => Some(false),
+        _ => None,
+    }
+}
+
+/// `board` with `colour`'s king and rook lifted off and `placed` put down,
+/// followed by the FEN fields in `rest`. Reading it back from a FEN lets
+/// chess_lib work out check and legal moves for it.
+fn rearranged(board: &Board, colour: Colour, king: Tile, rook: Tile, placed: &[(Piece, Tile)], rest: &str) -> Option<Board> {
+    let mut moved = board.clone();
+    let player = if colour.white() { &mut moved.white } else { &mut moved.black };
+    player.remove_piece_type(Piece::King, king);
+    player.remove_piece_type(Piece::Rook, rook);
+    for &(piece, tile) in placed {
+        player.place_piece(piece, tile);
+    }
+    let fen = moved.to_fen();
+    Board::new_from_fen(&format!("{} {}", fen.split_whitespace().next()?, rest)).ok()
+}
+
+/// The position after the side to move castles with the king on file `king`
+/// and the rook on file `rook`, if it may, with the castle's SAN. The king goes
+/// to the g or c-file, the rook beside it on the f or d-file.
+fn castled(board: &Board, king: u8, rook: u8, kingside: bool) -> Option<(Board, String)> {
+    let colour = board.turn;
+    let rank = back_rank(colour);
+    let tile = |x: u8| Tile::new_xy(x, rank);
+    let (king_tile, rook_tile) = (tile(king)?, tile(rook)?);
+    let (king_to, rook_to) = if kingside { (6, 5) } else { (2, 3) };
+    if board.get_piece_at_tile(king_tile) != Some((Piece::King, colour))
+        || board.get_piece_at_tile(rook_tile) != Some((Piece::Rook, colour))
+    {
+        return None;
+    }
+
+    // Everything the two pieces cross or land on is empty but for themselves
+    let low = king.min(rook).min(king_to).min(rook_to);
+    let high = king.max(rook).max(king_to).max(rook_to);
+    let blocked = (low..=high)
+        .filter(|&x| x != king && x != rook)
+        .filter_map(tile)
+        .any(|t| board.get_piece_at_tile(t).is_some());
+    if blocked {
+        return None;
+    }
+    // The king can't castle out of, through or into check, which chess_lib
+    // judges with the king standing on each square in turn
+    let turn = if colour.white() { "w" } else { "b" };
+    for x in king.min(king_to)..=king.max(king_to) {
+        let standing = rearranged(board, colour, king_tile, rook_tile, &[(Piece::King, tile(x)?)], &format!("{} - - 0 1", turn))?;
+        if standing.is_in_check(colour) {
+            return None;
+        }
+    }
+
+    let fen = board.to_fen();
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let halfmove = fields.get(4).and_then(|n| n.parse::<u32>().ok()).unwrap_or(0) + 1;
+    let fullmove = fields.get(5).and_then(|n| n.parse::<u32>().ok()).unwrap_or(1) + colour.black() as u32;
+    let next = if colour.white() { "b" } else { "w" };
+    let placed = [(Piece::King, tile(king_to)?), (Piece::Rook, tile(rook_to)?)];
+    let after = rearranged(board, colour, king_tile, rook_tile, &placed, &format!("{} - - {} {}", next, halfmove, fullmove))?;
+
+    let mut san = if kingside { "O-O" } else { "O-O-O" }.to_string();
+    if matches!(after.get_state(), GameState::Checkmate(_)) {
+        san.push('#');
+    } else if after.is_in_check(opponent(colour)) {
+        san.push('+');
+    }
+    Some((after, san))
+}
+
+impl Castling960
+{
+    /// A game from `fen` when its castling rights are ones chess_lib can't
+    /// play, else `None`.
+    pub fn from_fen(fen: &str) -> Result<Option<Self>, String> {
+        if chess960::orthodox_rights(fen) {
+            return Ok(None);
+        }
+        let start = Board::new_from_fen(&chess960::with_castling(fen, "-")).map_err(|e| format!("Invalid FEN: {}", e))?;
+        Ok(Some(Self {
+            rooks: chess960::castling_files(fen),
+            kings: chess960::king_files(fen),
+            start,
+            moves: Vec::new(),
+        }))
+    }
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+    /// How many half-moves have been played.
+    pub fn plies(&self) -> usize {
+        self.moves.len()
+    }
+    pub fn sans(&self) -> impl Iterator<Item = &String> {
+        self.moves.iter().map(|(_, san)| san)
+    }
+    pub fn last(&self) -> Option<GameMove> {
+        self.moves.last().map(|&(m, _)| m)
+    }
+    /// Who made the move at index `ply`.
+    fn mover(&self, ply: usize) -> Colour {
+        [self.start.turn, opponent(self.start.turn)][ply % 2]
+    }
+    /// The rights left after the first `ply` half-moves. A right goes once
+    /// anything moves from or to its king's or rook's square.
+    fn rights(&self, ply: usize) -> [bool; 4] {
+        let mut rights = self.rooks.map(|r| r.is_some());
+        for (p, &(m, _)) in self.moves.iter().enumerate().take(ply) {
+            let m = match m {
+                GameMove::Lib(m) => m,
+                GameMove::Castle { .. } => {
+                    let i = right_index(self.mover(p), true);
+                    rights[i] = false;
+                    rights[i + 1] = false;
+                    continue;
+                }
+            };
+            for (i, right) in rights.iter_mut().enumerate() {
+                let rank = if i < 2 { 0 } else { 7 };
+                let squares = [self.kings[i / 2], self.rooks[i]].map(|f| f.and_then(|f| Tile::new_xy(f, rank)));
+                let touched = |t: Tile| squares.contains(&Some(t));
+                if touched(m.from()) || touched(m.to()) {
+                    *right = false;
+                }
+            }
+        }
+        rights
+    }
+    /// The Shredder-FEN castling field after the first `ply` half-moves.
+    pub fn field(&self, ply: usize) -> String {
+        let rights = self.rights(ply);
+        let files = std::array::from_fn(|i| self.rooks[i].filter(|_| rights[i]));
+        chess960::castling_field(&files)
+    }
+    /// The castling field the game started with.
+    pub fn start_field(&self) -> String {
+        chess960::castling_field(&self.rooks)
+    }
+    /// The position after the side to move castles in `board`, the game's
+    /// latest position, if it may, with the castle's SAN.
+    pub fn castled(&self, board: &Board, kingside: bool) -> Option<(Board, String)> {
+        let colour = board.turn;
+        let i = right_index(colour, kingside);
+        if !self.rights(self.moves.len())[i] {
+            return None;
+        }
+        castled(board, self.kings[colour.black() as usize]?, self.rooks[i]?, kingside)
+    }
+    /// Castles in `board`, the game's latest position, and gives the position after.
+    pub fn castle(&mut self, board: &Board, kingside: bool) -> Option<Board> {
+        self.record(board);
+        let (after, san) = self.castled(board, kingside)?;
+        self.moves.push((GameMove::Castle { kingside }, san));
+        Some(after)
+    }
+    /// Logs the moves chess_lib has played on `board`, the game's latest
+    /// position, since they were last looked at. Its history starts over after
+    /// each castle, since the app sets up the position after one afresh.
+    pub fn record(&mut self, board: &Board) {
+        let since_castle = self.moves.iter().rposition(|(m, _)| matches!(m, GameMove::Castle { .. })).map_or(0, |i| i + 1);
+        let logged = self.moves.len() - since_castle;
+        for h in board.history.iter().skip(logged) {
+            self.moves.push((GameMove::Lib(h.last_move), h.san_string.clone()));
+        }
+    }
+    /// Takes back the last move, giving it with its SAN.
+    pub fn undo(&mut self) -> Option<(GameMove, String)> {
+        self.moves.pop()
+    }
+    /// Plays `m` on `board`, a position of this game after `ply` half-moves.
+    fn replay(&self, board: &mut Board, ply: usize, m: GameMove) {
+        match m {
+            GameMove::Lib(m) => board.make_move_unchecked(m),
+            GameMove::Castle { kingside } => {
+                let i = right_index(self.mover(ply), kingside);
+                let after = self.kings[i / 2].zip(self.rooks[i]).and_then(|(king, rook)| castled(board, king, rook, kingside));
+                if let Some((after, _)) = after {
+                    *board = after;
+                }
+            }
+        }
+    }
+    /// The position after the first `ply` half-moves, replayed from the start.
+    pub fn position_at(&self, ply: usize) -> Board {
+        let mut board = self.start.clone();
+        for (p, &(m, _)) in self.moves.iter().enumerate().take(ply) {
+            self.replay(&mut board, p, m);
+        }
+        board
+    }
+    /// Every position of the game from the start.
+    pub fn positions(&self) -> Vec<Board> {
+        let mut board = self.start.clone();
+        let mut positions = vec![board.clone()];
+        for (p, &(m, _)) in self.moves.iter().enumerate() {
+            self.replay(&mut board, p, m);
+            positions.push(board.clone());
+        }
+        positions
+    }
+    /// The moves in UCI, castles as king takes rook as in Chess960 UCI.
+    pub fn uci(&self) -> Result<Vec<String>, String> {
+        let mut board = self.start.clone();
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for (p, &(m, _)) in self.moves.iter().enumerate() {
+            let uci = match m {
+                GameMove::Lib(m) => notation::move_uci(&board, m),
+                GameMove::Castle { kingside } => {
+                    let colour = self.mover(p);
+                    let rank = back_rank(colour);
+                    let king = self.kings[colour.black() as usize].and_then(|f| Tile::new_xy(f, rank));
+                    let rook = self.rooks[right_index(colour, kingside)].and_then(|f| Tile::new_xy(f, rank));
+                    king.zip(rook).map(|(k, r)| format!("{}{}", notation::tile_name(k), notation::tile_name(r)))
+                }
+            };
+            moves.push(uci.ok_or(format!("Move {} is illegal", p + 1))?);
+            self.replay(&mut board, p, m);
+        }
+        Ok(moves)
+    }
+    /// The moves as PGN movetext, numbered from the start position's move number.
+    pub fn movetext(&self) -> String {
+        let fen = self.start.to_fen();
+        let first = fen.split_whitespace().nth(5).and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+        let black_first = self.start.turn.black();
+        let mut words = Vec::new();
+        for (p, san) in self.sans().enumerate() {
+            let number = first + (p + black_first as usize) / 2;
+            if p == 0 && black_first {
+                words.push(format!("{}...", number));
+            } else if self.mover(p).white() {
+                words.push(format!("{}.", number));
+            }
+            words.push(san.clone());
+        }
+        words.join(" ")
+    }
+}
+
+impl PlayTab {
+    /// Starts a new game from `fen`. In Chess960 its castling field may name
+    /// rook files, and rights chess_lib can't play are kept by the app.
+    pub fn set_position_fen(&mut self, fen: &str) -> Result<(), String> {
+        let castling = if self.chess960 { Castling960::from_fen(fen)? } else { None };
+        let board = match &castling {
+            Some(castling) => castling.start().clone(),
+            None => Board::new_from_fen(&chess960::lib_fen(fen)).map_err(|e| format!("Invalid FEN: {}", e))?,
+        };
+        self.set_position(board);
+        self.castle960 = castling;
+        Ok(())
+    }
+    /// Whether the app rather than chess_lib plays this game's castles.
+    pub fn app_castling(&self) -> bool {
+        self.castle960.is_some()
+    }
+    /// How many half-moves have been played.
+    pub fn ply_count(&self) -> usize {
+        match &self.castle960 {
+            Some(castling) => castling.plies(),
+            None => self.board.history.len(),
+        }
+    }
+    /// The SAN of every move played.
+    pub fn move_sans(&self) -> Vec<String> {
+        match &self.castle960 {
+            Some(castling) => castling.sans().cloned().collect(),
+            None => self.board.history.iter().map(|h| h.san_string.clone()).collect(),
+        }
+    }
+    /// The last move played.
+    pub fn last_game_move(&self) -> Option<GameMove> {
+        match &self.castle960 {
+            Some(castling) => castling.last(),
+            None => self.board.history.last().map(|h| GameMove::Lib(h.last_move)),
+        }
+    }
+    /// The castle a king-takes-own-rook move stands for, as kingside or not.
+    pub fn castle_squares(&self, from: Tile, to: Tile) -> Option<bool> {
+        let turn = self.board.turn;
+        if self.board.get_piece_at_tile(from) != Some((Piece::King, turn))
+            || self.board.get_piece_at_tile(to) != Some((Piece::Rook, turn))
+            || from.get_coords().1 != to.get_coords().1
+        {
+            return None;
+        }
+        Some(to.get_coords().0 > from.get_coords().0)
+    }
+    /// The castle typed as `O-O`, `O-O-O` or UCI king takes rook, when the app plays castles.
+    fn castle_text(&self, text: &str) -> Option<bool> {
+        if !self.app_castling() {
+            return None;
+        }
+        let text = text.trim();
+        castle_san(text).or_else(|| {
+            let from = notation::parse_tile(text.get(0..2)?)?;
+            let to = notation::parse_tile(text.get(2..)?)?;
+            self.castle_squares(from, to)
+        })
+    }
+    /// Plays the side to move's castle if it's legal.
+    pub fn castle(&mut self, kingside: bool) -> bool {
+        let Some(castling) = &mut self.castle960 else {
+            return false;
+        };
+        let Some(after) = castling.castle(&self.board, kingside) else {
+            return false;
+        };
+        self.board = after;
+        self.finish_move();
+        true
+    }
+    /// The castles open to the side to move, as kingside or not.
+    pub fn legal_castles(&self) -> Vec<bool> {
+        let Some(castling) = &self.castle960 else {
+            return Vec::new();
+        };
+        [true, false].into_iter().filter(|&k| castling.castled(&self.board, k).is_some()).collect()
+    }
+    /// Plays a move given in SAN or UCI, castles included. False if it isn't legal.
+    pub fn play_text(&mut self, text: &str) -> bool {
+        if let Some(kingside) = self.castle_text(text) {
+            return self.castle(kingside);
+        }
+        match notation::parse_move(&self.board, text) {
+            Some(m) => {
+                self.play_move(m);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Takes back the last move of a game the app castles in, setting the
+    /// board up again by replaying the rest.
+    pub fn undo_app_move(&mut self) -> Option<(GameMove, String)> {
+        let castling = self.castle960.as_mut()?;
+        let undone = castling.undo()?;
+        self.board = castling.position_at(castling.plies());
+        Some(undone)
+    }
+    /// The position after the game's first `ply` half-moves.
+    pub fn position_at(&self, ply: usize) -> Board {
+        match &self.castle960 {
+            Some(castling) => castling.position_at(ply),
+            None => {
+                let mut board = self.board.clone();
+                while board.history.len() > ply {
+                    board.undo_move();
+                }
+                board
+            }
+        }
+    }
+    /// Every position of the game from the start, for animations.
+    pub fn game_positions(&self) -> Vec<Board> {
+        match &self.castle960 {
+            Some(castling) => castling.positions(),
+            None => diagram::game_positions(&self.board),
+        }
+    }
+    /// The game's moves in UCI, castles the app played as king takes rook.
+    pub fn game_uci(&self) -> Result<Vec<String>, String> {
+        if let Some(castling) = &self.castle960 {
+            return castling.uci();
+        }
+        let mut board = self.position_at(0);
+        let mut moves = Vec::with_capacity(self.board.history.len());
+        for (ply, h) in self.board.history.iter().enumerate() {
+            moves.push(notation::move_uci(&board, h.last_move).ok_or(format!("Move {} is illegal", ply + 1))?);
+            board.make_move_unchecked(h.last_move);
+        }
+        Ok(moves)
+    }
+    /// The FEN of `board`, the position after `ply` half-moves, with the
+    /// castling rights in Shredder-FEN.
+    pub fn chess960_fen(&self, board: &Board, ply: usize) -> String {
+        let fen = board.to_fen();
+        match &self.castle960 {
+            Some(castling) => chess960::with_castling(&fen, &castling.field(ply)),
+            None => chess960::to_shredder_fen(&fen),
+        }
+    }
+    /// The FEN the game started from, in Shredder-FEN for Chess960.
+    pub fn start_fen_tag(&self) -> Option<String> {
+        let fen = self.start_fen.as_ref()?;
+        Some(match &self.castle960 {
+            Some(castling) => chess960::with_castling(fen, &castling.start_field()),
+            None if self.chess960 => chess960::to_shredder_fen(fen),
+            None => fen.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// King on c1 with rooks on b1 and g1, so chess_lib can't castle in it.
+    const FEN: &str = "1rk3r1/pppppppp/8/8/8/8/PPPPPPPP/1RK3R1 w GBgb - 0 1";
+
+    fn game() -> Castling960 {
+        Castling960::from_fen(FEN).unwrap().unwrap()
+    }
+
+    /// Plays `uci` with chess_lib on the game's latest position.
+    fn play(game: &mut Castling960, uci: &str) -> Board {
+        let mut board = game.position_at(game.plies());
+        let m = notation::parse_move(&board, uci).unwrap();
+        board.make_move_unchecked(m);
+        game.record(&board);
+        board
+    }
+
+    fn piece(board: &Board, square: &str) -> Option<(Piece, Colour)> {
+        board.get_piece_at_tile(notation::parse_tile(square).unwrap())
+    }
+
+    #[test]
+    fn orthodox_rights_are_left_to_chess_lib() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(Castling960::from_fen(fen).unwrap().is_none());
+    }
+
+    #[test]
+    fn castles_to_the_g_and_c_files() {
+        let mut game = game();
+        let start = game.start().clone();
+        let after = game.castle(&start, true).unwrap();
+        assert_eq!(piece(&after, "g1"), Some((Piece::King, Colour::White)));
+        assert_eq!(piece(&after, "f1"), Some((Piece::Rook, Colour::White)));
+        assert_eq!(piece(&after, "c1"), None);
+        assert_eq!(after.turn, Colour::Black);
+        assert_eq!(game.field(1), "gb");
+
+        let after = game.castle(&after, false).unwrap();
+        assert_eq!(piece(&after, "c8"), Some((Piece::King, Colour::Black)));
+        assert_eq!(piece(&after, "d8"), Some((Piece::Rook, Colour::Black)));
+        assert_eq!(piece(&after, "b8"), None);
+        assert_eq!(game.field(2), "-");
+        assert_eq!(game.sans().collect::<Vec<_>>(), ["O-O", "O-O-O"]);
+    }
+
+    #[test]
+    fn refuses_blocked_castles_and_spent_rights() {
+        let blocked = Castling960::from_fen("1rkb2r1/pppppppp/8/8/8/8/PPPPPPPP/1RKB2R1 w GBgb - 0 1").unwrap().unwrap();
+        assert!(blocked.castled(blocked.start(), true).is_none());
+
+        let mut game = game();
+        play(&mut game, "g1h1");
+        let board = play(&mut game, "a7a6");
+        assert!(game.castled(&board, true).is_none());
+        assert!(game.castled(&board, false).is_some());
+    }
+
+    #[test]
+    fn undo_and_replay_go_through_castles() {
+        let mut game = game();
+        play(&mut game, "d2d4");
+        let before = play(&mut game, "d7d5");
+        let after = game.castle(&before, true).unwrap();
+        let after = {
+            let mut board = after;
+            let m = notation::parse_move(&board, "e7e6").unwrap();
+            board.make_move_unchecked(m);
+            game.record(&board);
+            board
+        };
+        assert_eq!(game.plies(), 4);
+        assert_eq!(game.position_at(4).to_fen(), after.to_fen());
+        assert_eq!(game.position_at(2).to_fen(), before.to_fen());
+        assert_eq!(game.positions().len(), 5);
+
+        assert_eq!(game.undo().map(|(_, san)| san), Some("e6".to_string()));
+        assert_eq!(game.undo(), Some((GameMove::Castle { kingside: true }, "O-O".to_string())));
+        assert_eq!(game.position_at(game.plies()).to_fen(), before.to_fen());
+        assert_eq!(game.field(game.plies()), "GBgb");
+    }
+
+    #[test]
+    fn exports_castles_as_king_takes_rook() {
+        let mut game = game();
+        let board = play(&mut game, "d2d4");
+        let after = game.castle(&board, false).unwrap();
+        assert!(piece(&after, "c8").is_some());
+        assert_eq!(game.uci().unwrap(), ["d2d4", "c8b8"]);
+        assert_eq!(game.movetext(), "1. d4 O-O-O");
+    }
+}
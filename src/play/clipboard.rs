@@ -0,0 +1,168 @@
+use chess_lib::Board;
+use egui::{Context, RichText, Ui};
+
+use crate::{
+    chess960,
+    clipboard::{classify, copy_pressed, pasted_text, Pasted},
+    pgn::PgnGame,
+    play::{
+        game_info::{GameInfo, RESULTS},
+        state::PlayState,
+        PlayTab,
+    },
+    share::{decode_moves, encode_moves, make_link, share_base, SharedLink},
+};
+
+/// Tags with a field of their own in `GameInfo` or written from the game itself.
+const KNOWN_TAGS: [&str; 14] = [
+    "Event", "Site", "Date", "Round", "White", "Black", "Result", "TimeControl", "SetUp", "FEN", "Variant", "Termination",
+    "WhiteHints", "BlackHints",
+];
+
+impl PlayTab {
+    /// FEN of the board on screen, in Shredder-FEN for Chess960.
+    pub fn shown_fen(&self) -> String {
+        let (board, ply) = match self.state {
+            PlayState::Viewing(pos) => (&self.view_board, pos),
+            _ => (&self.board, self.ply_count()),
+        };
+        if self.chess960 { self.chess960_fen(board, ply) } else { board.to_fen() }
+    }
+    pub fn copy_fen(&mut self, ctx: &Context) {
+        ctx.copy_text(self.shown_fen());
+        self.clipboard_note = Some("Copied the FEN".to_string());
+    }
+    pub fn copy_pgn(&mut self, ctx: &Context) {
+        ctx.copy_text(self.game_pgn());
+        self.clipboard_note = Some("Copied the PGN".to_string());
+    }
+    /// Sets up a pasted FEN, or loads a pasted game.
+    pub fn paste(&mut self, text: &str) {
+        let result = classify(text).and_then(|pasted| match pasted {
+            Pasted::Fen(_, fen) => {
+                self.chess960 = chess960::is_chess960_fen(&fen);
+                self.set_position_fen(&fen).map(|()| "Set up the pasted position".to_string())
+            }
+            Pasted::Pgn(game) => self.load_game(&game).map(|()| {
+                format!("Loaded the pasted game, {} moves", game.moves.len())
+            }),
+        });
+        self.clipboard_note = Some(result.unwrap_or_else(|e| e));
+    }
+    /// Replays `game` from its start and takes its tags into the game info.
+    /// Stops at the first move that doesn't fit, keeping the moves before it.
+    pub fn load_game(&mut self, game: &PgnGame) -> Result<(), String> {
+        self.chess960 = game.tag("Variant").is_some_and(|v| v.eq_ignore_ascii_case("chess960"))
+            || game.tag("FEN").is_some_and(chess960::is_chess960_fen);
+        match game.tag("FEN") {
+            Some(fen) => self.set_position_fen(fen)?,
+            None => self.set_position(Board::new()),
+        }
+
+        let tag = |name: &str| {
+            let value = game.tag(name).unwrap_or_default();
+            if value == "?" { String::new() } else { value.to_string() }
+        };
+        self.game_info = GameInfo {
+            event: tag("Event"),
+            site: tag("Site"),
+            date: tag("Date"),
+            round: tag("Round"),
+            white: tag("White"),
+            black: tag("Black"),
+            // Keeps a resignation or adjudication the moves alone don't show
+            result: game.tag("Result").filter(|r| *r != "*" && RESULTS.contains(r)).map(|r| r.to_string()),
+            time_control: tag("TimeControl"),
+            custom: game
+                .tags
+                .iter()
+                .filter(|(name, _)| !KNOWN_TAGS.contains(&name.as_str()))
+                .cloned()
+                .collect(),
+        };
+        let hints = |name: &str| game.tag(name).and_then(|n| n.parse().ok()).unwrap_or(0);
+        self.assist.hints_used = [hints("WhiteHints"), hints("BlackHints")];
+
+        self.replay(&game.moves).map_err(|(_, san)| format!("Stopped at illegal move {}", san))
+    }
+    /// Plays the moves of a game from elsewhere, which the statistics shouldn't
+    /// count if it ends in them. Stops at the first illegal move and gives it with its index.
+    fn replay<'a>(&mut self, moves: &'a [String]) -> Result<(), (usize, &'a String)> {
+        self.game_recorded = true;
+        let illegal = moves.iter().enumerate().find(|(_, m)| !self.play_text(m));
+        // Play carried on from here is this app's and may still be recorded
+        self.game_recorded = self.game_over().is_some();
+        illegal.map_or(Ok(()), Err)
+    }
+    /// A link to the web build that opens this game.
+    pub fn share_link(&self) -> Result<String, String> {
+        let base = share_base().ok_or("There's no address to build links on")?;
+        let moves = self.game_uci()?;
+        let link = SharedLink {
+            tab: None,
+            fen: self.start_fen_tag(),
+            moves: if moves.is_empty() { None } else { Some(encode_moves(&moves)?) },
+        };
+        Ok(make_link(&base, &link))
+    }
+    pub fn copy_share_link(&mut self, ctx: &Context) {
+        self.clipboard_note = Some(match self.share_link() {
+            Ok(link) => {
+                ctx.copy_text(link);
+                "Copied a link to this game".to_string()
+            }
+            Err(e) => format!("Can't make a link: {}", e),
+        });
+    }
+    /// Starts the game a share link describes, as Chess960 if its FEN's castling says so.
+    pub fn open_link(&mut self, link: &SharedLink) -> Result<(), String> {
+        let moves = match &link.moves {
+            Some(text) => decode_moves(text)?,
+            None => Vec::new(),
+        };
+        match &link.fen {
+            Some(fen) => {
+                self.chess960 = chess960::is_chess960_fen(fen);
+                self.set_position_fen(fen)?;
+            }
+            None => {
+                self.chess960 = false;
+                self.set_position(Board::new());
+            }
+        }
+        self.replay(&moves).map_err(|(ply, uci)| format!("The link's move {} ({}) is illegal", ply + 1, uci))
+    }
+    /// Ctrl+C copies the FEN, Ctrl+V pastes, and a Paste button's read lands here.
+    pub fn handle_clipboard(&mut self, ctx: &Context) {
+        if copy_pressed(ctx) {
+            self.copy_fen(ctx);
+        }
+        if let Some(text) = pasted_text(ctx) {
+            self.paste(&text);
+        }
+        match self.clipboard.take() {
+            Some(Ok(text)) => self.paste(&text),
+            Some(Err(e)) => self.clipboard_note = Some(e),
+            None => (),
+        }
+    }
+    pub fn render_clipboard_buttons(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Copy FEN").clicked() {
+                self.copy_fen(ui.ctx());
+            }
+            if ui.button("Copy PGN").clicked() {
+                self.copy_pgn(ui.ctx());
+            }
+            if ui.button("Paste").on_hover_text("A FEN sets up the position, PGN loads the game").clicked() {
+                self.clipboard.request(ui.ctx());
+            }
+        });
+        if share_base().is_some() && ui.button("Copy share link").on_hover_text("A link that opens this game").clicked() {
+            self.copy_share_link(ui.ctx());
+        }
+        if let Some(note) = &self.clipboard_note {
+            ui.label(RichText::new(note).weak());
+        }
+    }
+}
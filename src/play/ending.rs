@@ -0,0 +1,137 @@
+use chess_lib::{Board, Colour, GameState};
+
+use crate::{
+    analysis::material_balance,
+    play::{state::{Engine, PlayState, Termination}, PlayTab},
+};
+
+fn halfmove_clock(board: &Board) -> usize {
+    board.to_fen().split_whitespace().nth(4).and_then(|n| n.parse().ok()).unwrap_or(0)
+}
+
+/// Placement, side to move, castling and en passant: the parts of the FEN
+/// that decide whether two positions are the same for repetition.
+fn position_key(board: &Board) -> String {
+    board.to_fen().split_whitespace().take(4).collect::<Vec<_>>().join(" ")
+}
+
+impl PlayTab {
+    /// How often the current position has occurred, looking back to the last capture or pawn move.
+    pub fn repetitions(&self) -> usize {
+        let key = position_key(&self.board);
+        // chess_lib's history starts over after a castle the app played, and no
+        // earlier position can come back after one anyway
+        let plies = halfmove_clock(&self.board).min(self.board.history.len());
+        let mut board = self.board.clone();
+        let mut count = 1;
+        for _ in 0..plies {
+            board.undo_move();
+            if position_key(&board) == key {
+                count += 1;
+            }
+        }
+        count
+    }
+    /// The state of the game at the latest position. chess_lib reports fifty moves and
+    /// threefold repetition as finished games, but those only end the game when claimed,
+    /// or on their own at seventy-five moves and fivefold repetition.
+    pub fn position_state(&self) -> PlayState {
+        if let Some(termination) = self.termination {
+            return PlayState::Over(termination);
+        }
+        let termination = match self.board.get_state() {
+            GameState::Playing => None,
+            GameState::Checkmate(loser) => Some(Termination::Checkmate(loser)),
+            GameState::Stalemate(_) => Some(Termination::Stalemate),
+            GameState::InsufficientMaterial => Some(Termination::InsufficientMaterial),
+            GameState::FiftyMoveRule | GameState::ThreeRepetition => {
+                if halfmove_clock(&self.board) >= 150 {
+                    Some(Termination::SeventyFiveMoves)
+                } else if self.repetitions() >= 5 {
+                    Some(Termination::FivefoldRepetition)
+                } else {
+                    None
+                }
+            }
+        };
+        termination.map_or(PlayState::Playing, PlayState::Over)
+    }
+    /// How the game ended, if it has.
+    pub fn game_over(&self) -> Option<Termination> {
+        match self.position_state() {
+            PlayState::Over(termination) => Some(termination),
+            _ => None,
+        }
+    }
+    /// The draw that can be claimed in the current position, if any.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.game_over().is_some() {
+            return None;
+        }
+        match self.board.get_state() {
+            GameState::FiftyMoveRule => Some(Termination::FiftyMoveClaim),
+            GameState::ThreeRepetition => Some(Termination::RepetitionClaim),
+            _ => None,
+        }
+    }
+    /// The side resigning or offering a draw: the human against the engine,
+    /// otherwise whoever is to move.
+    pub fn acting_colour(&self) -> Colour {
+        self.human_colour().unwrap_or(self.board.turn)
+    }
+    /// Whether a human is at the board to resign or offer a draw.
+    pub fn can_concede(&self) -> bool {
+        self.engine_plays != Engine::Both && self.game_over().is_none()
+    }
+    /// Ends the game for a reason the board can't see by itself.
+    pub fn end_game(&mut self, termination: Termination) {
+        self.termination = Some(termination);
+        self.state = PlayState::Over(termination);
+        self.view_board = self.board.clone();
+        self.draw_offer = None;
+        self.selected = None;
+        self.clear_premoves();
+        self.record_game();
+    }
+    pub fn resign(&mut self) {
+        if self.can_concede() {
+            self.end_game(Termination::Resignation(self.acting_colour()));
+        }
+    }
+    pub fn claim_draw(&mut self) {
+        if let Some(termination) = self.claimable_draw() {
+            self.end_game(termination);
+        }
+    }
+    /// Offers a draw. The engine answers straight away, a human opponent
+    /// through `answer_draw`.
+    pub fn offer_draw(&mut self) {
+        if !self.can_concede() || self.draw_offer.is_some() {
+            return;
+        }
+        let colour = self.acting_colour();
+        if self.engine_plays == Engine::Neither {
+            self.draw_offer = Some(colour);
+            return;
+        }
+        // The engine takes a draw unless it is ahead on material
+        let balance = material_balance(&self.board);
+        let engine_ahead = if colour.white() { balance < 0 } else { balance > 0 };
+        if engine_ahead {
+            self.draw_note = Some("The engine declines the draw".to_string());
+        } else {
+            self.end_game(Termination::Agreement);
+        }
+    }
+    pub fn answer_draw(&mut self, accept: bool) {
+        if self.draw_offer.is_none() {
+            return;
+        }
+        if accept {
+            self.end_game(Termination::Agreement);
+        } else {
+            self.draw_offer = None;
+            self.draw_note = Some("Draw offer declined".to_string());
+        }
+    }
+}
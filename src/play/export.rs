@@ -0,0 +1,190 @@
+use egui::{Context, DragValue, RichText};
+
+use crate::{
+    diagram::{self, raster, svg, DiagramOptions},
+    play::{state::PlayState, PlayTab},
+};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ImageFormat
+{
+    Png,
+    Svg,
+}
+
+/// Choices in the export dialog, kept between exports.
+#[derive(Debug, Clone)]
+pub struct ExportSettings
+{
+    pub format: ImageFormat,
+    pub size: u32,
+    pub coordinates: bool,
+    pub highlight_last_move: bool,
+    pub last_move_arrow: bool,
+    /// Square pairs such as `e2e4 g1f3`.
+    pub arrows: String,
+    /// Squares such as `e4 d5`.
+    pub highlights: String,
+    pub gif_size: u32,
+    pub gif_delay_ms: u32,
+    pub message: Option<String>,
+}
+impl Default for ExportSettings
+{
+    fn default() -> Self {
+        Self {
+            format: ImageFormat::Png,
+            size: 480,
+            coordinates: true,
+            highlight_last_move: true,
+            last_move_arrow: false,
+            arrows: String::new(),
+            highlights: String::new(),
+            gif_size: 320,
+            gif_delay_ms: 800,
+            message: None,
+        }
+    }
+}
+
+impl PlayTab {
+    /// The board on screen: the viewed position while browsing history.
+    fn shown_board(&self) -> &chess_lib::Board {
+        if let PlayState::Viewing(_) = self.state { &self.view_board } else { &self.board }
+    }
+    fn diagram_options(&self, size: u32) -> Result<DiagramOptions, String> {
+        let settings = &self.export;
+        let mut arrows = diagram::parse_arrows(&settings.arrows)?;
+        if let Some(h) = self.shown_board().history.last().filter(|_| settings.last_move_arrow) {
+            arrows.push((h.last_move.from(), h.last_move.to()));
+        }
+        Ok(DiagramOptions {
+            size,
+            flipped: self.flipped,
+            coordinates: settings.coordinates,
+            highlight_last_move: settings.highlight_last_move,
+            highlights: diagram::parse_squares(&settings.highlights)?,
+            arrows,
+        })
+    }
+    pub fn export_image(&mut self) {
+        let result = self.diagram_options(self.export.size).and_then(|options| match self.export.format {
+            ImageFormat::Png => raster::board_png(self.shown_board(), &options).map(|bytes| (bytes, "png")),
+            ImageFormat::Svg => Ok((svg::board_svg(self.shown_board(), &options).into_bytes(), "svg")),
+        });
+        self.export.message = Some(match result {
+            Ok((bytes, extension)) => save_file(&format!("position.{}", extension), extension, &bytes),
+            Err(e) => e,
+        });
+    }
+    pub fn export_gif(&mut self) {
+        let result = self
+            .diagram_options(self.export.gif_size)
+            .and_then(|options| raster::game_gif(&self.game_positions(), &options, self.export.gif_delay_ms));
+        self.export.message = Some(match result {
+            Ok(bytes) => save_file("game.gif", "gif", &bytes),
+            Err(e) => e,
+        });
+    }
+    pub fn render_export(&mut self, ctx: &Context) {
+        if !self.show_export {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let settings = &mut self.export;
+                ui.heading("Image");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut settings.format, ImageFormat::Png, "PNG");
+                    ui.radio_value(&mut settings.format, ImageFormat::Svg, "SVG");
+                    ui.label("Size");
+                    ui.add(DragValue::new(&mut settings.size).range(64..=2048).suffix(" px"));
+                });
+                ui.checkbox(&mut settings.coordinates, "Coordinates");
+                ui.checkbox(&mut settings.highlight_last_move, "Highlight last move");
+                ui.checkbox(&mut settings.last_move_arrow, "Arrow for last move");
+                ui.horizontal(|ui| {
+                    ui.label("Arrows");
+                    ui.add(egui::TextEdit::singleline(&mut settings.arrows).hint_text("e2e4 g1f3"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Highlights");
+                    ui.add(egui::TextEdit::singleline(&mut settings.highlights).hint_text("e4 d5"));
+                });
+                ui.label(RichText::new("Exports the position on the board, or the one being viewed").weak());
+                let export_image = ui.button("Export image").clicked();
+
+                ui.separator();
+                ui.heading("Animated GIF");
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    ui.add(DragValue::new(&mut settings.gif_size).range(64..=1024).suffix(" px"));
+                    ui.label("Delay");
+                    ui.add(DragValue::new(&mut settings.gif_delay_ms).range(50..=10_000).suffix(" ms"));
+                });
+                let export_gif = ui.button("Export GIF").on_hover_text("Every move of the game, one frame each").clicked();
+
+                if let Some(message) = &settings.message {
+                    ui.add_space(8.0);
+                    ui.label(RichText::new(message).weak());
+                }
+
+                if export_image {
+                    self.export_image();
+                }
+                if export_gif {
+                    self.export_gif();
+                }
+            });
+        if !open {
+            self.show_export = false;
+        }
+    }
+}
+
+/// Asks where to save `bytes` and reports what happened.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_file(name: &str, extension: &str, bytes: &[u8]) -> String {
+    let Some(path) = rfd::FileDialog::new()
+        .set_file_name(name)
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file()
+    else {
+        return "Export cancelled".to_string();
+    };
+    match std::fs::write(&path, bytes) {
+        Ok(()) => format!("Saved {}", path.display()),
+        Err(e) => format!("Failed to save {}: {}", path.display(), e),
+    }
+}
+
+/// Hands `bytes` to the browser as a download.
+#[cfg(target_arch = "wasm32")]
+fn save_file(name: &str, _extension: &str, bytes: &[u8]) -> String {
+    use web_sys::wasm_bindgen::JsCast;
+    use web_sys::{Blob, HtmlAnchorElement, Url};
+
+    let array = js_sys::Array::new();
+    array.push(&js_sys::Uint8Array::from(bytes));
+    let Ok(blob) = Blob::new_with_u8_array_sequence(&array) else {
+        return "Failed to create the download".to_string();
+    };
+    let Ok(url) = Url::create_object_url_with_blob(&blob) else {
+        return "Failed to create the download".to_string();
+    };
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return "Failed to create the download".to_string();
+    };
+    if let Ok(a) = document.create_element("a") {
+        let a = a.unchecked_into::<HtmlAnchorElement>();
+        a.set_href(&url);
+        a.set_download(name);
+        a.click();
+    }
+    Url::revoke_object_url(&url).ok();
+    format!("Downloaded {}", name)
+}
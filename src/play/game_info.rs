@@ -0,0 +1,131 @@
+use chess_lib::Colour;
+
+use crate::{
+    pgn,
+    play::{state::Engine, PlayTab},
+    statistics::record::{GameRecord, GameResult},
+    utils::{pgn_date, unix_time},
+};
+
+/// Results that can be picked in place of the one the game reached.
+pub const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Tags edited in the game info dialog. Empty fields are filled in on export.
+#[derive(Debug, Clone, Default)]
+pub struct GameInfo
+{
+    pub event: String,
+    pub site: String,
+    /// `YYYY.MM.DD`, today when empty.
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+    /// Overrides the result the game reached, e.g. for an adjourned game.
+    pub result: Option<String>,
+    pub time_control: String,
+    pub custom: Vec<(String, String)>,
+}
+
+/// PGN tag names are letters, digits and underscores, starting with a letter.
+pub fn valid_tag_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl PlayTab {
+    /// The name written for `colour` when the player left it empty.
+    pub fn default_player_name(&self, colour: Colour) -> String {
+        let engine = match self.engine_plays {
+            Engine::Both => true,
+            Engine::White => colour.white(),
+            Engine::Black => colour.black(),
+            Engine::Neither => false,
+        };
+        if engine { format!("chess_engine (depth {})", self.engine_depth) } else { "?".to_string() }
+    }
+    /// The result going into the PGN: the dialog's choice, else the game's.
+    pub fn pgn_result(&self) -> String {
+        match (&self.game_info.result, self.game_over()) {
+            (Some(result), _) => result.clone(),
+            (None, Some(termination)) => termination.result().to_string(),
+            (None, None) => "*".to_string(),
+        }
+    }
+    /// The game so far as PGN, with the tags chess_lib doesn't write itself.
+    pub fn game_pgn(&self) -> String {
+        let mut text = match &self.castle960 {
+            Some(castling) => castling.movetext(),
+            None => self.board.to_pgn(),
+        };
+
+        let info = &self.game_info;
+        let or = |value: &str, fallback: &str| {
+            let value = value.trim();
+            if value.is_empty() { fallback.to_string() } else { value.to_string() }
+        };
+        text = pgn::set_tag(&text, "Event", &or(&info.event, "?"));
+        text = pgn::set_tag(&text, "Site", &or(&info.site, "?"));
+        text = pgn::set_tag(&text, "Date", &or(&info.date, &pgn_date(unix_time())));
+        text = pgn::set_tag(&text, "Round", &or(&info.round, "-"));
+        text = pgn::set_tag(&text, "White", &or(&info.white, &self.default_player_name(Colour::White)));
+        text = pgn::set_tag(&text, "Black", &or(&info.black, &self.default_player_name(Colour::Black)));
+        text = pgn::set_tag(&text, "TimeControl", &or(&info.time_control, "-"));
+
+        if let Some(fen) = self.start_fen_tag() {
+            text = pgn::set_tag(&text, "SetUp", "1");
+            text = pgn::set_tag(&text, "FEN", &fen);
+        }
+        if self.chess960 {
+            text = pgn::set_tag(&text, "Variant", "Chess960");
+        }
+        let [white_hints, black_hints] = self.assist.hints_used;
+        if white_hints + black_hints > 0 {
+            text = pgn::set_tag(&text, "WhiteHints", &white_hints.to_string());
+            text = pgn::set_tag(&text, "BlackHints", &black_hints.to_string());
+        }
+        for (name, value) in &info.custom {
+            if valid_tag_name(name.trim()) {
+                text = pgn::set_tag(&text, name.trim(), value.trim());
+            }
+        }
+        // chess_lib doesn't know about resignations, agreements or unclaimed
+        // draws. Both follow the result written, which the dialog may have changed
+        let result = self.pgn_result();
+        let ended = if result == "*" { "unterminated" } else { "normal" };
+        text = pgn::set_tag(&text, "Termination", ended);
+        // How the game ended on the board only explains the game's own result
+        let comment = self.game_over().filter(|t| t.result() == result).map(|t| t.message());
+        pgn::set_result(&text, &result, comment.as_deref())
+    }
+    /// Saves the game as it stands, finished or not.
+    pub fn save_game(&self) {
+        let pgn = self.game_pgn();
+
+        #[cfg(target_arch = "wasm32")]
+        Self::download_pgn_web(&pgn);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        Self::download_pgn_native(&pgn);
+    }
+    /// Queues the game for the statistics tab the first time it reaches a result.
+    pub fn record_game(&mut self) {
+        let Some(termination) = self.game_over() else {
+            return;
+        };
+        if self.game_recorded || self.embedded {
+            return;
+        }
+        self.game_recorded = true;
+        self.finished_games.push(GameRecord {
+            timestamp: unix_time(),
+            result: GameResult::from_termination(termination),
+            human_colour: self.human_colour(),
+            engine_side: self.engine_plays.to_string().to_string(),
+            engine_depth: self.engine_depth,
+            termination: termination.label().to_string(),
+            start_fen: self.start_fen.clone(),
+            moves: self.move_sans(),
+        });
+    }
+}
@@ -1,7 +1,7 @@
 use chess_lib::{Colour, Piece};
 use egui::{Pos2, Rect, Vec2};
 
-use crate::play::PlayTab;
+use crate::{diagram::atlas_cell, play::PlayTab};
 
 
 
@@ -40,21 +40,7 @@ impl PlayTab {
         // row 2: BPawn BKnight BBishop
         // row 3: BRook BQueen  BKing
         // -----------------------------------------------
-        let (row, col) = match (piece, colour) {
-            (Piece::Pawn  , Colour::White) => (0, 0),
-            (Piece::Knight, Colour::White) => (0, 1),
-            (Piece::Bishop, Colour::White) => (0, 2),
-            (Piece::Rook  , Colour::White) => (1, 0),
-            (Piece::Queen , Colour::White) => (1, 1),
-            (Piece::King  , Colour::White) => (1, 2),
-    
-            (Piece::Pawn  , Colour::Black) => (2, 0),
-            (Piece::Knight, Colour::Black) => (2, 1),
-            (Piece::Bishop, Colour::Black) => (2, 2),
-            (Piece::Rook  , Colour::Black) => (3, 0),
-            (Piece::Queen , Colour::Black) => (3, 1),
-            (Piece::King  , Colour::Black) => (3, 2),
-        };
+        let (row, col) = atlas_cell(*piece, colour);
     
         let atlas_sz = self.atlas.size_vec2();
         let cell_w = atlas_sz.x / 3.0;
@@ -1,19 +1,21 @@
 
-use chess_lib::{Board, MoveList, MoveResult, Tile};
-use egui::{InputState, Key, Pos2, Response};
+use chess_lib::{MoveList, MoveResult, Tile};
+use egui::{InputState, Pos2, Response};
 use rand::Rng;
 
-use crate::play::{state::PlayState, PlayTab};
+use crate::play::{keymap::Action, state::PlayState, PlayTab};
 
 
 
 
 impl PlayTab {
     pub fn move_input(&mut self, response: Response, origin: Pos2) {
-        if !response.clicked() {
+        // Right-click cancels any queued premoves
+        if response.secondary_clicked() {
+            self.clear_premoves();
             return;
         }
-        if self.engine_turn() {
+        if !response.clicked() {
             return;
         }
 
@@ -23,13 +25,43 @@ impl PlayTab {
         };
 
         let (x, y) = self.screen_to_tile(pos, origin);
-        let player = self.board.current_players().0;
-
         let target_tile = match Tile::new_xy(x as u8, y as u8) {
             Some(t) => t,
             None => return,
         };
 
+        // A mouse click takes over from the keyboard cursor
+        self.cursor = None;
+        if self.engine_turn() {
+            self.premove_input(target_tile);
+        } else {
+            self.tile_input(target_tile);
+        }
+    }
+    /// Chess960 input convention: moving the king onto its own rook castles.
+    /// Where chess_lib castles, orthodox-style, this becomes the king's g/c-file move.
+    fn castle_destination(&self, target: Tile) -> Option<Tile> {
+        if !self.chess960 {
+            return None;
+        }
+        let selected = self.selected?;
+        let kingside = self.castle_squares(selected, target)?;
+        Tile::new_xy(if kingside { 6 } else { 2 }, selected.get_coords().1)
+    }
+    /// Selects, deselects or moves to `target_tile`, shared by mouse and keyboard input.
+    pub fn tile_input(&mut self, target_tile: Tile) {
+        if self.app_castling() {
+            let castle = self.selected.and_then(|s| self.castle_squares(s, target_tile));
+            if let Some(kingside) = castle {
+                if !self.castle(kingside) {
+                    self.selected = None;
+                }
+                return;
+            }
+        }
+        let target_tile = self.castle_destination(target_tile).unwrap_or(target_tile);
+        let player = self.board.current_players().0;
+
         if player.pieces.get_bit(target_tile) {
             // Selecting or deselecting a piece
             self.selected = match self.selected {
@@ -48,10 +80,8 @@ impl PlayTab {
         match self.board.try_move_piece(selected, target_tile, None) {
             Ok(move_result) => {
                 match move_result {
-                    MoveResult::MoveApplied(game_state) => {
-                        self.state = PlayState::Playing(game_state);
-                        self.selected = None;
-                        self.engine_timer = 0.0
+                    MoveResult::MoveApplied(_) => {
+                        self.finish_move();
                     }
                     MoveResult::PromotionNeeded(tile) => {
                         self.state = PlayState::Promotion(tile);
@@ -80,47 +110,84 @@ impl PlayTab {
         }
     }
     pub fn utility_input(&mut self, input: &InputState) {
-
-        // Reset Board
-        if input.key_pressed(Key::R) {
-            self.board = Board::new();
-            self.view_board = Board::new();
-
-            self.selected = None;
-            self.state = PlayState::Playing(chess_lib::GameState::Playing);
-        }
-
-        // Flip Board
-        if input.key_pressed(Key::F) {
-            self.flip();
+        // Don't fire actions while the keybinding editor is listening for a key
+        if self.rebinding.is_some() || self.pending_confirm.is_some() {
+            return;
         }
-
-        // Undo Move
-        if input.modifiers.ctrl && input.key_pressed(Key::Z) {
-            self.board.undo_move();
-
-            self.selected = None;
-            self.state = PlayState::Playing(chess_lib::GameState::Playing);
+        for action in Action::ALL {
+            if self.keymap.pressed(action, input) {
+                self.request_action(action);
+            }
         }
-        
-        // Make random move
-        if input.key_pressed(Key::Space) {
-            self.make_random();
+    }
+    /// Runs `action`, or asks for confirmation first if it is destructive and the keymap says so.
+    pub fn request_action(&mut self, action: Action) {
+        if action.destructive() && self.keymap.confirm(action) {
+            self.pending_confirm = Some(action);
+        } else {
+            self.run_action(action);
         }
-
-        // Exit program
-        if input.key_pressed(Key::Escape) {
-            self.should_close = true;
+    }
+    pub fn run_action(&mut self, action: Action) {
+        // Arrows are relative to the screen, so they invert when the board is flipped
+        let dir = if self.flipped { -1 } else { 1 };
+        match action {
+            Action::Reset => self.reset(),
+            Action::Flip => self.flip(),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::TakeBack => self.take_back(),
+            Action::RandomMove => self.make_random(),
+            Action::Quit => self.should_close = true,
+            Action::Resign => self.resign(),
+            Action::OfferDraw => self.offer_draw(),
+            Action::ClaimDraw => self.claim_draw(),
+            Action::Hint => self.hint(),
+            Action::ShowThreats => self.assist.show_threats = !self.assist.show_threats,
+            Action::HistoryBack => self.view_previous(),
+            Action::HistoryForward => self.view_next(),
+            Action::HistoryStart => self.view_start(),
+            Action::HistoryEnd => self.view_end(),
+            Action::CursorUp => self.move_cursor(0, dir),
+            Action::CursorDown => self.move_cursor(0, -dir),
+            Action::CursorLeft => self.move_cursor(-dir, 0),
+            Action::CursorRight => self.move_cursor(dir, 0),
+            Action::CursorSelect => {
+                let can_move = self.state == PlayState::Playing;
+                if let Some(c) = self.cursor.filter(|_| can_move) {
+                    if self.engine_turn() {
+                        self.premove_input(c);
+                    } else {
+                        self.tile_input(c);
+                    }
+                }
+            }
         }
-
-        // View previous move
-        if input.key_pressed(Key::ArrowRight) {
-            self.view_next();
+    }
+    /// Moves the keyboard square cursor, placing it on the selected piece or king first.
+    pub fn move_cursor(&mut self, dx: i32, dy: i32) {
+        self.cursor = match self.cursor {
+            None => Some(self.selected.unwrap_or(self.board.current_players().0.king_tile())),
+            Some(c) => {
+                let (x, y) = c.get_coords();
+                let nx = (x as i32 + dx).clamp(0, 7) as u8;
+                let ny = (y as i32 + dy).clamp(0, 7) as u8;
+                Tile::new_xy(nx, ny)
+            }
+        };
+    }
+    /// Plays the SAN/UCI move typed into the move entry box.
+    pub fn submit_move_text(&mut self) {
+        if self.engine_turn() || self.input_locked || self.state != PlayState::Playing {
+            self.move_text_error = Some("Not your turn".to_string());
+            return;
         }
-
-        // View next move
-        if input.key_pressed(Key::ArrowLeft) {
-            self.view_previous();
+        let text = self.move_text.clone();
+        if self.play_text(&text) {
+            self.move_text.clear();
+            self.move_text_error = None;
+        } else {
+            self.move_text_error = Some(format!("\"{}\" is not a legal move", text.trim()));
         }
     }
     pub fn handle_play_state(
@@ -133,26 +200,21 @@ impl PlayTab {
         match self.state {
             PlayState::Viewing(_) => {
             }
-            PlayState::Playing(game_state) => {
-                use chess_lib::GameState as gs;
-                match game_state {
-                    gs::Playing => {
-                        self.move_input(response, origin);
-                    },
-                    gs::Checkmate(_) => (),
-                    gs::Stalemate(_) => (),
-                    gs::InsufficientMaterial => (),
-                    gs::FiftyMoveRule => (),
-                    gs::ThreeRepetition => (),
-                }
+            PlayState::Playing if self.input_locked || !self.visibility.board_input() => (),
+            PlayState::Playing => {
+                self.move_input(response, origin);
             }
+            PlayState::Over(_) => (),
             PlayState::Promotion(tile) => {
                 self.render_promotion_choices(ui, ctx, tile, origin);
             }
         }
-        ctx.input(|i| {
-            self.utility_input(i);            
-        });
+        // Don't treat typing in the move entry box as shortcuts
+        if !self.embedded && !ctx.wants_keyboard_input() {
+            ctx.input(|i| {
+                self.utility_input(i);
+            });
+        }
     }
     pub fn make_random(&mut self) {
         if !self.engine_turn() {
@@ -160,11 +222,8 @@ impl PlayTab {
             self.board.generate_legal_moves(self.board.turn, &mut moves);
             if !moves.is_empty() {
                 let random_index = self.rand.random_range(0..moves.len());
-                self.board.make_move_unchecked(moves[random_index]);
+                self.play_move(moves[random_index]);
             }
-            self.selected = None;
-            self.state = PlayState::Playing(chess_lib::GameState::Playing);
-            self.engine_timer = 0.0;
         }
     }
     pub fn view_previous(&mut self) {
@@ -174,12 +233,25 @@ impl PlayTab {
             }
         }
         else {
-            if self.board.history.len() > 0 {
-                self.state = PlayState::Viewing(self.board.history.len() - 1)
+            if self.ply_count() > 0 {
+                self.state = PlayState::Viewing(self.ply_count() - 1)
             }
         }
         self.selected = None;
     }
+    pub fn view_start(&mut self) {
+        if self.ply_count() > 0 {
+            self.state = PlayState::Viewing(0);
+        }
+        self.selected = None;
+    }
+    pub fn view_end(&mut self) {
+        if let PlayState::Viewing(_) = self.state {
+            // Viewing the last position switches back to playing
+            self.state = PlayState::Viewing(self.ply_count());
+        }
+        self.selected = None;
+    }
     pub fn view_next(&mut self) {
         if let PlayState::Viewing(pos) = self.state {
             self.state = PlayState::Viewing(pos + 1)
@@ -0,0 +1,283 @@
+use egui::{InputState, Key, Modifiers};
+
+use crate::storage;
+
+const KEYMAP_FILE: &str = "keybindings.txt";
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Action
+{
+    Reset,
+    Flip,
+    Undo,
+    Redo,
+    TakeBack,
+    RandomMove,
+    Quit,
+    Resign,
+    OfferDraw,
+    ClaimDraw,
+    Hint,
+    ShowThreats,
+    HistoryBack,
+    HistoryForward,
+    HistoryStart,
+    HistoryEnd,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    CursorSelect,
+}
+impl Action
+{
+    pub const ALL: [Action; 21] = [
+        Action::Reset,
+        Action::Flip,
+        Action::Undo,
+        Action::Redo,
+        Action::TakeBack,
+        Action::RandomMove,
+        Action::Quit,
+        Action::Resign,
+        Action::OfferDraw,
+        Action::ClaimDraw,
+        Action::Hint,
+        Action::ShowThreats,
+        Action::HistoryBack,
+        Action::HistoryForward,
+        Action::HistoryStart,
+        Action::HistoryEnd,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::CursorSelect,
+    ];
+
+    pub fn label(&self) -> &str
+    {
+        match self {
+            Action::Reset => "New game",
+            Action::Flip => "Flip board",
+            Action::Undo => "Undo move",
+            Action::Redo => "Redo move",
+            Action::TakeBack => "Take back",
+            Action::RandomMove => "Random move",
+            Action::Quit => "Quit",
+            Action::Resign => "Resign",
+            Action::OfferDraw => "Offer draw",
+            Action::ClaimDraw => "Claim draw",
+            Action::Hint => "Hint",
+            Action::ShowThreats => "Show threats",
+            Action::HistoryBack => "Previous move",
+            Action::HistoryForward => "Next move",
+            Action::HistoryStart => "Start of game",
+            Action::HistoryEnd => "End of game",
+            Action::CursorUp => "Cursor up",
+            Action::CursorDown => "Cursor down",
+            Action::CursorLeft => "Cursor left",
+            Action::CursorRight => "Cursor right",
+            Action::CursorSelect => "Cursor select/move",
+        }
+    }
+    /// Stable name used in the saved keymap file.
+    fn id(&self) -> &str
+    {
+        match self {
+            Action::Reset => "reset",
+            Action::Flip => "flip",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::TakeBack => "take_back",
+            Action::RandomMove => "random_move",
+            Action::Quit => "quit",
+            Action::Resign => "resign",
+            Action::OfferDraw => "offer_draw",
+            Action::ClaimDraw => "claim_draw",
+            Action::Hint => "hint",
+            Action::ShowThreats => "show_threats",
+            Action::HistoryBack => "history_back",
+            Action::HistoryForward => "history_forward",
+            Action::HistoryStart => "history_start",
+            Action::HistoryEnd => "history_end",
+            Action::CursorUp => "cursor_up",
+            Action::CursorDown => "cursor_down",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::CursorSelect => "cursor_select",
+        }
+    }
+    /// Actions that throw away the game or the app, and so can ask first.
+    pub fn destructive(&self) -> bool {
+        matches!(self, Action::Reset | Action::Quit | Action::Resign)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct KeyBinding
+{
+    pub key: Key,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+impl KeyBinding
+{
+    pub const fn new(key: Key) -> Self {
+        Self { key, ctrl: false, shift: false, alt: false }
+    }
+    pub const fn ctrl(key: Key) -> Self {
+        Self { key, ctrl: true, shift: false, alt: false }
+    }
+    pub fn from_input(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, ctrl: modifiers.ctrl, shift: modifiers.shift, alt: modifiers.alt }
+    }
+    /// Modifiers must match exactly so that `Ctrl+Left` doesn't also trigger `Left`.
+    pub fn pressed(&self, input: &InputState) -> bool {
+        let m = input.modifiers;
+        input.key_pressed(self.key) && m.ctrl == self.ctrl && m.shift == self.shift && m.alt == self.alt
+    }
+    pub fn label(&self) -> String {
+        let mut s = String::new();
+        if self.ctrl {
+            s.push_str("Ctrl+");
+        }
+        if self.shift {
+            s.push_str("Shift+");
+        }
+        if self.alt {
+            s.push_str("Alt+");
+        }
+        s.push_str(self.key.name());
+        s
+    }
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut binding = Self::new(Key::Space);
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // `Plus` is spelled out by egui, so the last part is always the key name
+        let key = parts.pop()?;
+        for p in parts {
+            match p {
+                "Ctrl" => binding.ctrl = true,
+                "Shift" => binding.shift = true,
+                "Alt" => binding.alt = true,
+                _ => return None,
+            }
+        }
+        binding.key = Key::from_name(key)?;
+        Some(binding)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap
+{
+    pub bindings: Vec<(Action, Option<KeyBinding>)>,
+    pub confirm_reset: bool,
+    pub confirm_quit: bool,
+}
+impl Default for Keymap
+{
+    fn default() -> Self {
+        let bindings = vec![
+            (Action::Reset, Some(KeyBinding::new(Key::R))),
+            (Action::Flip, Some(KeyBinding::new(Key::F))),
+            (Action::Undo, Some(KeyBinding::ctrl(Key::Z))),
+            (Action::Redo, Some(KeyBinding::ctrl(Key::Y))),
+            (Action::TakeBack, Some(KeyBinding::new(Key::T))),
+            (Action::RandomMove, Some(KeyBinding::new(Key::Space))),
+            (Action::Quit, Some(KeyBinding::new(Key::Escape))),
+            (Action::Resign, None),
+            (Action::OfferDraw, None),
+            (Action::ClaimDraw, None),
+            (Action::Hint, Some(KeyBinding::new(Key::H))),
+            (Action::ShowThreats, None),
+            (Action::HistoryBack, Some(KeyBinding::ctrl(Key::ArrowLeft))),
+            (Action::HistoryForward, Some(KeyBinding::ctrl(Key::ArrowRight))),
+            (Action::HistoryStart, Some(KeyBinding::new(Key::Home))),
+            (Action::HistoryEnd, Some(KeyBinding::new(Key::End))),
+            (Action::CursorUp, Some(KeyBinding::new(Key::ArrowUp))),
+            (Action::CursorDown, Some(KeyBinding::new(Key::ArrowDown))),
+            (Action::CursorLeft, Some(KeyBinding::new(Key::ArrowLeft))),
+            (Action::CursorRight, Some(KeyBinding::new(Key::ArrowRight))),
+            (Action::CursorSelect, Some(KeyBinding::new(Key::Enter))),
+        ];
+        Self { bindings, confirm_reset: true, confirm_quit: true }
+    }
+}
+impl Keymap
+{
+    /// Loads the saved keymap, falling back to the defaults for anything missing.
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        if let Some(text) = storage::load(KEYMAP_FILE) {
+            keymap.apply_text(&text);
+        }
+        keymap
+    }
+    pub fn save(&self) {
+        storage::save(KEYMAP_FILE, &self.to_text());
+    }
+
+    pub fn binding(&self, action: Action) -> Option<KeyBinding> {
+        self.bindings.iter().find(|(a, _)| *a == action).and_then(|(_, b)| *b)
+    }
+    pub fn set(&mut self, action: Action, binding: Option<KeyBinding>) {
+        if let Some(entry) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            entry.1 = binding;
+        }
+    }
+    pub fn pressed(&self, action: Action, input: &InputState) -> bool {
+        self.binding(action).is_some_and(|b| b.pressed(input))
+    }
+    pub fn confirm(&self, action: Action) -> bool {
+        match action {
+            Action::Reset => self.confirm_reset,
+            Action::Quit => self.confirm_quit,
+            Action::Resign => true,
+            _ => false,
+        }
+    }
+    /// Other actions sharing the binding of `action`.
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let Some(binding) = self.binding(action) else {
+            return Vec::new();
+        };
+        self.bindings
+            .iter()
+            .filter(|(a, b)| *a != action && *b == Some(binding))
+            .map(|(a, _)| *a)
+            .collect()
+    }
+    pub fn has_conflicts(&self) -> bool {
+        Action::ALL.iter().any(|a| !self.conflicts(*a).is_empty())
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (action, binding) in &self.bindings {
+            let b = binding.map(|b| b.label()).unwrap_or_default();
+            text.push_str(&format!("{}={}\n", action.id(), b));
+        }
+        text.push_str(&format!("confirm_reset={}\n", self.confirm_reset));
+        text.push_str(&format!("confirm_quit={}\n", self.confirm_quit));
+        text
+    }
+    fn apply_text(&mut self, text: &str) {
+        for line in text.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            match name {
+                "confirm_reset" => self.confirm_reset = value != "false",
+                "confirm_quit" => self.confirm_quit = value != "false",
+                _ => {
+                    if let Some(action) = Action::ALL.iter().find(|a| a.id() == name) {
+                        self.set(*action, KeyBinding::parse(value));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use chess_lib::{Colour, MoveResult, Piece, Tile};
+use egui::{Color32, Painter, Pos2};
+
+use crate::play::{state::Engine, PlayTab};
+
+impl PlayTab {
+    /// The side the human plays against the engine, if there is exactly one.
+    pub fn human_colour(&self) -> Option<Colour> {
+        match self.engine_plays {
+            Engine::White => Some(Colour::Black),
+            Engine::Black => Some(Colour::White),
+            Engine::Neither | Engine::Both => None,
+        }
+    }
+    pub fn clear_premoves(&mut self) {
+        self.premoves.clear();
+        self.premove_selected = None;
+    }
+    /// Where the human's pieces will stand once the queued premoves are played,
+    /// so a piece can be premoved again from its queued destination.
+    fn premove_piece_at(&self, tile: Tile, colour: Colour) -> bool {
+        if self.premoves.iter().any(|&(_, to)| to == tile) {
+            return true;
+        }
+        if self.premoves.iter().any(|&(from, _)| from == tile) {
+            return false;
+        }
+        let player = if colour.white() { &self.board.white } else { &self.board.black };
+        player.pieces.get_bit(tile)
+    }
+    /// Queues premoves while the engine is thinking. Legality is only checked when they're played.
+    pub fn premove_input(&mut self, target_tile: Tile) {
+        let colour = match self.human_colour() {
+            Some(c) => c,
+            None => return,
+        };
+
+        match self.premove_selected {
+            Some(from) if from == target_tile => self.premove_selected = None,
+            Some(from) => {
+                self.premoves.push((from, target_tile));
+                self.premove_selected = None;
+            }
+            None => {
+                if self.premove_piece_at(target_tile, colour) {
+                    self.premove_selected = Some(target_tile);
+                }
+            }
+        }
+    }
+    /// Plays the next queued premove once it's the human's turn again.
+    /// An illegal premove cancels the whole queue.
+    pub fn play_premove(&mut self) {
+        if self.premoves.is_empty() || self.engine_turn() {
+            return;
+        }
+        let (from, to) = self.premoves.remove(0);
+
+        if let Some(kingside) = self.castle_squares(from, to).filter(|_| self.app_castling()) {
+            if !self.castle(kingside) {
+                self.clear_premoves();
+            }
+            return;
+        }
+        let result = match self.board.try_move_piece(from, to, None) {
+            // Premoves always promote to a queen
+            Ok(MoveResult::PromotionNeeded(_)) => self.board.try_move_piece(from, to, Some(Piece::Queen)),
+            r => r,
+        };
+        match result {
+            Ok(MoveResult::MoveApplied(_)) => self.finish_move(),
+            _ => self.clear_premoves(),
+        }
+    }
+    pub fn render_premoves(&self, painter: &Painter, origin: Pos2) {
+        let highlight = Color32::from_rgba_unmultiplied(80, 130, 220, 110);
+        let tiles = self
+            .premoves
+            .iter()
+            .flat_map(|&(from, to)| [from, to])
+            .chain(self.premove_selected);
+
+        for t in tiles {
+            let (x, y) = t.get_coords();
+            let rect = self.tile_to_screen(x as f32, y as f32, origin);
+            painter.rect_filled(rect, 0.0, highlight);
+        }
+    }
+}
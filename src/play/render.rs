@@ -1,11 +1,12 @@
 use chess_lib::{Board, Colour, MoveList, Piece, Tile};
-use egui::{Color32, ComboBox, Context, Painter, Pos2, RichText, Vec2};
+use egui::{Color32, ComboBox, Context, Id, Key, Modal, Painter, Pos2, RichText, Stroke, StrokeKind, Vec2};
+use rand::Rng;
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs;
 #[cfg(not(target_arch = "wasm32"))]
 use rfd::FileDialog;
 
-use crate::play::{state::{Engine, PlayState}, PlayTab};
+use crate::{chess960, diagram::{DARK_SQUARE, LIGHT_SQUARE}, notation, play::{game_info::{valid_tag_name, RESULTS}, keymap::{Action, KeyBinding, Keymap}, state::{Engine, PlayState}, PlayTab}, utils::{pgn_date, unix_time}};
 
 impl PlayTab
 {
@@ -17,9 +18,12 @@ impl PlayTab
         origin: Pos2,
     ) {
         if self.auto_queen {
-            let _ = self.board.try_move_piece(self.selected.unwrap(), tile, Some(Piece::Queen));
-            self.selected = None;
-            self.state = PlayState::Playing(self.board.get_state());
+            if self.board.try_move_piece(self.selected.unwrap(), tile, Some(Piece::Queen)).is_ok() {
+                self.finish_move();
+            } else {
+                self.selected = None;
+                self.state = self.position_state();
+            }
             return;
         }
         let (x, y) = tile.get_coords();
@@ -36,13 +40,40 @@ impl PlayTab
             painter.image(self.atlas.id(), pos_rect, uv, Color32::WHITE);
 
             if response.clicked() {
-                let _ = self.board.try_move_piece(self.selected.unwrap(), tile, Some(piece));
-                self.selected = None;
-                self.state = PlayState::Playing(self.board.get_state());
+                if self.board.try_move_piece(self.selected.unwrap(), tile, Some(piece)).is_ok() {
+                    self.finish_move();
+                } else {
+                    self.selected = None;
+                    self.state = self.position_state();
+                }
             }
         }
     }
 
+    pub fn render_chess960_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.chess960, "Chess960")
+            .on_hover_text("Applies from the next new game");
+        if let Some(e) = &self.chess960_error {
+            ui.colored_label(Color32::RED, e);
+        }
+        if !self.chess960 {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Start position");
+            ui.add(egui::DragValue::new(&mut self.chess960_number).range(0..=959));
+            if ui.button("Random").clicked() {
+                self.chess960_number = self.rand.random_range(0..960);
+            }
+        });
+        let rank = chess960::back_rank(self.chess960_number);
+        let names: String = rank.iter().map(|p| notation::piece_char(*p)).collect();
+        ui.label(RichText::new(names).monospace());
+        if !chess960::orthodox_castling(&rank) {
+            ui.label(RichText::new("Castle by moving the king onto its rook or typing O-O. The engine doesn't castle in this start position").weak());
+        }
+    }
+
    pub fn render_engine_side_selector(&mut self, ui: &mut egui::Ui) {
     let available_size = ui.available_size();
 
@@ -90,8 +121,8 @@ impl PlayTab
         for rank in 0..8 {
             for file in 0..8 {
                 let rect = self.tile_to_screen(file as f32, rank as f32, origin);
-                let light = Color32::from_rgb(240, 217, 181);
-                let dark  = Color32::from_rgb(181, 136,  99);
+                let light = Color32::from_rgb(LIGHT_SQUARE[0], LIGHT_SQUARE[1], LIGHT_SQUARE[2]);
+                let dark  = Color32::from_rgb(DARK_SQUARE[0], DARK_SQUARE[1], DARK_SQUARE[2]);
                 let mut clr   = if (file + rank) % 2 == 1 { light } else { dark };
 
                 if white_check {
@@ -109,19 +140,27 @@ impl PlayTab
             }
         }
     }
-    pub fn render_pieces(&self, painter: &Painter, origin: Pos2, board: &Board) {
+    /// Draws the pieces as the visibility mode allows, `hovered` being the square under the pointer.
+    pub fn render_pieces(&self, painter: &Painter, origin: Pos2, board: &Board, hovered: Option<Tile>) {
         for (colour, player) in [(Colour::White, &board.white), (Colour::Black, &board.black)] {
             for (i, bb) in player.bb.iter().enumerate() {
                 let piece = Piece::from_index(i);
                 let uv_rect = self.atlas_uv(&piece, colour);
                 for t in bb.iter() {
                     let (x, y) = t.get_coords();
-                    painter.image(
-                        self.atlas.id(), 
-                        self.tile_to_screen(x as f32, y as f32, origin), 
-                        uv_rect, 
-                        Color32::WHITE,
-                    );
+                    let rect = self.tile_to_screen(x as f32, y as f32, origin);
+                    match self.piece_shown(t, hovered) {
+                        Some(true) => {
+                            painter.image(
+                                self.atlas.id(), 
+                                rect, 
+                                uv_rect, 
+                                Color32::WHITE,
+                            );
+                        }
+                        Some(false) => self.render_blob(painter, rect, colour),
+                        None => (),
+                    }
                 }
             }
         }
@@ -148,25 +187,323 @@ impl PlayTab
             return;
         }
     }
-    pub fn render_game_over(&mut self, ctx: &Context) {
-        if let PlayState::Playing(game_state) = self.state {
-            match game_state {
-                chess_lib::GameState::Playing => (),
-                chess_lib::GameState::Checkmate(_) => self.show_popup = true,
-                chess_lib::GameState::Stalemate(_) => self.show_popup = true,
-                chess_lib::GameState::InsufficientMaterial => self.show_popup = true,
-                chess_lib::GameState::FiftyMoveRule => self.show_popup = true,
-                chess_lib::GameState::ThreeRepetition => self.show_popup = true,
-            };
-            if !self.show_popup { return; }
-            let message = match game_state {
-                chess_lib::GameState::Checkmate(loser) => format!("Checkmate! {} wins.", if loser.white() { "Black" } else { "White" } ),
-                chess_lib::GameState::Stalemate(_) => "Stalemate! It's a draw.".to_string(),
-                chess_lib::GameState::InsufficientMaterial => "Draw: Insufficient material.".to_string(),
-                chess_lib::GameState::FiftyMoveRule => "Draw: 50-move rule.".to_string(),
-                chess_lib::GameState::ThreeRepetition => "Draw: Threefold repetition.".to_string(),
-                _ => return,
+    pub fn render_cursor(&self, painter: &Painter, origin: Pos2) {
+        if let Some(c) = self.cursor {
+            let (x, y) = c.get_coords();
+            let rect = self.tile_to_screen(x as f32, y as f32, origin);
+            painter.rect_stroke(
+                rect.shrink(2.0),
+                0.0,
+                Stroke::new(4.0, Color32::from_rgb(30, 120, 230)),
+                StrokeKind::Inside,
+            );
+        }
+    }
+    pub fn render_move_entry(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Move:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.move_text)
+                    .hint_text("Nf3, exd5, O-O, g1f3")
+                    .desired_width(160.0),
+            );
+            if std::mem::take(&mut self.focus_move_entry) {
+                response.request_focus();
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                self.submit_move_text();
+                // Keep focus so several moves can be typed in a row
+                response.request_focus();
+            }
+            if response.changed() {
+                self.move_text_error = None;
+            }
+        });
+
+        if let Some(e) = &self.move_text_error {
+            ui.colored_label(Color32::RED, e);
+        }
+
+        if self.move_text.trim().is_empty() || self.engine_turn() {
+            return;
+        }
+        if self.state == PlayState::Playing {
+            let suggestions = notation::completions(&self.board, &self.move_text);
+            let typed = self.move_text.trim().replace('0', "O");
+            let castles: Vec<bool> = self
+                .legal_castles()
+                .into_iter()
+                .filter(|&kingside| if kingside { "O-O" } else { "O-O-O" }.starts_with(&typed))
+                .collect();
+            ui.horizontal_wrapped(|ui| {
+                for kingside in castles {
+                    if ui.small_button(if kingside { "O-O" } else { "O-O-O" }).clicked() {
+                        self.castle(kingside);
+                        self.move_text.clear();
+                    }
+                }
+                for m in suggestions.iter().take(12) {
+                    if ui.small_button(&m.san).on_hover_text(&m.uci).clicked() {
+                        self.play_move(m.mv);
+                        self.move_text.clear();
+                    }
+                }
+            });
+        }
+    }
+    /// Resign, draw offer and draw claim buttons, with any offer waiting for an answer.
+    pub fn render_game_actions(&mut self, ui: &mut egui::Ui) {
+        if let Some(colour) = self.draw_offer {
+            ui.label(format!("{} offers a draw", if colour.white() { "White" } else { "Black" }));
+            ui.horizontal(|ui| {
+                if ui.button("Accept").clicked() {
+                    self.answer_draw(true);
+                }
+                if ui.button("Decline").clicked() {
+                    self.answer_draw(false);
+                }
+            });
+        }
+        ui.horizontal_wrapped(|ui| {
+            let can_concede = self.can_concede();
+            if ui.add_enabled(can_concede, egui::Button::new("Resign")).clicked() {
+                self.request_action(Action::Resign);
+            }
+            let can_offer = can_concede && self.draw_offer.is_none();
+            if ui.add_enabled(can_offer, egui::Button::new("Offer draw")).clicked() {
+                self.request_action(Action::OfferDraw);
+            }
+            let claim = self.claimable_draw();
+            let button = ui.add_enabled(claim.is_some(), egui::Button::new("Claim draw"));
+            let button = match claim {
+                Some(t) => button.on_hover_text(t.label()),
+                None => button.on_disabled_hover_text("Needs fifty moves without a capture or pawn move, or a threefold repetition"),
             };
+            if button.clicked() {
+                self.request_action(Action::ClaimDraw);
+            }
+        });
+        if let Some(note) = &self.draw_note {
+            ui.label(RichText::new(note).weak());
+        }
+    }
+    pub fn render_keybindings(&mut self, ctx: &Context) {
+        if !self.show_keybindings {
+            return;
+        }
+
+        // Capture the next key press for the action being rebound
+        if let Some(action) = self.rebinding {
+            let pressed = ctx.input(|i| {
+                i.events.iter().find_map(|e| match e {
+                    egui::Event::Key { key, pressed: true, repeat: false, modifiers, .. } => {
+                        Some(KeyBinding::from_input(*key, *modifiers))
+                    }
+                    _ => None,
+                })
+            });
+            if let Some(binding) = pressed {
+                self.keymap.set(action, Some(binding));
+                self.keymap.save();
+                self.rebinding = None;
+            }
+        }
+
+        let mut open = true;
+        egui::Window::new("Keybindings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("keybindings_grid")
+                    .num_columns(3)
+                    .spacing([12.0, 6.0])
+                    .show(ui, |ui| {
+                        for action in Action::ALL {
+                            ui.label(action.label());
+
+                            let conflicts = self.keymap.conflicts(action);
+                            let text = if self.rebinding == Some(action) {
+                                RichText::new("Press a key...").italics()
+                            } else {
+                                let label = self.keymap.binding(action).map(|b| b.label()).unwrap_or("Unbound".to_string());
+                                if conflicts.is_empty() {
+                                    RichText::new(label)
+                                } else {
+                                    RichText::new(label).color(Color32::RED)
+                                }
+                            };
+
+                            let response = ui.button(text);
+                            let response = if conflicts.is_empty() {
+                                response
+                            } else {
+                                let names: Vec<&str> = conflicts.iter().map(|a| a.label()).collect();
+                                response.on_hover_text(format!("Also bound to: {}", names.join(", ")))
+                            };
+                            if response.clicked() {
+                                self.rebinding = Some(action);
+                            }
+
+                            if ui.button("Clear").clicked() {
+                                self.keymap.set(action, None);
+                                self.keymap.save();
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if self.keymap.has_conflicts() {
+                    ui.colored_label(Color32::RED, "Some keys are bound to more than one action");
+                }
+
+                ui.add_space(8.0);
+
+                let mut changed = ui.checkbox(&mut self.keymap.confirm_reset, "Confirm before new game").changed();
+                changed |= ui.checkbox(&mut self.keymap.confirm_quit, "Confirm before quitting").changed();
+                if changed {
+                    self.keymap.save();
+                }
+
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    if self.rebinding.is_some() && ui.button("Cancel").clicked() {
+                        self.rebinding = None;
+                    }
+                    if ui.button("Restore defaults").clicked() {
+                        self.keymap = Keymap::default();
+                        self.keymap.save();
+                        self.rebinding = None;
+                    }
+                });
+            });
+
+        if !open {
+            self.show_keybindings = false;
+            self.rebinding = None;
+        }
+    }
+    /// Editor for the tags written when the game is saved.
+    pub fn render_game_info(&mut self, ctx: &Context) {
+        if !self.show_game_info {
+            return;
+        }
+        let white_hint = self.default_player_name(Colour::White);
+        let black_hint = self.default_player_name(Colour::Black);
+        let game_result = self.game_over().map(|t| t.result().to_string()).unwrap_or("*".to_string());
+        let today = pgn_date(unix_time());
+        let [white_hints, black_hints] = self.assist.hints_used;
+
+        let mut open = true;
+        egui::Window::new("Game info")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let info = &mut self.game_info;
+                egui::Grid::new("game_info_grid")
+                    .num_columns(2)
+                    .spacing([12.0, 6.0])
+                    .show(ui, |ui| {
+                        let field = |ui: &mut egui::Ui, label: &str, value: &mut String, hint: &str| {
+                            ui.label(label);
+                            ui.add(egui::TextEdit::singleline(value).hint_text(hint));
+                            ui.end_row();
+                        };
+                        field(ui, "Event", &mut info.event, "?");
+                        field(ui, "Site", &mut info.site, "?");
+                        field(ui, "Date", &mut info.date, &today);
+                        field(ui, "Round", &mut info.round, "-");
+                        field(ui, "White", &mut info.white, &white_hint);
+                        field(ui, "Black", &mut info.black, &black_hint);
+                        field(ui, "TimeControl", &mut info.time_control, "40/7200:3600 or 300+2");
+
+                        ui.label("Result");
+                        let selected = match &info.result {
+                            Some(r) => r.clone(),
+                            None => format!("From the game ({})", game_result),
+                        };
+                        ComboBox::from_id_salt("game_info_result")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut info.result, None, format!("From the game ({})", game_result));
+                                for result in RESULTS {
+                                    ui.selectable_value(&mut info.result, Some(result.to_string()), result);
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Hints used");
+                        ui.label(format!("White {}, Black {}", white_hints, black_hints));
+                        ui.end_row();
+                    });
+
+                ui.add_space(8.0);
+                ui.label("Other tags");
+                let mut remove = None;
+                for (i, (name, value)) in info.custom.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(name).hint_text("Name").desired_width(100.0));
+                        ui.add(egui::TextEdit::singleline(value).hint_text("Value").desired_width(160.0));
+                        if ui.small_button("✖").on_hover_text("Remove tag").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                    if !name.trim().is_empty() && !valid_tag_name(name.trim()) {
+                        ui.colored_label(Color32::RED, "Tag names are letters, digits and _");
+                    }
+                }
+                if let Some(i) = remove {
+                    info.custom.remove(i);
+                }
+                if ui.button("Add tag").clicked() {
+                    info.custom.push((String::new(), String::new()));
+                }
+
+                ui.add_space(8.0);
+                ui.label(RichText::new("Empty fields are filled in when saving").weak());
+                if ui.button("Save game").clicked() {
+                    self.save_game();
+                }
+            });
+        if !open {
+            self.show_game_info = false;
+        }
+    }
+    pub fn render_confirm(&mut self, ctx: &Context) {
+        let action = match self.pending_confirm {
+            Some(a) => a,
+            None => return,
+        };
+        let question = match action {
+            Action::Reset => "Start a new game? The current game will be lost.",
+            Action::Quit => "Quit the app?",
+            Action::Resign => "Resign this game?",
+            _ => action.label(),
+        };
+        Modal::new(Id::new("confirm_action"))
+            .show(ctx, |ui| {
+                ui.label(question);
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Yes").clicked() {
+                        self.pending_confirm = None;
+                        self.run_action(action);
+                    }
+                    if ui.button("No").clicked() {
+                        self.pending_confirm = None;
+                    }
+                });
+            });
+    }
+    pub fn render_game_over(&mut self, ctx: &Context) {
+        if self.embedded {
+            return;
+        }
+        if let PlayState::Over(termination) = self.state {
+            self.show_popup = true;
+            let message = termination.message();
             egui::Window::new("Game Over")
                 .collapsible(false)
                 .resizable(false)
@@ -191,13 +528,7 @@ impl PlayTab
                             ui.add_space(8.0);
 
                             if ui.button("Save Game").clicked() {
-                                let pgn = self.board.to_pgn();
-
-                                #[cfg(target_arch = "wasm32")]
-                                Self::download_pgn_web(&pgn);
-
-                                #[cfg(not(target_arch = "wasm32"))]
-                                Self::download_pgn_native(&pgn);
+                                self.save_game();
                             }
                         },
                     );
@@ -1,12 +1,79 @@
-use chess_lib::{GameState, Tile};
+use chess_lib::{Colour, Tile};
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum PlayState
 {
-    Playing(GameState),
+    Playing,
+    /// The game has ended and no more moves can be made.
+    Over(Termination),
     Viewing(usize),
     Promotion(Tile),
 }
+/// Why a game ended.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Termination
+{
+    /// The side that was mated.
+    Checkmate(Colour),
+    Stalemate,
+    InsufficientMaterial,
+    /// The side that resigned.
+    Resignation(Colour),
+    Agreement,
+    FiftyMoveClaim,
+    RepetitionClaim,
+    /// Automatic draws that need no claim.
+    SeventyFiveMoves,
+    FivefoldRepetition,
+}
+impl Termination
+{
+    pub fn winner(&self) -> Option<Colour> {
+        match self {
+            Termination::Checkmate(loser) | Termination::Resignation(loser) => {
+                Some(if loser.white() { Colour::Black } else { Colour::White })
+            }
+            _ => None,
+        }
+    }
+    /// The PGN result string.
+    pub fn result(&self) -> &str {
+        match self.winner() {
+            Some(Colour::White) => "1-0",
+            Some(Colour::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+    /// Short name of the reason, as kept in the game history.
+    pub fn label(&self) -> &str {
+        match self {
+            Termination::Checkmate(_) => "Checkmate",
+            Termination::Stalemate => "Stalemate",
+            Termination::InsufficientMaterial => "Insufficient material",
+            Termination::Resignation(_) => "Resignation",
+            Termination::Agreement => "Draw agreed",
+            Termination::FiftyMoveClaim => "Fifty-move rule",
+            Termination::RepetitionClaim => "Threefold repetition",
+            Termination::SeventyFiveMoves => "Seventy-five-move rule",
+            Termination::FivefoldRepetition => "Fivefold repetition",
+        }
+    }
+    /// A sentence describing the end of the game.
+    pub fn message(&self) -> String {
+        let side = |c: Colour| if c.white() { "White" } else { "Black" };
+        let winner = side(self.winner().unwrap_or(Colour::White));
+        match self {
+            Termination::Checkmate(_) => format!("Checkmate! {} wins.", winner),
+            Termination::Stalemate => "Stalemate! It's a draw.".to_string(),
+            Termination::Resignation(loser) => format!("{} resigns. {} wins.", side(*loser), winner),
+            Termination::FiftyMoveClaim => "Draw claimed: 50-move rule.".to_string(),
+            Termination::RepetitionClaim => "Draw claimed: Threefold repetition.".to_string(),
+            Termination::SeventyFiveMoves => "Draw: 75-move rule.".to_string(),
+            Termination::FivefoldRepetition => "Draw: Fivefold repetition.".to_string(),
+            _ => format!("Draw: {}.", self.label()),
+        }
+    }
+}
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Engine
 {
@@ -26,4 +93,4 @@ impl Engine
             Engine::Both => "Both",
         }
     }
-}
\ No newline at end of file
+}
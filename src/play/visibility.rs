@@ -0,0 +1,90 @@
+use chess_lib::{Colour, Tile};
+use egui::{Color32, ComboBox, Painter, Pos2, Rect, RichText, Stroke, Ui};
+
+use crate::play::PlayTab;
+
+/// How much of the position the board shows, for visualisation training.
+/// Only the drawing changes: the game, the engine and move entry carry on as usual.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Visibility
+{
+    #[default]
+    Normal,
+    /// Empty squares only. Moves are typed or picked from the suggestions.
+    Blindfold,
+    /// Each piece is a disc in its side's colour.
+    Blobs,
+    /// Pieces appear only under the pointer and on the selected square.
+    RevealOnHover,
+}
+impl Visibility
+{
+    pub const ALL: [Visibility; 4] = [Visibility::Normal, Visibility::Blindfold, Visibility::Blobs, Visibility::RevealOnHover];
+
+    pub fn label(&self) -> &str {
+        match self {
+            Visibility::Normal => "Normal",
+            Visibility::Blindfold => "Blindfold",
+            Visibility::Blobs => "Blobs",
+            Visibility::RevealOnHover => "Reveal on hover",
+        }
+    }
+    /// Whether anything on the board or under it would give the position away.
+    pub fn hides_position(&self) -> bool {
+        *self != Visibility::Normal
+    }
+    /// The board takes clicks in every mode but blindfold.
+    pub fn board_input(&self) -> bool {
+        *self != Visibility::Blindfold
+    }
+}
+
+impl PlayTab {
+    /// How the piece on `tile` is drawn, if at all: `Some(true)` for the real
+    /// piece, `Some(false)` for a blob.
+    pub fn piece_shown(&self, tile: Tile, hovered: Option<Tile>) -> Option<bool> {
+        match self.visibility {
+            Visibility::Normal => Some(true),
+            Visibility::Blindfold => None,
+            Visibility::Blobs => Some(false),
+            Visibility::RevealOnHover => {
+                (hovered == Some(tile) || self.selected == Some(tile)).then_some(true)
+            }
+        }
+    }
+    pub fn render_blob(&self, painter: &Painter, rect: Rect, colour: Colour) {
+        let (fill, outline) = if colour.white() {
+            (Color32::from_gray(240), Color32::from_gray(40))
+        } else {
+            (Color32::from_gray(30), Color32::from_gray(200))
+        };
+        let radius = rect.width() * 0.32;
+        painter.circle(rect.center(), radius, fill, Stroke::new(rect.width() * 0.03, outline));
+    }
+    /// The square under the pointer, if it is over the board.
+    pub fn hovered_tile(&self, rect: Rect, hover: Option<Pos2>) -> Option<Tile> {
+        let pos = hover.filter(|p| rect.contains(*p))?;
+        let (x, y) = self.screen_to_tile(pos, rect.min);
+        Tile::new_xy(x as u8, y as u8)
+    }
+    pub fn render_visibility_settings(&mut self, ui: &mut Ui) {
+        let before = self.visibility;
+        ui.horizontal(|ui| {
+            ui.label("Board display");
+            ComboBox::from_id_salt("play_visibility")
+                .selected_text(self.visibility.label())
+                .show_ui(ui, |ui| {
+                    for v in Visibility::ALL {
+                        ui.selectable_value(&mut self.visibility, v, v.label());
+                    }
+                });
+        });
+        if self.visibility != before {
+            self.selected = None;
+            self.focus_move_entry = self.visibility == Visibility::Blindfold;
+        }
+        if self.visibility == Visibility::Blindfold {
+            ui.label(RichText::new("Type moves in the box under the board, the move list keeps the score").weak());
+        }
+    }
+}
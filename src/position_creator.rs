@@ -1,8 +1,25 @@
-use chess_lib::{Board, CastlingRights, Colour, Piece};
-use egui::{load::SizedTexture, Color32, ComboBox, Context, Image, ImageButton, Key, TextureHandle, Ui, Vec2};
+use chess_lib::{Board, CastlingRights, Colour, Piece, Tile};
+use egui::{load::SizedTexture, Color32, ComboBox, Context, DragValue, Image, ImageButton, Key, Rect, TextureHandle, Ui, Vec2};
 
+use crate::{
+    clipboard::ClipboardReader,
+    notation::{parse_tile, tile_name},
+};
+
+mod clipboard;
+pub mod generate;
 mod helper;
+mod history;
+mod import;
+mod input;
 mod render;
+mod transform;
+use generate::GenerateSettings;
+use history::Snapshot;
+use import::ImportSettings;
+use input::DraggedPiece;
+pub mod validate;
+use validate::{castling_rook_files, en_passant_squares, validate, validate_castling_files, Problem, CASTLING_RIGHTS};
 
 pub struct PositionTab
 {
@@ -11,12 +28,40 @@ pub struct PositionTab
     pub board_size: f32,
     pub flipped: bool,
     pub selected_piece: Option<(Piece, bool)>,
+    /// Paint the selected piece on click/drag instead of dragging pieces around.
+    pub paint_mode: bool,
+    pub dragging: Option<DraggedPiece>,
+    board_rect: Option<Rect>,
     pub inputted_fen: String,
 
+    // FEN fields that aren't part of the piece placement
+    pub chess960: bool,
+    /// Castling rook files in Chess960 mode, in `CASTLING_RIGHTS` order.
+    pub castling_files: [Option<u8>; 4],
+    pub en_passant: Option<Tile>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+
+    pub fen_error: Option<String>,
+    clipboard: ClipboardReader,
+    /// What the last copy or paste did.
+    pub clipboard_note: Option<String>,
+    pub problems: Vec<Problem>,
+
+    pub generate: GenerateSettings,
+    pub import: ImportSettings,
+
+    pub undo_stack: Vec<Snapshot>,
+    pub redo_stack: Vec<Snapshot>,
+    pending_edit: Option<Snapshot>,
+    edit_restored: bool,
+
     pub atlas: TextureHandle,
     
     pub should_close: bool,
     pub change_tab: bool,
+    /// Asks the app to open the current position in the Drills tab.
+    pub drill_requested: bool,
 }
 
 impl PositionTab
@@ -26,41 +71,152 @@ impl PositionTab
             Some(pos) => Board::new_from_fen(pos).unwrap(),
             None => Board::new(),
         };
-        Self { 
-            
-            board: board, 
+        let mut tab = Self {
+            board: board.clone(),
 
             board_size: 400.0,
             flipped: false,
             selected_piece: None,
+            paint_mode: false,
+            dragging: None,
+            board_rect: None,
             inputted_fen: String::new(),
 
+            chess960: false,
+            castling_files: [None; 4],
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+
+            fen_error: None,
+            clipboard: ClipboardReader::default(),
+            clipboard_note: None,
+            problems: Vec::new(),
+
+            generate: GenerateSettings::default(),
+            import: ImportSettings::default(),
+
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            pending_edit: None,
+            edit_restored: false,
+
             atlas,
 
             should_close: false,
             change_tab: false,
+            drill_requested: false,
+        };
+        tab.set_board(board);
+        tab
+    }
+    /// Replaces the edited position, taking the en passant square and move
+    /// counters from the board's own FEN.
+    pub fn set_board(&mut self, board: Board) {
+        let fen = board.to_fen();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        self.en_passant = fields.get(3).and_then(|f| parse_tile(f));
+        self.halfmove_clock = fields.get(4).and_then(|f| f.parse().ok()).unwrap_or(0);
+        self.fullmove_number = fields.get(5).and_then(|f| f.parse().ok()).unwrap_or(1);
+        self.board = board;
+    }
+    /// The board's FEN with the edited en passant square and move counters.
+    /// In Chess960 mode the castling field names the rook files (Shredder-FEN).
+    pub fn fen(&self) -> String {
+        if !self.chess960 {
+            return self.lib_fen();
         }
+        let castling: String = self
+            .castling_files
+            .iter()
+            .zip(CASTLING_RIGHTS)
+            .filter_map(|(file, (_, white, _))| {
+                let c = (b'a' + (*file)?) as char;
+                Some(if white { c.to_ascii_uppercase() } else { c })
+            })
+            .collect();
+        let castling = if castling.is_empty() { "-".to_string() } else { castling };
+        self.fen_with_castling(&castling)
+    }
+    /// The FEN as chess_lib reads it, with only orthodox `KQkq` castling. The
+    /// Play tab takes `fen()` instead and plays other Chess960 rights itself.
+    pub fn lib_fen(&self) -> String {
+        let fen = self.board.to_fen();
+        let castling = fen.split_whitespace().nth(2).unwrap_or("-").to_string();
+        self.fen_with_castling(&castling)
+    }
+    fn fen_with_castling(&self, castling: &str) -> String {
+        let fen = self.board.to_fen();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let ep = self.en_passant.map(tile_name).unwrap_or("-".to_string());
+        format!(
+            "{} {} {} {} {} {}",
+            fields.first().unwrap_or(&"8/8/8/8/8/8/8/8"),
+            fields.get(1).unwrap_or(&"w"),
+            castling,
+            ep,
+            self.halfmove_clock,
+            self.fullmove_number,
+        )
     }
     pub fn render(&mut self, ctx: &Context) {
-        ctx.input(|i| {
-            if i.key_pressed(Key::Escape) {
-                self.should_close = true;
-            }
-        });
+        if !ctx.wants_keyboard_input() {
+            ctx.input(|i| {
+                if i.key_pressed(Key::Escape) {
+                    self.should_close = true;
+                }
+                if i.modifiers.ctrl && i.key_pressed(Key::Z) {
+                    self.undo_edit();
+                }
+                if i.modifiers.ctrl && i.key_pressed(Key::Y) {
+                    self.redo_edit();
+                }
+            });
+        }
         let screen_size = ctx.screen_rect();
         let is_portrait = screen_size.height() > screen_size.width();
 
-        self.render_board(ctx);
+        self.problems = validate(&self.board, self.en_passant, self.halfmove_clock);
+        if self.chess960 {
+            self.problems.extend(validate_castling_files(&self.board, &self.castling_files));
+        }
+
+        let before = self.snapshot();
+
+        self.poll_generation(ctx);
+        self.handle_dropped_image(ctx);
+        self.handle_clipboard(ctx);
+        // Side panel first so the board fills the remaining space
         self.render_side_panel(ctx);
+        self.render_board(ctx);
+        self.handle_drop(ctx);
+        self.render_dragged(ctx);
+
+        let pointer_down = ctx.input(|i| i.pointer.any_down());
+        self.track_edit(before, pointer_down);
+        if !pointer_down {
+            self.finish_pending_edit();
+        }
     }
     pub fn render_side_panel(&mut self, ctx: &Context) {
         egui::SidePanel::right("piece_selection")
             .resizable(true)
             .show(ctx, |ui| {
-                ui.heading("Pieces");
-                self.render_piece_buttons(ui);
-                ui.separator();
-                self.render_helper_buttons(ui);
+                egui::ScrollArea::vertical()
+                    .id_salt("scroll_position_settings")
+                    .show(ui, |ui| {
+                        ui.heading("Pieces");
+                        self.render_piece_buttons(ui);
+                        ui.separator();
+                        self.render_helper_buttons(ui);
+                        ui.separator();
+                        self.render_transform_buttons(ui);
+                        ui.separator();
+                        self.render_generate_panel(ui);
+                        self.render_import_panel(ui);
+                        ui.separator();
+                        self.render_problems(ui);
+                    });
             });
     }
 
@@ -103,7 +259,10 @@ impl PositionTab
                 .bg_fill(color),
         );
 
-        let response = ui.add(button).interact(egui::Sense::click());
+        let response = ui.add(button).interact(egui::Sense::click_and_drag());
+        if response.drag_started() {
+            self.dragging = Some(DraggedPiece { piece, white: is_white });
+        }
         if response.clicked() {
             if is_selected {
                 self.selected_piece = None; // Deselect if already selected
@@ -118,17 +277,28 @@ impl PositionTab
         ui.add_space(8.0);
 
         if ui.button("Clear board").clicked() {
-            self.board = Board::new_empty();
+            self.set_board(Board::new_empty());
         }
 
         ui.add_space(8.0);
         
         if ui.button("Reset board").clicked() {
-            self.board = Board::new();
+            self.set_board(Board::new());
         }
 
         ui.add_space(8.0);
 
+        if ui.button("Flip board").clicked() {
+            self.flipped = !self.flipped;
+        }
+
+        ui.add_space(8.0);
+
+        ui.checkbox(&mut self.paint_mode, "Paint mode")
+            .on_hover_text("Click or drag to paint the selected piece instead of moving pieces");
+
+        ui.add_space(8.0);
+
         ui.label("Select board turn");
         let text = if self.board.turn.white() { "White" } else { "Black" };
         ComboBox::from_id_salt("board_turn")
@@ -140,30 +310,185 @@ impl PositionTab
 
         ui.add_space(8.0);
 
-        ui.label("White castling");
-        self.castling_right_checkbox(ui, CastlingRights::WHITE_KINGSIDE, "Kingside");
-        self.castling_right_checkbox(ui, CastlingRights::WHITE_QUEENSIDE, "Queenside");
+        if ui.checkbox(&mut self.chess960, "Chess960 castling").changed() && self.chess960 {
+            // Carry over the orthodox rights as their a/h-file rooks
+            for (i, (right, _, _)) in CASTLING_RIGHTS.into_iter().enumerate() {
+                let kingside = matches!(right, CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_KINGSIDE);
+                self.castling_files[i] = self.board.castling.contains(right).then_some(if kingside { 7 } else { 0 });
+            }
+        }
 
         ui.add_space(4.0);
 
-        ui.label("Black castling");
-        self.castling_right_checkbox(ui, CastlingRights::BLACK_KINGSIDE, "Kingside");
-        self.castling_right_checkbox(ui, CastlingRights::BLACK_QUEENSIDE, "Queenside");
+        if self.chess960 {
+            self.render_castling_files(ui);
+        } else {
+            ui.label("White castling");
+            self.castling_right_checkbox(ui, CastlingRights::WHITE_KINGSIDE, "Kingside");
+            self.castling_right_checkbox(ui, CastlingRights::WHITE_QUEENSIDE, "Queenside");
+
+            ui.add_space(4.0);
+
+            ui.label("Black castling");
+            self.castling_right_checkbox(ui, CastlingRights::BLACK_KINGSIDE, "Kingside");
+            self.castling_right_checkbox(ui, CastlingRights::BLACK_QUEENSIDE, "Queenside");
+        }
+
+        ui.add_space(8.0);
+
+        self.render_fen_fields(ui);
 
         ui.add_space(8.0);
 
-        if ui.button("Play position").clicked() {
+        let playable = self.problems.is_empty();
+        let play = ui
+            .add_enabled(playable, egui::Button::new("Play position"))
+            .on_disabled_hover_text("Fix the problems listed below first");
+        if play.clicked() {
             self.change_tab = true;
         }
+        let drill = ui
+            .add_enabled(playable, egui::Button::new("Drill position"))
+            .on_hover_text("Practise this position against the engine in the Drills tab");
+        if drill.clicked() {
+            self.drill_requested = true;
+        }
 
         ui.add_space(8.0);
 
-        ui.text_edit_singleline(&mut self.inputted_fen);
+        if ui.text_edit_singleline(&mut self.inputted_fen).changed() {
+            self.fen_error = None;
+        }
         if ui.button("Load FEN position").clicked() {
             let b = Board::new_from_fen(&self.inputted_fen);
             match b {
-                Ok(board) => self.board = board,
-                Err(e) => (),
+                Ok(board) => {
+                    self.set_board(board);
+                    self.fen_error = None;
+                }
+                Err(e) => self.fen_error = Some(e),
+            }
+        }
+        if let Some(e) = &self.fen_error {
+            ui.colored_label(Color32::RED, format!("Invalid FEN: {}", e));
+        }
+
+        ui.add_space(4.0);
+
+        self.render_clipboard_buttons(ui);
+    }
+    pub fn render_transform_buttons(&mut self, ui: &mut Ui) {
+        ui.heading("Edit");
+
+        ui.add_space(4.0);
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!self.undo_stack.is_empty(), egui::Button::new("Undo")).clicked() {
+                self.undo_edit();
+            }
+            if ui.add_enabled(!self.redo_stack.is_empty(), egui::Button::new("Redo")).clicked() {
+                self.redo_edit();
+            }
+        });
+
+        ui.add_space(8.0);
+
+        if ui.button("Flip colours").on_hover_text("Mirror the position for the other side").clicked() {
+            self.flip_colours();
+        }
+        if ui.button("Mirror files").on_hover_text("Mirror the position left to right").clicked() {
+            self.mirror_files();
+        }
+        if ui.button("Swap side to move").clicked() {
+            self.swap_turn();
+        }
+
+        ui.add_space(4.0);
+
+        ui.label("Shift pieces");
+        ui.horizontal(|ui| {
+            // Shift directions follow the screen, so they invert when flipped
+            let dir = if self.flipped { -1 } else { 1 };
+            for (label, dx, dy) in [("⬅", -dir, 0), ("➡", dir, 0), ("⬆", 0, dir), ("⬇", 0, -dir)] {
+                if ui.add_enabled(self.can_shift(dx, dy), egui::Button::new(label)).clicked() {
+                    self.shift(dx, dy);
+                }
+            }
+        });
+    }
+    pub fn render_problems(&mut self, ui: &mut Ui) {
+        ui.heading("Position check");
+
+        ui.add_space(4.0);
+
+        if self.problems.is_empty() {
+            ui.colored_label(Color32::GREEN, "Position is legal");
+            return;
+        }
+        for problem in &self.problems {
+            ui.colored_label(Color32::RED, format!("• {}", problem.message));
+        }
+    }
+
+    pub fn render_fen_fields(&mut self, ui: &mut Ui) {
+        ui.label("En passant square");
+        let squares = en_passant_squares(&self.board);
+        let text = self.en_passant.map(tile_name).unwrap_or("None".to_string());
+        ComboBox::from_id_salt("en_passant")
+            .selected_text(text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.en_passant, None, "None");
+                for t in squares {
+                    ui.selectable_value(&mut self.en_passant, Some(t), tile_name(t));
+                }
+            });
+
+        ui.add_space(4.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Halfmove clock");
+            ui.add(DragValue::new(&mut self.halfmove_clock).range(0..=150));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Fullmove number");
+            ui.add(DragValue::new(&mut self.fullmove_number).range(1..=999));
+        });
+    }
+
+    /// Chess960 castling: pick the rook on each side of the king by its file.
+    fn render_castling_files(&mut self, ui: &mut Ui) {
+        for (i, (right, white, label)) in CASTLING_RIGHTS.into_iter().enumerate() {
+            let colour = if white { Colour::White } else { Colour::Black };
+            let kingside = matches!(right, CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_KINGSIDE);
+            let choices = castling_rook_files(&self.board, colour, kingside);
+
+            let text = match self.castling_files[i] {
+                Some(f) => format!("{}-file rook", (b'a' + f) as char),
+                None => "None".to_string(),
+            };
+            let before = self.castling_files[i];
+            ui.horizontal(|ui| {
+                ui.label(label);
+                ComboBox::from_id_salt(("castling_file", i))
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.castling_files[i], None, "None");
+                        for f in choices {
+                            ui.selectable_value(&mut self.castling_files[i], Some(f), format!("{}-file rook", (b'a' + f) as char));
+                        }
+                    });
+            });
+
+            if self.castling_files[i] != before {
+                // The board keeps the rights chess_lib plays itself, `fen()` has them all
+                let orthodox = self.castling_files[i] == Some(if kingside { 7 } else { 0 })
+                    && self.board.get_piece_at_tile(Tile::new_xy(4, if white { 0 } else { 7 }).unwrap())
+                        == Some((Piece::King, colour));
+                if orthodox {
+                    self.board.castling.insert(right);
+                } else {
+                    self.board.castling.remove(right);
+                }
             }
         }
     }
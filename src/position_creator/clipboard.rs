@@ -0,0 +1,94 @@
+use egui::{Context, RichText, Ui};
+
+use crate::{
+    chess960,
+    clipboard::{classify, copy_pressed, final_board, pasted_text, Pasted},
+    position_creator::PositionTab,
+    share::{link_game, make_link, share_base, SharedLink},
+};
+
+impl PositionTab
+{
+    pub fn copy_fen(&mut self, ctx: &Context) {
+        ctx.copy_text(self.fen());
+        self.clipboard_note = Some("Copied the FEN".to_string());
+    }
+    /// A pasted FEN replaces the position, a pasted game gives the position it ends in.
+    pub fn paste(&mut self, text: &str) {
+        let result = classify(text).and_then(|pasted| match pasted {
+            Pasted::Fen(board, fen) => {
+                self.set_board(board);
+                self.set_castling_from(&fen);
+                Ok("Pasted the position".to_string())
+            }
+            Pasted::Pgn(game) => final_board(&game).map(|board| {
+                self.set_board(board);
+                format!("Pasted the position after {} moves", game.moves.len())
+            }),
+        });
+        self.clipboard_note = Some(result.unwrap_or_else(|e| e));
+    }
+    /// A link to the web build that opens this position in the editor.
+    pub fn share_link(&self) -> Option<String> {
+        let link = SharedLink {
+            tab: Some("editor".to_string()),
+            fen: Some(self.fen()),
+            moves: None,
+        };
+        Some(make_link(&share_base()?, &link))
+    }
+    pub fn copy_share_link(&mut self, ctx: &Context) {
+        if let Some(link) = self.share_link() {
+            ctx.copy_text(link);
+            self.clipboard_note = Some("Copied a link to this position".to_string());
+        }
+    }
+    /// Shows the position a share link ends in. A position with no moves
+    /// keeps its Chess960 rook files.
+    pub fn open_link(&mut self, link: &SharedLink) -> Result<(), String> {
+        let (mut board, moves) = link_game(link)?;
+        for m in &moves {
+            board.make_move_unchecked(*m);
+        }
+        self.set_board(board);
+        if let Some(fen) = link.fen.as_deref().filter(|_| moves.is_empty()) {
+            self.set_castling_from(fen);
+        }
+        Ok(())
+    }
+    /// Chess960 mode and rook files as `fen`'s castling field gives them.
+    fn set_castling_from(&mut self, fen: &str) {
+        self.chess960 = chess960::is_chess960_fen(fen);
+        self.castling_files = chess960::castling_files(fen);
+    }
+    /// Ctrl+C copies the FEN, Ctrl+V pastes, and a Paste button's read lands here.
+    pub fn handle_clipboard(&mut self, ctx: &Context) {
+        if copy_pressed(ctx) {
+            self.copy_fen(ctx);
+        }
+        if let Some(text) = pasted_text(ctx) {
+            self.paste(&text);
+        }
+        match self.clipboard.take() {
+            Some(Ok(text)) => self.paste(&text),
+            Some(Err(e)) => self.clipboard_note = Some(e),
+            None => (),
+        }
+    }
+    pub fn render_clipboard_buttons(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Copy FEN").clicked() {
+                self.copy_fen(ui.ctx());
+            }
+            if ui.button("Paste").on_hover_text("A FEN, or a PGN game for the position it ends in").clicked() {
+                self.clipboard.request(ui.ctx());
+            }
+        });
+        if share_base().is_some() && ui.button("Copy share link").on_hover_text("A link that opens this position").clicked() {
+            self.copy_share_link(ui.ctx());
+        }
+        if let Some(note) = &self.clipboard_note {
+            ui.label(RichText::new(note).weak());
+        }
+    }
+}
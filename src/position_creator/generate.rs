@@ -0,0 +1,355 @@
+use chess_lib::{Board, Colour, Piece, Tile};
+use egui::{ComboBox, Context, DragValue, Ui};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    analysis::{material_balance, LineSearch},
+    background::{Background, Work},
+    notation::piece_from_char,
+    position_creator::{transform::build, validate::validate, PositionTab},
+};
+
+/// How many random placements to try before giving up on the constraints.
+const MAX_ATTEMPTS: usize = 20_000;
+/// How many placements may go through the engine for the material window.
+const MAX_ENGINE_CHECKS: usize = 1_000;
+/// The engine line that settles the material: two plies at depth four, enough
+/// for a hanging piece to be taken.
+const SETTLE_DEPTH: u8 = 4;
+const SETTLE_PLIES: usize = 2;
+
+/// Pieces added at random when extra pieces are asked for.
+const FILLER: [Piece; 5] = [Piece::Pawn, Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook];
+
+pub struct GenerateSettings
+{
+    pub white_material: String,
+    pub black_material: String,
+    /// `None` picks the side to move at random.
+    pub side_to_move: Option<Colour>,
+    pub no_checks: bool,
+    /// Adds random pieces of either colour on top of the given material, up to
+    /// a total in `min_pieces..=max_pieces`. Off keeps the material exact.
+    pub extra_pieces: bool,
+    pub min_pieces: usize,
+    pub max_pieces: usize,
+    pub use_balance: bool,
+    /// Material in centipawns from White's side after a short engine line. This
+    /// is a material count, not an evaluation of the position.
+    pub balance_min: i32,
+    pub balance_max: i32,
+    pub seed: u64,
+    pub status: Option<String>,
+    running: Option<Background<Generation>>,
+}
+impl Default for GenerateSettings
+{
+    fn default() -> Self {
+        Self {
+            white_material: "KRP".to_string(),
+            black_material: "KR".to_string(),
+            side_to_move: Some(Colour::White),
+            no_checks: true,
+            extra_pieces: false,
+            min_pieces: 2,
+            max_pieces: 32,
+            use_balance: false,
+            balance_min: -100,
+            balance_max: 100,
+            seed: 1,
+            status: None,
+            running: None,
+        }
+    }
+}
+
+/// One side's material, which must have exactly one king.
+fn parse_material(s: &str, side: &str) -> Result<Vec<Piece>, String> {
+    let pieces = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| piece_from_char(c).ok_or(format!("'{}' is not a piece letter (KQRBNP)", c)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let kings = pieces.iter().filter(|&&p| p == Piece::King).count();
+    if kings != 1 {
+        return Err(format!("{}'s material needs exactly one king, not {}", side, kings));
+    }
+    Ok(pieces)
+}
+
+/// The search for a random legal position meeting the settings. Placements
+/// are cheap and tried in a batch per step; a candidate for the material window
+/// then gets a short engine line, one search per step, so hanging pieces count
+/// as lost. Ends with the position, or why there isn't one.
+pub struct Generation
+{
+    white: Vec<Piece>,
+    black: Vec<Piece>,
+    side_to_move: Option<Colour>,
+    no_checks: bool,
+    /// The range the total piece count is picked from, when extra pieces are added.
+    extra: Option<(usize, usize)>,
+    balance: Option<(i32, i32)>,
+    rng: StdRng,
+    attempts: usize,
+    engine_checks: usize,
+    /// The placement waiting on its engine line.
+    candidate: Option<(Board, LineSearch)>,
+    done: bool,
+}
+impl Generation
+{
+    pub fn new(settings: &GenerateSettings) -> Result<Self, String> {
+        let white = parse_material(&settings.white_material, "White")?;
+        let black = parse_material(&settings.black_material, "Black")?;
+
+        let required = white.len() + black.len();
+        if settings.extra_pieces && required > settings.max_pieces {
+            return Err(format!("The material has {} pieces, more than the maximum of {}", required, settings.max_pieces));
+        }
+        let min = settings.min_pieces.max(required);
+        if settings.extra_pieces && min > settings.max_pieces.min(64) {
+            return Err("The piece-count range is empty".to_string());
+        }
+
+        Ok(Self {
+            white,
+            black,
+            side_to_move: settings.side_to_move,
+            no_checks: settings.no_checks,
+            extra: settings.extra_pieces.then_some((min, settings.max_pieces.min(64))),
+            balance: settings.use_balance.then_some((settings.balance_min, settings.balance_max)),
+            rng: StdRng::seed_from_u64(settings.seed),
+            attempts: 0,
+            engine_checks: 0,
+            candidate: None,
+            done: false,
+        })
+    }
+    /// A random placement of the material, if it makes a legal position within the constraints.
+    fn place(&mut self) -> Option<Board> {
+        let rng = &mut self.rng;
+        let mut pieces: Vec<(Piece, Colour)> = self
+            .white
+            .iter()
+            .map(|p| (*p, Colour::White))
+            .chain(self.black.iter().map(|p| (*p, Colour::Black)))
+            .collect();
+
+        let total = match self.extra {
+            Some((min, max)) => rng.random_range(min..=max),
+            None => pieces.len(),
+        };
+        while pieces.len() < total {
+            let piece = FILLER[rng.random_range(0..FILLER.len())];
+            let colour = if rng.random_bool(0.5) { Colour::White } else { Colour::Black };
+            pieces.push((piece, colour));
+        }
+
+        let placed = place_randomly(&pieces, rng)?;
+        let turn = match self.side_to_move {
+            Some(c) => c,
+            None if rng.random_bool(0.5) => Colour::White,
+            None => Colour::Black,
+        };
+        let board = build(&placed, turn);
+
+        if !validate(&board, None, 0).is_empty() {
+            return None;
+        }
+        if self.no_checks && (board.is_in_check(Colour::White) || board.is_in_check(Colour::Black)) {
+            return None;
+        }
+        Some(board)
+    }
+    fn finish(&mut self, result: Result<Board, String>) -> Result<Board, String> {
+        self.done = true;
+        result
+    }
+}
+impl Work for Generation
+{
+    type Output = Result<Board, String>;
+
+    fn step(&mut self) -> Option<Self::Output> {
+        if let Some((board, search)) = &mut self.candidate {
+            let (_, end) = search.step()?;
+            let board = board.clone();
+            self.candidate = None;
+            let balance = material_balance(&end);
+            if self.balance.is_some_and(|(min, max)| (min..=max).contains(&balance)) {
+                return Some(self.finish(Ok(board)));
+            }
+            return None;
+        }
+        if self.engine_checks == MAX_ENGINE_CHECKS {
+            return Some(self.finish(Err(format!(
+                "No position in the material window after {} engine checks, try a wider window",
+                MAX_ENGINE_CHECKS
+            ))));
+        }
+        while self.attempts < MAX_ATTEMPTS {
+            self.attempts += 1;
+            let Some(board) = self.place() else {
+                continue;
+            };
+            if self.balance.is_none() {
+                return Some(self.finish(Ok(board)));
+            }
+            self.engine_checks += 1;
+            self.candidate = Some((board.clone(), LineSearch::new(&board, None, SETTLE_DEPTH, SETTLE_PLIES)));
+            return None;
+        }
+        Some(self.finish(Err(format!("No position found in {} attempts, try looser constraints", MAX_ATTEMPTS))))
+    }
+    fn finished(&self) -> bool {
+        self.done
+    }
+}
+
+/// Puts each piece on a distinct random square, keeping pawns off the back ranks.
+fn place_randomly(pieces: &[(Piece, Colour)], rng: &mut StdRng) -> Option<Vec<(Piece, Colour, Tile)>> {
+    let mut taken = [false; 64];
+    let mut placed = Vec::with_capacity(pieces.len());
+
+    for &(piece, colour) in pieces {
+        let (low, high) = if piece == Piece::Pawn { (1, 6) } else { (0, 7) };
+        let free: Vec<(u8, u8)> = (low..=high)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .filter(|&(x, y)| !taken[(y * 8 + x) as usize])
+            .collect();
+        if free.is_empty() {
+            return None;
+        }
+        let (x, y) = free[rng.random_range(0..free.len())];
+        taken[(y * 8 + x) as usize] = true;
+        placed.push((piece, colour, Tile::new_xy(x, y)?));
+    }
+    Some(placed)
+}
+
+impl PositionTab
+{
+    /// Takes in the generated position once the background search is done with it.
+    pub fn poll_generation(&mut self, ctx: &Context) {
+        let g = &mut self.generate;
+        let Some(running) = &mut g.running else {
+            return;
+        };
+        let results = running.poll(ctx);
+        if results.is_empty() && !running.finished() {
+            return;
+        }
+        g.running = None;
+        match results.into_iter().next() {
+            Some(Ok(board)) => {
+                g.status = Some(format!("Generated with seed {}", g.seed));
+                // Step the seed so the next press gives a new, still reproducible, position
+                g.seed = g.seed.wrapping_add(1);
+                self.set_board(board);
+            }
+            Some(Err(e)) => g.status = Some(e),
+            None => g.status = Some("Generation stopped without a position".to_string()),
+        }
+    }
+    pub fn render_generate_panel(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("Generate").show(ui, |ui| {
+            let g = &mut self.generate;
+
+            egui::Grid::new("generate_grid").num_columns(2).show(ui, |ui| {
+                ui.label("White material");
+                ui.text_edit_singleline(&mut g.white_material);
+                ui.end_row();
+
+                ui.label("Black material");
+                ui.text_edit_singleline(&mut g.black_material);
+                ui.end_row();
+
+                ui.label("Side to move");
+                let text = match g.side_to_move {
+                    Some(Colour::White) => "White",
+                    Some(Colour::Black) => "Black",
+                    None => "Random",
+                };
+                ComboBox::from_id_salt("generate_turn")
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut g.side_to_move, Some(Colour::White), "White");
+                        ui.selectable_value(&mut g.side_to_move, Some(Colour::Black), "Black");
+                        ui.selectable_value(&mut g.side_to_move, None, "Random");
+                    });
+                ui.end_row();
+
+                ui.checkbox(&mut g.extra_pieces, "Extra pieces")
+                    .on_hover_text("Random pieces for either side on top of the material above");
+                ui.add_enabled_ui(g.extra_pieces, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut g.min_pieces).range(2..=32));
+                        ui.label("to");
+                        ui.add(DragValue::new(&mut g.max_pieces).range(2..=32));
+                        ui.label("pieces in all");
+                    });
+                });
+                ui.end_row();
+
+                ui.label("Seed");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut g.seed));
+                    if ui.button("🎲").on_hover_text("Random seed").clicked() {
+                        g.seed = rand::rng().random();
+                    }
+                });
+                ui.end_row();
+            });
+
+            ui.checkbox(&mut g.no_checks, "No side in check");
+            ui.checkbox(&mut g.use_balance, "Material window")
+                .on_hover_text("Material in centipawns (White's view) once a short engine line has played out the hanging pieces. Not an evaluation");
+            if g.use_balance {
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut g.balance_min).range(-4000..=4000));
+                    ui.label("to");
+                    ui.add(DragValue::new(&mut g.balance_max).range(-4000..=4000));
+                });
+            }
+
+            ui.add_space(4.0);
+
+            if g.running.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Generating…");
+                    if ui.button("Stop").clicked() {
+                        g.running = None;
+                        g.status = Some("Stopped".to_string());
+                    }
+                });
+            } else if ui.button("Generate").clicked() {
+                match Generation::new(g) {
+                    Ok(work) => {
+                        g.running = Some(Background::start(work));
+                        g.status = None;
+                    }
+                    Err(e) => g.status = Some(e),
+                }
+            }
+            if let Some(status) = &self.generate.status {
+                ui.label(status);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn material_needs_one_king_a_side() {
+        assert_eq!(parse_material("K R P", "White").map(|p| p.len()), Ok(3));
+        assert!(parse_material("RP", "White").is_err());
+        assert!(parse_material("KKR", "Black").is_err());
+        assert!(parse_material("KX", "Black").is_err());
+    }
+}
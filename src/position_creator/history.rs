@@ -0,0 +1,89 @@
+use chess_lib::{Board, Tile};
+
+use crate::position_creator::PositionTab;
+
+/// How many edits are kept for undo.
+const MAX_HISTORY: usize = 200;
+
+/// Everything the editor can change, saved for undo/redo.
+#[derive(Clone)]
+pub struct Snapshot
+{
+    board: Board,
+    castling_files: [Option<u8>; 4],
+    en_passant: Option<Tile>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    fen: String,
+}
+
+impl PositionTab
+{
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board.clone(),
+            castling_files: self.castling_files,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            fen: self.fen(),
+        }
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.board = snapshot.board;
+        self.castling_files = snapshot.castling_files;
+        self.en_passant = snapshot.en_passant;
+        self.halfmove_clock = snapshot.halfmove_clock;
+        self.fullmove_number = snapshot.fullmove_number;
+    }
+    /// Records `before` as an undo step if the position has changed since.
+    /// While a pointer button is held (painting, dragging a value) the step is
+    /// held back, so a whole drag undoes in one go.
+    pub fn track_edit(&mut self, before: Snapshot, pointer_down: bool) {
+        // Undo and redo change the position without being edits themselves
+        if self.edit_restored {
+            self.edit_restored = false;
+            self.pending_edit = None;
+            return;
+        }
+        if self.fen() == before.fen {
+            return;
+        }
+        if pointer_down {
+            if self.pending_edit.is_none() {
+                self.pending_edit = Some(before);
+            }
+            return;
+        }
+        let before = self.pending_edit.take().unwrap_or(before);
+        self.push_edit(before);
+    }
+    /// Flushes a held back step once the pointer is released without further changes.
+    pub fn finish_pending_edit(&mut self) {
+        if let Some(before) = self.pending_edit.take().filter(|b| b.fen != self.fen()) {
+            self.push_edit(before);
+        }
+    }
+    /// Records an edit step, dropping the oldest past `MAX_HISTORY`.
+    fn push_edit(&mut self, before: Snapshot) {
+        self.undo_stack.push(before);
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+    pub fn undo_edit(&mut self) {
+        if let Some(s) = self.undo_stack.pop() {
+            self.redo_stack.push(self.snapshot());
+            self.restore(s);
+            self.edit_restored = true;
+        }
+    }
+    pub fn redo_edit(&mut self) {
+        if let Some(s) = self.redo_stack.pop() {
+            self.undo_stack.push(self.snapshot());
+            self.restore(s);
+            self.edit_restored = true;
+        }
+    }
+}
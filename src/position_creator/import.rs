@@ -0,0 +1,152 @@
+use chess_lib::{CastlingRights, Colour, Piece, Tile};
+use egui::{ComboBox, Context, RichText, Ui};
+#[cfg(not(target_arch = "wasm32"))]
+use rfd::FileDialog;
+
+use crate::{
+    notation::tile_name,
+    position_creator::{transform::build, PositionTab},
+    recognize::{recognize, Orientation, Recognition},
+};
+
+pub struct ImportSettings
+{
+    pub orientation: Orientation,
+    pub status: Option<String>,
+}
+impl Default for ImportSettings
+{
+    fn default() -> Self {
+        Self {
+            orientation: Orientation::Auto,
+            status: None,
+        }
+    }
+}
+
+/// Image types the importer reads.
+const EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Castling rights whose king and rook are still on their home squares.
+const HOME_SQUARES: [(CastlingRights, Colour, u8, u8); 4] = [
+    (CastlingRights::WHITE_KINGSIDE, Colour::White, 0, 7),
+    (CastlingRights::WHITE_QUEENSIDE, Colour::White, 0, 0),
+    (CastlingRights::BLACK_KINGSIDE, Colour::Black, 7, 7),
+    (CastlingRights::BLACK_QUEENSIDE, Colour::Black, 7, 0),
+];
+
+fn summary(found: &Recognition) -> String {
+    let board = match found.board {
+        Some([x, y, w, h]) => format!("Found a {}x{} board at ({}, {})", w, h, x, y),
+        None => "No board grid stood out, so the whole image was read".to_string(),
+    };
+    let mut text = format!("{}. Read {} pieces.", board, found.pieces.len());
+    if !found.uncertain.is_empty() {
+        let squares: Vec<String> = found.uncertain.iter().map(|&t| tile_name(t)).collect();
+        text.push_str(&format!(" Check {}.", squares.join(", ")));
+    }
+    text
+}
+
+impl PositionTab
+{
+    /// Reads a screenshot into the editor, White to move, castling wherever
+    /// the king and rook haven't moved.
+    pub fn import_image(&mut self, bytes: &[u8]) {
+        let found = match recognize(bytes, self.import.orientation) {
+            Ok(found) => found,
+            Err(e) => {
+                self.import.status = Some(e);
+                return;
+            }
+        };
+        let mut board = build(&found.pieces, Colour::White);
+        for (right, colour, rank, rook_file) in HOME_SQUARES {
+            let at = |file: u8| Tile::new_xy(file, rank).and_then(|t| board.get_piece_at_tile(t));
+            if at(4) == Some((Piece::King, colour)) && at(rook_file) == Some((Piece::Rook, colour)) {
+                board.castling.insert(right);
+            }
+        }
+        self.chess960 = false;
+        self.castling_files = [None; 4];
+        self.set_board(board);
+        // Show the board the way the screenshot did
+        self.flipped = found.flipped;
+        self.import.status = Some(summary(&found));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_image(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("Images", &EXTENSIONS)
+            .pick_file()
+        else {
+            return;
+        };
+        match std::fs::read(&path) {
+            Ok(bytes) => self.import_image(&bytes),
+            Err(e) => self.import.status = Some(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Imports an image dropped onto the window. Browsers hand over the
+    /// bytes, native builds the path. Other kinds of file are left alone.
+    pub fn handle_dropped_image(&mut self, ctx: &Context) {
+        let Some(file) = ctx.input(|i| i.raw.dropped_files.first().cloned()) else {
+            return;
+        };
+        let name = match &file.path {
+            Some(path) => path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            None => file.name.clone(),
+        };
+        let extension = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+        let is_image = matches!(file.mime.as_str(), "image/png" | "image/jpeg")
+            || extension.is_some_and(|e| EXTENSIONS.contains(&e.as_str()));
+        if !is_image {
+            self.import.status = Some(format!("Only PNG and JPEG images can be imported, not {}", name));
+            return;
+        }
+        if let Some(bytes) = &file.bytes {
+            self.import_image(bytes);
+            return;
+        }
+        if let Some(path) = &file.path {
+            match std::fs::read(path) {
+                Ok(bytes) => self.import_image(&bytes),
+                Err(e) => self.import.status = Some(format!("Failed to read {}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    pub fn render_import_panel(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("Import from image").show(ui, |ui| {
+            ui.label(RichText::new("A PNG or JPEG of a 2D board, such as an exported diagram or a lichess screenshot").weak());
+            ui.label(RichText::new("Reads this app's pieces and the cburnett set best; others may need checking").weak());
+
+            ui.horizontal(|ui| {
+                ui.label("Orientation");
+                let orientation = &mut self.import.orientation;
+                ComboBox::from_id_salt("import_orientation")
+                    .selected_text(orientation.label())
+                    .show_ui(ui, |ui| {
+                        for o in [Orientation::Auto, Orientation::WhiteBottom, Orientation::BlackBottom] {
+                            ui.selectable_value(orientation, o, o.label());
+                        }
+                    });
+            });
+
+            ui.add_space(4.0);
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Open image").clicked() {
+                self.pick_image();
+            }
+            ui.label(RichText::new("Or drop an image onto the window").weak());
+
+            if let Some(status) = &self.import.status {
+                ui.add_space(4.0);
+                ui.label(status);
+            }
+        });
+    }
+}
@@ -0,0 +1,130 @@
+use chess_lib::{Colour, Piece, Tile};
+use egui::{Color32, Context, Id, LayerId, Order, PointerButton, Rect, Response, Vec2};
+
+use crate::position_creator::PositionTab;
+
+/// A piece being dragged from the palette or picked up off the board.
+#[derive(Debug, Clone, Copy)]
+pub struct DraggedPiece
+{
+    pub piece: Piece,
+    pub white: bool,
+}
+
+impl PositionTab
+{
+    fn tile_at(&self, response: &Response) -> Option<Tile> {
+        let p = response.interact_pointer_pos()?;
+        let (x, y) = self.screen_to_tile(p, response.rect.min);
+        Tile::new_xy(x as u8, y as u8)
+    }
+    /// Removes whatever piece is on `tile` and returns it.
+    pub fn take_piece(&mut self, tile: Tile) -> Option<(Piece, Colour)> {
+        let (p, c) = self.board.get_piece_at_tile(tile)?;
+        if c == Colour::White {
+            self.board.white.remove_piece_type(p, tile);
+        } else {
+            self.board.black.remove_piece_type(p, tile);
+        }
+        Some((p, c))
+    }
+    /// Puts a piece on `tile`, replacing anything already there.
+    pub fn put_piece(&mut self, tile: Tile, piece: Piece, white: bool) {
+        self.take_piece(tile);
+        if white {
+            self.board.white.place_piece(piece, tile);
+        } else {
+            self.board.black.place_piece(piece, tile);
+        }
+    }
+
+    pub fn board_input(&mut self, response: &Response) {
+        // The secondary button always erases
+        if response.dragged_by(PointerButton::Secondary) || response.secondary_clicked() {
+            if let Some(tile) = self.tile_at(response) {
+                self.take_piece(tile);
+            }
+            return;
+        }
+
+        if self.paint_mode {
+            self.paint_input(response);
+        } else {
+            self.drag_input(response);
+        }
+    }
+    /// Paints the selected piece on click or drag, or erases with no piece selected.
+    fn paint_input(&mut self, response: &Response) {
+        if !(response.dragged_by(PointerButton::Primary) || response.clicked()) {
+            return;
+        }
+        let Some(tile) = self.tile_at(response) else {
+            return;
+        };
+        match self.selected_piece {
+            Some((piece, white)) => self.put_piece(tile, piece, white),
+            None => {
+                self.take_piece(tile);
+            }
+        }
+    }
+    /// Picks pieces up to move them; a click still places the selected palette piece.
+    fn drag_input(&mut self, response: &Response) {
+        if response.drag_started_by(PointerButton::Primary) {
+            // Pick up from where the press started, not where the drag was detected
+            let origin = response.ctx.input(|i| i.pointer.press_origin());
+            let tile = origin.and_then(|p| {
+                let (x, y) = self.screen_to_tile(p, response.rect.min);
+                Tile::new_xy(x as u8, y as u8)
+            });
+            let Some(tile) = tile else {
+                return;
+            };
+            if let Some((piece, colour)) = self.take_piece(tile) {
+                self.dragging = Some(DraggedPiece { piece, white: colour.white() });
+            }
+            return;
+        }
+        if !response.clicked() {
+            return;
+        }
+        if let (Some(tile), Some((piece, white))) = (self.tile_at(response), self.selected_piece) {
+            self.put_piece(tile, piece, white);
+        }
+    }
+    /// Drops the dragged piece when the button is released: onto the square
+    /// under the pointer, or nowhere (deleting it) if that's off the board.
+    pub fn handle_drop(&mut self, ctx: &Context) {
+        let Some(dragged) = self.dragging else {
+            return;
+        };
+        let (released, pos) = ctx.input(|i| (!i.pointer.primary_down(), i.pointer.latest_pos()));
+        if !released {
+            ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+            return;
+        }
+        self.dragging = None;
+
+        let (Some(pos), Some(rect)) = (pos, self.board_rect) else {
+            return;
+        };
+        if rect.contains(pos) {
+            let (x, y) = self.screen_to_tile(pos, rect.min);
+            if let Some(tile) = Tile::new_xy(x as u8, y as u8) {
+                self.put_piece(tile, dragged.piece, dragged.white);
+            }
+        }
+    }
+    /// Draws the dragged piece under the pointer, above every panel.
+    pub fn render_dragged(&self, ctx: &Context) {
+        let Some(dragged) = self.dragging else {
+            return;
+        };
+        let Some(pos) = ctx.input(|i| i.pointer.latest_pos()) else {
+            return;
+        };
+        let painter = ctx.layer_painter(LayerId::new(Order::Tooltip, Id::new("dragged_piece")));
+        let rect = Rect::from_center_size(pos, Vec2::splat(self.board_size / 8.0));
+        painter.image(self.atlas.id(), rect, self.atlas_uv(&dragged.piece, dragged.white), Color32::WHITE);
+    }
+}
@@ -1,4 +1,4 @@
-use chess_lib::{Board, Colour, Piece, Tile};
+use chess_lib::{Board, Piece};
 use egui::{Color32, Context, Painter, Pos2, RichText, Vec2};
 
 use crate::position_creator::PositionTab;
@@ -31,49 +31,9 @@ impl PositionTab
                     egui::Sense::click_and_drag(),
                 );
 
-                if response.dragged_by(egui::PointerButton::Secondary) || response.secondary_clicked() {
-                    let pos = response.interact_pointer_pos();
-                    if let Some(p) = pos {
-                        let (x, y) = self.screen_to_tile(p, response.rect.min);
-                        let t = Tile::new_xy(x as u8, y as u8);
-                        if let Some(tile) = t {
-                            if let Some((p, w)) = self.board.get_piece_at_tile(tile) {
-                                if w == Colour::White {
-                                    self.board.white.remove_piece_type(p, tile);
-                                } else {
-                                    self.board.black.remove_piece_type(p, tile);
-                                }
-                            }
-                        }
-                    }
-                }
-                if response.dragged_by(egui::PointerButton::Primary) || response.clicked() {
-                    let pos = response.interact_pointer_pos();
-                    if let Some(p) = pos {
-                        let (x, y) = self.screen_to_tile(p, response.rect.min);
-                        let t = Tile::new_xy(x as u8, y as u8);
-                        if let Some(tile) = t {
-                            if let Some((piece, colour)) = self.selected_piece {
-                                if colour {
-                                    self.board.white.place_piece(piece, tile);
-                                } else {
-                                    self.board.black.place_piece(piece, tile);
-                                }
-                            } else { 
-                                if let Some((p, c)) = self.board.get_piece_at_tile(tile) {
-                                    if c == Colour::White {
-                                        self.board.white.remove_piece_type(p, tile);
-                                    } else {
-                                        self.board.black.remove_piece_type(p, tile);
-                                    }
-                                }
-                            }
-                        }
-
-                    }
-                        
-                }
-                
+                self.board_rect = Some(response.rect);
+                self.board_input(&response);
+
                 let origin = response.rect.min;
 
                 self.render_tiles(&painter, origin);
@@ -89,7 +49,7 @@ impl PositionTab
 
             ui.vertical_centered(|ui| {
                 let font_size = self.board_size * 0.02;
-                let fen_text = RichText::new(format!("FEN: {}", self.board.to_fen()))
+                let fen_text = RichText::new(format!("FEN: {}", self.fen()))
                     .monospace()
                     .size(font_size);
                 ui.label(fen_text);
@@ -106,7 +66,15 @@ impl PositionTab
                 let rect = self.tile_to_screen(file as f32, rank as f32, origin);
                 let light = Color32::from_rgb(240, 217, 181);
                 let dark  = Color32::from_rgb(181, 136,  99);
-                let clr   = if (file + rank) % 2 == 1 { light } else { dark };
+                let mut clr   = if (file + rank) % 2 == 1 { light } else { dark };
+
+                let offending = self.problems
+                    .iter()
+                    .flat_map(|p| p.tiles.iter())
+                    .any(|t| t.get_coords() == (file, rank));
+                if offending {
+                    clr = clr.blend(Color32::from_rgba_unmultiplied(255, 0, 0, 140));
+                }
                 painter.rect_filled(rect, 0.0, clr);
 
             }
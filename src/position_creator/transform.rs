@@ -0,0 +1,121 @@
+use chess_lib::{Board, CastlingRights, Colour, Piece, Tile};
+
+use crate::position_creator::{validate::pieces_of, PositionTab};
+
+const ALL_RIGHTS: [CastlingRights; 4] = [
+    CastlingRights::WHITE_KINGSIDE,
+    CastlingRights::WHITE_QUEENSIDE,
+    CastlingRights::BLACK_KINGSIDE,
+    CastlingRights::BLACK_QUEENSIDE,
+];
+
+fn other(colour: Colour) -> Colour {
+    if colour.white() { Colour::Black } else { Colour::White }
+}
+
+/// Every piece on the board as (piece, colour, tile).
+fn all_pieces(board: &Board) -> Vec<(Piece, Colour, Tile)> {
+    let mut pieces = Vec::new();
+    for colour in [Colour::White, Colour::Black] {
+        for piece in Piece::ALL_PIECES {
+            for t in pieces_of(board, colour, piece) {
+                pieces.push((piece, colour, t));
+            }
+        }
+    }
+    pieces
+}
+
+/// Builds a board from a piece list with the given side to move and no castling rights.
+pub fn build(pieces: &[(Piece, Colour, Tile)], turn: Colour) -> Board {
+    let mut board = Board::new_empty();
+    for right in ALL_RIGHTS {
+        board.castling.remove(right);
+    }
+    board.turn = turn;
+    for &(piece, colour, tile) in pieces {
+        if colour.white() {
+            board.white.place_piece(piece, tile);
+        } else {
+            board.black.place_piece(piece, tile);
+        }
+    }
+    board
+}
+
+/// Moves every piece by (dx, dy), or `None` if a piece would fall off the board.
+fn shifted(pieces: &[(Piece, Colour, Tile)], dx: i8, dy: i8) -> Option<Vec<(Piece, Colour, Tile)>> {
+    pieces
+        .iter()
+        .map(|&(p, c, t)| {
+            let (x, y) = t.get_coords();
+            let nx = u8::try_from(x as i8 + dx).ok()?;
+            let ny = u8::try_from(y as i8 + dy).ok()?;
+            Tile::new_xy(nx, ny).map(|t| (p, c, t))
+        })
+        .collect()
+}
+
+impl PositionTab
+{
+    /// Mirrors the position top to bottom and swaps the colours, giving the
+    /// same position for the other side.
+    pub fn flip_colours(&mut self) {
+        let pieces: Vec<_> = all_pieces(&self.board)
+            .into_iter()
+            .filter_map(|(p, c, t)| {
+                let (x, y) = t.get_coords();
+                Tile::new_xy(x, 7 - y).map(|t| (p, other(c), t))
+            })
+            .collect();
+
+        let mut board = build(&pieces, other(self.board.turn));
+        let swaps = [
+            (CastlingRights::WHITE_KINGSIDE, CastlingRights::BLACK_KINGSIDE),
+            (CastlingRights::WHITE_QUEENSIDE, CastlingRights::BLACK_QUEENSIDE),
+            (CastlingRights::BLACK_KINGSIDE, CastlingRights::WHITE_KINGSIDE),
+            (CastlingRights::BLACK_QUEENSIDE, CastlingRights::WHITE_QUEENSIDE),
+        ];
+        for (from, to) in swaps {
+            if self.board.castling.contains(from) {
+                board.castling.insert(to);
+            }
+        }
+
+        self.en_passant = self.en_passant.and_then(|t| {
+            let (x, y) = t.get_coords();
+            Tile::new_xy(x, 7 - y)
+        });
+        self.board = board;
+    }
+    /// Mirrors the position left to right. Castling rights can't survive this.
+    pub fn mirror_files(&mut self) {
+        let pieces: Vec<_> = all_pieces(&self.board)
+            .into_iter()
+            .filter_map(|(p, c, t)| {
+                let (x, y) = t.get_coords();
+                Tile::new_xy(7 - x, y).map(|t| (p, c, t))
+            })
+            .collect();
+
+        self.en_passant = self.en_passant.and_then(|t| {
+            let (x, y) = t.get_coords();
+            Tile::new_xy(7 - x, y)
+        });
+        self.board = build(&pieces, self.board.turn);
+    }
+    pub fn can_shift(&self, dx: i8, dy: i8) -> bool {
+        shifted(&all_pieces(&self.board), dx, dy).is_some()
+    }
+    /// Shifts all pieces by one file or rank, as long as none fall off the board.
+    pub fn shift(&mut self, dx: i8, dy: i8) {
+        if let Some(pieces) = shifted(&all_pieces(&self.board), dx, dy) {
+            self.board = build(&pieces, self.board.turn);
+            self.en_passant = None;
+        }
+    }
+    pub fn swap_turn(&mut self) {
+        self.board.turn = other(self.board.turn);
+        self.en_passant = None;
+    }
+}
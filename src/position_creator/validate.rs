@@ -0,0 +1,241 @@
+use chess_lib::{Board, CastlingRights, Colour, Piece, Tile};
+
+use crate::notation::{parse_tile, tile_name};
+
+/// Something that makes the edited position unplayable, with the squares to blame.
+#[derive(Debug, Clone)]
+pub struct Problem
+{
+    pub message: String,
+    pub tiles: Vec<Tile>,
+}
+impl Problem
+{
+    fn new(message: String, tiles: Vec<Tile>) -> Self {
+        Self { message, tiles }
+    }
+}
+
+/// Each castling right with its colour (`true` for White) and a label.
+pub const CASTLING_RIGHTS: [(CastlingRights, bool, &str); 4] = [
+    (CastlingRights::WHITE_KINGSIDE, true, "White short"),
+    (CastlingRights::WHITE_QUEENSIDE, true, "White long"),
+    (CastlingRights::BLACK_KINGSIDE, false, "Black short"),
+    (CastlingRights::BLACK_QUEENSIDE, false, "Black long"),
+];
+
+fn colour_name(colour: Colour) -> &'static str {
+    if colour.white() { "White" } else { "Black" }
+}
+
+pub fn pieces_of(board: &Board, colour: Colour, piece: Piece) -> Vec<Tile> {
+    let player = if colour.white() { &board.white } else { &board.black };
+    player
+        .bb
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| Piece::from_index(*i) == piece)
+        .flat_map(|(_, bb)| bb.iter())
+        .collect()
+}
+
+fn tile(name: &str) -> Tile {
+    parse_tile(name).expect("valid square name")
+}
+
+/// Checks `board` and the FEN fields edited alongside it for everything that
+/// would stop it being a reachable, playable position.
+pub fn validate(board: &Board, en_passant: Option<Tile>, halfmove_clock: u32) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    // Kings
+    let mut kings_ok = true;
+    for colour in [Colour::White, Colour::Black] {
+        let kings = pieces_of(board, colour, Piece::King);
+        match kings.len() {
+            0 => {
+                problems.push(Problem::new(format!("{} has no king", colour_name(colour)), Vec::new()));
+                kings_ok = false;
+            }
+            1 => (),
+            n => {
+                problems.push(Problem::new(format!("{} has {} kings", colour_name(colour), n), kings));
+                kings_ok = false;
+            }
+        }
+    }
+
+    // Pawns on the back ranks
+    for colour in [Colour::White, Colour::Black] {
+        let bad: Vec<Tile> = pieces_of(board, colour, Piece::Pawn)
+            .into_iter()
+            .filter(|t| t.get_coords().1 == 0 || t.get_coords().1 == 7)
+            .collect();
+        if !bad.is_empty() {
+            let names: Vec<String> = bad.iter().map(|t| tile_name(*t)).collect();
+            problems.push(Problem::new(
+                format!("{} pawn on the first or last rank ({})", colour_name(colour), names.join(", ")),
+                bad,
+            ));
+        }
+    }
+
+    // Checks only make sense with exactly one king each
+    if kings_ok {
+        let w_king = board.white.king_tile();
+        let b_king = board.black.king_tile();
+        let (wx, wy) = w_king.get_coords();
+        let (bx, by) = b_king.get_coords();
+        if wx.abs_diff(bx) <= 1 && wy.abs_diff(by) <= 1 {
+            problems.push(Problem::new("The kings are touching".to_string(), vec![w_king, b_king]));
+        }
+
+        let waiting = if board.turn.white() { Colour::Black } else { Colour::White };
+        if board.is_in_check(waiting) {
+            let king = if waiting.white() { w_king } else { b_king };
+            problems.push(Problem::new(
+                format!("{} is in check but it is {} to move", colour_name(waiting), colour_name(board.turn)),
+                vec![king],
+            ));
+        }
+    }
+
+    // Castling rights need the king and rook on their home squares
+    let castling = [
+        (CastlingRights::WHITE_KINGSIDE, Colour::White, "e1", "h1", "White kingside"),
+        (CastlingRights::WHITE_QUEENSIDE, Colour::White, "e1", "a1", "White queenside"),
+        (CastlingRights::BLACK_KINGSIDE, Colour::Black, "e8", "h8", "Black kingside"),
+        (CastlingRights::BLACK_QUEENSIDE, Colour::Black, "e8", "a8", "Black queenside"),
+    ];
+    for (right, colour, king_sq, rook_sq, name) in castling {
+        if !board.castling.contains(right) {
+            continue;
+        }
+        let king_home = board.get_piece_at_tile(tile(king_sq)) == Some((Piece::King, colour));
+        let rook_home = board.get_piece_at_tile(tile(rook_sq)) == Some((Piece::Rook, colour));
+        if !king_home || !rook_home {
+            problems.push(Problem::new(
+                format!("{} castling needs the king on {} and a rook on {}", name, king_sq, rook_sq),
+                vec![tile(king_sq), tile(rook_sq)],
+            ));
+        }
+    }
+
+    // En passant square
+    if let Some(ep) = en_passant {
+        if let Some(reason) = en_passant_problem(board, ep) {
+            problems.push(Problem::new(format!("En passant square {} is impossible: {}", tile_name(ep), reason), vec![ep]));
+        } else if halfmove_clock != 0 {
+            problems.push(Problem::new(
+                "A pawn just moved for en passant, so the halfmove clock must be 0".to_string(),
+                vec![ep],
+            ));
+        }
+    }
+
+    // Material
+    for colour in [Colour::White, Colour::Black] {
+        let count = |p| pieces_of(board, colour, p).len();
+        let total: usize = Piece::ALL_PIECES.iter().map(|p| count(*p)).sum();
+        if total > 16 {
+            problems.push(Problem::new(format!("{} has {} pieces, more than 16", colour_name(colour), total), Vec::new()));
+        }
+
+        let pawns = count(Piece::Pawn);
+        if pawns > 8 {
+            problems.push(Problem::new(
+                format!("{} has {} pawns", colour_name(colour), pawns),
+                pieces_of(board, colour, Piece::Pawn),
+            ));
+        }
+
+        // Every piece beyond the starting set must have come from a promoted pawn
+        let promoted = count(Piece::Queen).saturating_sub(1)
+            + count(Piece::Rook).saturating_sub(2)
+            + count(Piece::Bishop).saturating_sub(2)
+            + count(Piece::Knight).saturating_sub(2);
+        if promoted > 8usize.saturating_sub(pawns) {
+            problems.push(Problem::new(
+                format!(
+                    "{} needs {} promotions but only has {} missing pawns",
+                    colour_name(colour),
+                    promoted,
+                    8usize.saturating_sub(pawns)
+                ),
+                Vec::new(),
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Files of `colour`'s rooks on its back rank on the given side of its king.
+pub fn castling_rook_files(board: &Board, colour: Colour, kingside: bool) -> Vec<u8> {
+    let back = if colour.white() { 0 } else { 7 };
+    let kings = pieces_of(board, colour, Piece::King);
+    let king_file = match kings.as_slice() {
+        [k] if k.get_coords().1 == back => k.get_coords().0,
+        _ => return Vec::new(),
+    };
+    pieces_of(board, colour, Piece::Rook)
+        .into_iter()
+        .map(|t| t.get_coords())
+        .filter(|&(x, y)| y == back && if kingside { x > king_file } else { x < king_file })
+        .map(|(x, _)| x)
+        .collect()
+}
+
+/// Chess960 castling rights need the king on its back rank and the named rook on the right side of it.
+pub fn validate_castling_files(board: &Board, files: &[Option<u8>; 4]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (file, (right, white, label)) in files.iter().zip(CASTLING_RIGHTS) {
+        let Some(file) = *file else {
+            continue;
+        };
+        let colour = if white { Colour::White } else { Colour::Black };
+        let kingside = matches!(right, CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_KINGSIDE);
+        if !castling_rook_files(board, colour, kingside).contains(&file) {
+            let back = if white { 0 } else { 7 };
+            let tiles = Tile::new_xy(file, back).into_iter().collect();
+            problems.push(Problem::new(
+                format!("{} castling needs the king on the back rank and a rook on the {}-file beside it", label, (b'a' + file) as char),
+                tiles,
+            ));
+        }
+    }
+    problems
+}
+
+/// Squares that could be the en passant target for the side to move.
+pub fn en_passant_squares(board: &Board) -> Vec<Tile> {
+    let rank = if board.turn.white() { 5 } else { 2 };
+    (0..8)
+        .filter_map(|x| Tile::new_xy(x, rank))
+        .filter(|t| en_passant_problem(board, *t).is_none())
+        .collect()
+}
+
+/// Why `ep` can't be the en passant target square, if it can't.
+pub fn en_passant_problem(board: &Board, ep: Tile) -> Option<&'static str> {
+    let (x, y) = ep.get_coords();
+    // The pawn that just moved double stepped past `ep` to the rank beyond it
+    let (ep_rank, pawn_rank, start_rank, mover) = if board.turn.white() {
+        (5, 4, 6, Colour::Black)
+    } else {
+        (2, 3, 1, Colour::White)
+    };
+
+    if y != ep_rank {
+        return Some("wrong rank for the side to move");
+    }
+    let pawn = Tile::new_xy(x, pawn_rank)?;
+    let start = Tile::new_xy(x, start_rank)?;
+    if board.get_piece_at_tile(pawn) != Some((Piece::Pawn, mover)) {
+        return Some("no pawn has just double stepped past it");
+    }
+    if board.get_piece_at_tile(ep).is_some() || board.get_piece_at_tile(start).is_some() {
+        return Some("the pawn's path is not empty");
+    }
+    None
+}
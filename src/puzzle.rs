@@ -0,0 +1,325 @@
+use chess_lib::{Board, Colour, GameState};
+use egui::{Context, TextureHandle};
+use instant::Instant;
+
+use crate::{notation, play::PlayTab, storage};
+
+pub mod generate;
+pub mod parse;
+mod progress;
+mod render;
+mod themes;
+use generate::Generator;
+use parse::{parse_puzzles, Puzzle};
+use progress::Progress;
+
+const PUZZLE_FILE: &str = "puzzles.csv";
+const BUILTIN_PUZZLES: &str = include_str!("../assets/puzzles.csv");
+/// Pause before the opponent's reply so the solver can see their own move land.
+const REPLY_DELAY: f32 = 0.6;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PuzzleStatus
+{
+    Solving,
+    Solved,
+    /// Playing the rest of the solution after the solver gave up.
+    ShowingSolution,
+    Failed,
+}
+
+pub struct PuzzleTab
+{
+    pub play: PlayTab,
+    pub puzzles: Vec<Puzzle>,
+    pub current: Option<usize>,
+    /// Index of the next solution move to be played.
+    step: usize,
+    pub status: PuzzleStatus,
+    /// Set once the current attempt has counted as a failure.
+    failed: bool,
+    is_retry: bool,
+    pub feedback: Option<String>,
+    reply_timer: f32,
+    last_frame_time: Instant,
+
+    pub progress: Progress,
+    pub paste_text: String,
+    pub load_message: Option<String>,
+    pub generator: Generator,
+}
+
+impl PuzzleTab
+{
+    pub fn new(atlas: TextureHandle) -> Self {
+        let mut play = PlayTab::new(None, atlas);
+        play.embedded = true;
+
+        let mut tab = Self {
+            play,
+            puzzles: Vec::new(),
+            current: None,
+            step: 0,
+            status: PuzzleStatus::Solving,
+            failed: false,
+            is_retry: false,
+            feedback: None,
+            reply_timer: 0.0,
+            last_frame_time: Instant::now(),
+
+            progress: Progress::load(),
+            paste_text: String::new(),
+            load_message: None,
+            generator: Generator::default(),
+        };
+        let text = storage::load(PUZZLE_FILE).unwrap_or(BUILTIN_PUZZLES.to_string());
+        tab.puzzles = parse_puzzles(&text).0;
+        tab.next_puzzle();
+        tab
+    }
+    /// Replaces the puzzle set with `text` and remembers it for next time.
+    pub fn load_text(&mut self, text: &str) {
+        let (puzzles, skipped) = parse_puzzles(text);
+        if puzzles.is_empty() {
+            self.load_message = Some("No usable puzzles found".to_string());
+            return;
+        }
+        self.load_message = Some(if skipped > 0 {
+            format!("Loaded {} puzzles, skipped {} invalid lines", puzzles.len(), skipped)
+        } else {
+            format!("Loaded {} puzzles", puzzles.len())
+        });
+        let lines: Vec<&str> = puzzles.iter().map(|p| p.line.as_str()).collect();
+        storage::save(PUZZLE_FILE, &lines.join("\n"));
+
+        self.puzzles = puzzles;
+        self.progress.attempted.clear();
+        self.progress.retry.clear();
+        self.progress.save();
+        self.next_puzzle();
+    }
+    /// Adds puzzles to the current set, skipping ids it already has.
+    pub fn add_puzzles(&mut self, puzzles: Vec<Puzzle>) {
+        let before = self.puzzles.len();
+        for p in puzzles {
+            if !self.puzzles.iter().any(|q| q.id == p.id) {
+                self.puzzles.push(p);
+            }
+        }
+        let lines: Vec<&str> = self.puzzles.iter().map(|p| p.line.as_str()).collect();
+        storage::save(PUZZLE_FILE, &lines.join("\n"));
+        self.load_message = Some(format!("Added {} puzzles", self.puzzles.len() - before));
+        if self.current.is_none() {
+            self.next_puzzle();
+        }
+    }
+    pub fn puzzle(&self) -> Option<&Puzzle> {
+        self.current.and_then(|i| self.puzzles.get(i))
+    }
+    /// The side the solver plays.
+    pub fn solver_colour(&self) -> Option<Colour> {
+        let puzzle = self.puzzle()?;
+        let board = Board::new_from_fen(&puzzle.fen).ok()?;
+        let turn = board.turn;
+        Some(match (puzzle.setup_move, turn) {
+            (false, c) => c,
+            (true, Colour::White) => Colour::Black,
+            (true, Colour::Black) => Colour::White,
+        })
+    }
+
+    /// Serves a due retry first, otherwise the unattempted puzzle closest to the solver's rating.
+    pub fn next_puzzle(&mut self) {
+        if self.puzzles.is_empty() {
+            self.current = None;
+            return;
+        }
+        while let Some(id) = self.progress.take_due_retry() {
+            if let Some(idx) = self.puzzles.iter().position(|p| p.id == id) {
+                self.start_puzzle(idx, true);
+                return;
+            }
+        }
+        if self.puzzles.iter().all(|p| self.progress.attempted.contains(&p.id)) {
+            self.progress.attempted.clear();
+        }
+        let rating = self.progress.rating;
+        let idx = self
+            .puzzles
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| !self.progress.attempted.contains(&p.id))
+            .min_by(|(_, a), (_, b)| (a.rating - rating).abs().total_cmp(&(b.rating - rating).abs()))
+            .map(|(i, _)| i);
+        if let Some(idx) = idx {
+            self.start_puzzle(idx, false);
+        }
+    }
+    pub fn start_puzzle(&mut self, idx: usize, retry: bool) {
+        let Some(puzzle) = self.puzzles.get(idx) else {
+            return;
+        };
+        let board = match Board::new_from_fen(&puzzle.fen) {
+            Ok(b) => b,
+            Err(e) => {
+                self.feedback = Some(e);
+                return;
+            }
+        };
+        self.progress.attempted.insert(puzzle.id.clone());
+        self.progress.played += 1;
+        self.progress.save();
+
+        self.current = Some(idx);
+        self.play.set_position(board);
+        self.step = 0;
+        self.status = PuzzleStatus::Solving;
+        self.failed = false;
+        self.is_retry = retry;
+        self.feedback = None;
+        self.reply_timer = 0.0;
+        self.play.flipped = self.solver_colour() == Some(Colour::Black);
+        self.play.input_locked = !self.solver_to_move();
+    }
+    /// Restarts the current puzzle without counting it as a new attempt.
+    pub fn restart(&mut self) {
+        let Some(puzzle) = self.puzzle() else {
+            return;
+        };
+        if let Ok(board) = Board::new_from_fen(&puzzle.fen) {
+            self.play.set_position(board);
+            self.step = 0;
+            self.status = PuzzleStatus::Solving;
+            self.feedback = None;
+            self.reply_timer = 0.0;
+            self.play.input_locked = !self.solver_to_move();
+        }
+    }
+    pub fn give_up(&mut self) {
+        self.fail();
+        self.status = PuzzleStatus::ShowingSolution;
+        self.play.input_locked = true;
+        self.reply_timer = 0.0;
+    }
+
+    fn solver_to_move(&self) -> bool {
+        Some(self.play.board.turn) == self.solver_colour()
+    }
+    fn fail(&mut self) {
+        if self.failed {
+            return;
+        }
+        self.failed = true;
+        if let Some(p) = self.puzzle() {
+            let (id, rating) = (p.id.clone(), p.rating);
+            self.progress.record(&id, rating, false, self.is_retry);
+        }
+    }
+    fn solve(&mut self) {
+        self.status = PuzzleStatus::Solved;
+        self.play.input_locked = true;
+        if !self.failed {
+            if let Some(p) = self.puzzle() {
+                let (id, rating) = (p.id.clone(), p.rating);
+                self.progress.record(&id, rating, true, self.is_retry);
+            }
+            self.feedback = Some("Solved!".to_string());
+        } else {
+            self.feedback = Some("Solved, but it will come back for another try".to_string());
+        }
+    }
+    /// Plays the next solution move on the board.
+    fn play_solution_move(&mut self) {
+        let Some(text) = self.puzzle().and_then(|p| p.moves.get(self.step)).cloned() else {
+            return;
+        };
+        if let Some(m) = notation::parse_move(&self.play.board, &text) {
+            self.play.play_move(m);
+            self.play.view_board = self.play.board.clone();
+        }
+        self.step += 1;
+    }
+    /// Checks a move the solver just made against the solution.
+    fn check_solver_move(&mut self) {
+        let Some(puzzle) = self.puzzle() else {
+            return;
+        };
+        let total = puzzle.moves.len();
+        let Some(last) = self.play.board.history.last().map(|h| h.last_move) else {
+            return;
+        };
+        let mut before = self.play.board.clone();
+        before.undo_move();
+        let expected = notation::parse_move(&before, &puzzle.moves[self.step]);
+
+        if expected == Some(last) {
+            self.step += 1;
+            self.feedback = None;
+            if self.step >= total {
+                self.solve();
+            } else {
+                self.play.input_locked = true;
+                self.reply_timer = 0.0;
+            }
+        } else if matches!(self.play.board.get_state(), GameState::Checkmate(_)) {
+            // Any mate solves the puzzle, even if it isn't the listed one
+            self.solve();
+        } else {
+            self.fail();
+            self.play.undo();
+            self.play.redo_stack.clear();
+            self.feedback = Some("That's not it, try again".to_string());
+        }
+    }
+    fn update(&mut self, ctx: &Context, dt: f32) {
+        let total = self.puzzle().map(|p| p.moves.len()).unwrap_or(0);
+        match self.status {
+            PuzzleStatus::Solving => {
+                if self.play.board.history.len() > self.step {
+                    self.check_solver_move();
+                } else if !self.solver_to_move() && self.step < total {
+                    self.reply_timer += dt;
+                    if self.reply_timer >= REPLY_DELAY {
+                        self.play_solution_move();
+                        self.play.input_locked = false;
+                    }
+                    ctx.request_repaint();
+                }
+            }
+            PuzzleStatus::ShowingSolution => {
+                if self.step >= total {
+                    self.status = PuzzleStatus::Failed;
+                    self.feedback = Some("That was the solution".to_string());
+                } else {
+                    self.reply_timer += dt;
+                    if self.reply_timer >= REPLY_DELAY {
+                        self.reply_timer = 0.0;
+                        self.play_solution_move();
+                    }
+                    ctx.request_repaint();
+                }
+            }
+            PuzzleStatus::Solved | PuzzleStatus::Failed => (),
+        }
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame_time).as_secs_f32();
+        self.last_frame_time = now;
+
+        egui::SidePanel::right("puzzle_panel")
+            .resizable(false)
+            .min_width(260.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("scroll_puzzle")
+                    .show(ui, |ui| {
+                        self.render_panel(ui);
+                    });
+            });
+        self.play.render_board(ctx);
+        // The solver's move lands during render_board
+        self.update(ctx, dt);
+        self.generator.step(ctx);
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::VecDeque;
+
+use chess_lib::{Board, Colour, GameState, Move};
+use egui::{Context, RichText, Slider, Ui};
+#[cfg(not(target_arch = "wasm32"))]
+use rfd::FileDialog;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+use crate::{
+    analysis::{material_balance, LineSearch},
+    background::{Background, Work},
+    notation, pgn,
+    puzzle::{parse::{parse_puzzles, Puzzle}, themes::material_themes, PuzzleTab},
+    statistics::record::load_games,
+};
+
+/// Longest mating line looked for, in plies (mate in 3).
+const MATE_PLIES: usize = 5;
+/// Plies played out before comparing material.
+const MATERIAL_PLIES: usize = 4;
+/// How much more the best line has to win than the game move, in centipawns.
+const MIN_GAIN: i32 = 300;
+
+/// A game to mine for puzzles.
+pub struct GameSource
+{
+    pub name: String,
+    pub start_fen: Option<String>,
+    pub moves: Vec<String>,
+    /// Only look at this side's moves.
+    pub only: Option<Colour>,
+}
+
+/// A position from a game, with the move before it (the puzzle's setup move)
+/// and the move that was actually played.
+struct Job
+{
+    name: String,
+    before_setup: Board,
+    setup: Move,
+    position: Board,
+    played: Move,
+}
+
+pub struct Generator
+{
+    pub depth: u8,
+    pub only_my_moves: bool,
+    /// PGN player name whose moves are checked, empty for both sides.
+    pub player: String,
+    pub pgn_text: String,
+    analysis: Option<Background<Analysis>>,
+    total: usize,
+    checked: usize,
+    pub found: Vec<Puzzle>,
+    pub message: Option<String>,
+}
+impl Default for Generator
+{
+    fn default() -> Self {
+        Self {
+            depth: 3,
+            only_my_moves: true,
+            player: String::new(),
+            pgn_text: String::new(),
+            analysis: None,
+            total: 0,
+            checked: 0,
+            found: Vec::new(),
+            message: None,
+        }
+    }
+}
+impl Generator
+{
+    pub fn running(&self) -> bool {
+        self.analysis.is_some()
+    }
+    /// Replays the games and queues every position worth checking.
+    pub fn queue_games(&mut self, games: Vec<GameSource>) {
+        let mut jobs = VecDeque::new();
+        self.found.clear();
+        for game in games {
+            let start = game.start_fen.as_deref().map(Board::new_from_fen).unwrap_or(Ok(Board::new()));
+            let Ok(mut board) = start else {
+                continue;
+            };
+            let mut previous: Option<(Board, Move)> = None;
+            for (ply, san) in game.moves.iter().enumerate() {
+                let Some(m) = notation::parse_move(&board, san) else {
+                    break;
+                };
+                let wanted = game.only.is_none_or(|c| c == board.turn);
+                if let Some((before_setup, setup)) = previous.take().filter(|_| wanted) {
+                    jobs.push_back(Job {
+                        name: format!("{}-{}", game.name, ply + 1),
+                        before_setup,
+                        setup,
+                        position: board.clone(),
+                        played: m,
+                    });
+                }
+                previous = Some((board.clone(), m));
+                board.make_move_unchecked(m);
+            }
+        }
+        self.total = jobs.len();
+        self.checked = 0;
+        self.message = Some(format!("Checking {} positions", self.total));
+        self.analysis = (!jobs.is_empty()).then(|| Background::start(Analysis { jobs, depth: self.depth, current: None }));
+    }
+    pub fn stop(&mut self) {
+        self.analysis = None;
+        self.message = Some(format!("Stopped, found {} puzzles", self.found.len()));
+    }
+    /// Takes in the positions the background analysis has got through.
+    pub fn step(&mut self, ctx: &Context) {
+        let Some(analysis) = &mut self.analysis else {
+            return;
+        };
+        for puzzle in analysis.poll(ctx) {
+            self.checked += 1;
+            self.found.extend(puzzle);
+        }
+        if analysis.finished() {
+            self.analysis = None;
+            self.message = Some(format!("Done, found {} puzzles", self.found.len()));
+        }
+    }
+}
+
+fn mates(board: &Board, winner: Colour) -> bool {
+    matches!(board.get_state(), GameState::Checkmate(loser) if loser != winner)
+}
+
+/// Material from `colour`'s side after the first `plies` moves of `line`.
+fn balance_after(position: &Board, line: &[Move], plies: usize, colour: Colour) -> i32 {
+    let mut board = position.clone();
+    for &m in line.iter().take(plies) {
+        board.make_move_unchecked(m);
+    }
+    let balance = material_balance(&board);
+    if colour.white() { balance } else { -balance }
+}
+
+/// Turns a position into a puzzle if the engine finds a mate or a clear material
+/// win that the game move missed. Runs a search per step.
+struct JobSearch
+{
+    job: Job,
+    depth: u8,
+    best: LineSearch,
+    /// The game move's line, once the best line has shown it's worth comparing.
+    played: Option<LineSearch>,
+}
+impl JobSearch
+{
+    fn new(job: Job, depth: u8) -> Self {
+        let best = LineSearch::new(&job.position, None, depth, MATE_PLIES);
+        Self { job, depth, best, played: None }
+    }
+    /// The puzzle, or `Some(None)` for no puzzle, once the searches are done.
+    fn step(&mut self) -> Option<Option<Puzzle>> {
+        let colour = self.job.position.turn;
+        if !self.best.finished() {
+            self.best.step();
+            return match self.best.line().first() {
+                Some(&best) if best != self.job.played => None,
+                _ => Some(None),
+            };
+        }
+        let mate = mates(self.best.board(), colour);
+        let played = match &mut self.played {
+            Some(played) => played,
+            None => {
+                // Only missed wins, not blunders that merely lost material
+                if !mate && self.best_gain() - balance_after(&self.job.position, &[], 0, colour) < MIN_GAIN {
+                    return Some(None);
+                }
+                let plies = if mate { MATE_PLIES } else { MATERIAL_PLIES };
+                self.played.insert(LineSearch::new(&self.job.position, Some(self.job.played), self.depth, plies))
+            }
+        };
+        if !played.finished() {
+            played.step();
+            return None;
+        }
+        Some(self.puzzle())
+    }
+    fn best_gain(&self) -> i32 {
+        balance_after(&self.job.position, self.best.line(), MATERIAL_PLIES, self.job.position.turn)
+    }
+    /// Compares the finished lines.
+    fn puzzle(&self) -> Option<Puzzle> {
+        let job = &self.job;
+        let colour = job.position.turn;
+        let best_line = self.best.line();
+        let played = self.played.as_ref()?;
+
+        let (solution, themes, rating) = if mates(self.best.board(), colour) {
+            if mates(played.board(), colour) && played.line().len() <= best_line.len() {
+                return None;
+            }
+            let n = best_line.len().div_ceil(2);
+            (best_line.to_vec(), vec![format!("mateIn{}", n)], 800 + 250 * (n as i32 - 1))
+        } else {
+            let best_gain = self.best_gain();
+            let played_gain = balance_after(&job.position, played.line(), MATERIAL_PLIES, colour);
+            if best_gain - played_gain < MIN_GAIN {
+                return None;
+            }
+            // One move is enough when the gain stands after the reply
+            let quick = balance_after(&job.position, best_line, 2, colour) - played_gain >= MIN_GAIN;
+            let plies = if quick || best_line.len() < 3 { 1 } else { 3 };
+            let themes = material_themes(&job.position, best_line[0], plies);
+            (best_line[..plies].to_vec(), themes, if plies == 1 { 1100 } else { 1400 })
+        };
+
+        let mut moves = vec![notation::move_uci(&job.before_setup, job.setup)?];
+        let mut board = job.position.clone();
+        for &m in &solution {
+            moves.push(notation::move_uci(&board, m)?);
+            board.make_move_unchecked(m);
+        }
+        let line = format!(
+            "{},{},{},{},,,,{},,",
+            job.name,
+            job.before_setup.to_fen(),
+            moves.join(" "),
+            rating,
+            themes.join(" ")
+        );
+        parse_puzzles(&line).0.pop()
+    }
+}
+
+/// The queued positions, checked in the background a search at a time.
+struct Analysis
+{
+    jobs: VecDeque<Job>,
+    depth: u8,
+    current: Option<JobSearch>,
+}
+impl Work for Analysis
+{
+    /// A checked position and the puzzle found in it, if any.
+    type Output = Option<Puzzle>;
+
+    fn step(&mut self) -> Option<Self::Output> {
+        let current = match &mut self.current {
+            Some(current) => current,
+            None => self.current.insert(JobSearch::new(self.jobs.pop_front()?, self.depth)),
+        };
+        let puzzle = current.step()?;
+        self.current = None;
+        Some(puzzle)
+    }
+    fn finished(&self) -> bool {
+        self.current.is_none() && self.jobs.is_empty()
+    }
+}
+
+/// Games from PGN text, keeping only `player`'s moves when a name is given.
+fn pgn_sources(text: &str, player: &str) -> Vec<GameSource> {
+    let player = player.trim().to_lowercase();
+    pgn::parse_games(text)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, game)| {
+            let plays = |tag: &str| game.tag(tag).is_some_and(|n| n.to_lowercase().contains(&player));
+            let only = if player.is_empty() {
+                None
+            } else if plays("White") {
+                Some(Colour::White)
+            } else if plays("Black") {
+                Some(Colour::Black)
+            } else {
+                return None;
+            };
+            Some(GameSource {
+                name: format!("pgn{}", i + 1),
+                start_fen: game.tag("FEN").map(|f| f.to_string()),
+                moves: game.moves,
+                only,
+            })
+        })
+        .collect()
+}
+
+impl PuzzleTab
+{
+    pub fn render_generator_panel(&mut self, ui: &mut Ui) {
+        let generator = &mut self.generator;
+        ui.label(RichText::new("Find positions in your games where a mate or a piece was missed").weak());
+        ui.horizontal(|ui| {
+            ui.label("Engine depth");
+            ui.add(Slider::new(&mut generator.depth, 1..=6));
+        });
+
+        if generator.running() {
+            let done = generator.checked;
+            ui.add(egui::ProgressBar::new(done as f32 / generator.total.max(1) as f32).text(format!("{} / {}", done, generator.total)));
+            if ui.button("Stop").clicked() {
+                generator.stop();
+            }
+        } else {
+            ui.checkbox(&mut generator.only_my_moves, "Only my moves");
+            if ui.button("From my game history").clicked() {
+                let only_mine = generator.only_my_moves;
+                let sources = load_games()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, g)| GameSource {
+                        name: format!("game{}", i + 1),
+                        start_fen: g.start_fen.clone(),
+                        only: g.human_colour.filter(|_| only_mine),
+                        moves: g.moves,
+                    })
+                    .collect();
+                generator.queue_games(sources);
+            }
+
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                ui.label("Player");
+                ui.text_edit_singleline(&mut generator.player)
+                    .on_hover_text("Only check this player's moves in PGN games, leave empty for both sides");
+            });
+            ui.add(
+                egui::TextEdit::multiline(&mut generator.pgn_text)
+                    .hint_text("Paste PGN here")
+                    .desired_rows(4)
+                    .code_editor(),
+            );
+            ui.horizontal(|ui| {
+                if ui.button("From PGN").clicked() {
+                    let sources = pgn_sources(&generator.pgn_text, &generator.player);
+                    generator.queue_games(sources);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Open PGN file").clicked() {
+                    let text = FileDialog::new()
+                        .add_filter("PGN", &["pgn"])
+                        .pick_file()
+                        .and_then(|path| fs::read_to_string(path).ok());
+                    if let Some(text) = text {
+                        let sources = pgn_sources(&text, &generator.player);
+                        generator.queue_games(sources);
+                    }
+                }
+            });
+        }
+        if let Some(message) = &generator.message {
+            ui.label(message);
+        }
+
+        if generator.found.is_empty() {
+            return;
+        }
+        for p in &generator.found {
+            ui.label(RichText::new(format!("{}: {}", p.id, p.themes.join(", "))).monospace());
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add to puzzle set").clicked() {
+                let found = std::mem::take(&mut self.generator.found);
+                self.add_puzzles(found);
+            }
+            if ui.button("Copy as CSV").clicked() {
+                let lines: Vec<&str> = self.generator.found.iter().map(|p| p.line.as_str()).collect();
+                ui.ctx().copy_text(lines.join("\n"));
+            }
+        });
+    }
+}
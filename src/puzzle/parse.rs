@@ -0,0 +1,132 @@
+use chess_lib::Board;
+
+use crate::notation;
+
+/// Large databases are cut off here so loading and saving stay quick.
+pub const MAX_PUZZLES: usize = 5000;
+const DEFAULT_RATING: f32 = 1500.0;
+
+#[derive(Debug, Clone)]
+pub struct Puzzle
+{
+    pub id: String,
+    pub fen: String,
+    /// Solution moves in UCI or SAN. When `setup_move` is set the first one is
+    /// the opponent's move leading into the puzzle, as in the Lichess database.
+    pub moves: Vec<String>,
+    pub setup_move: bool,
+    pub rating: f32,
+    pub themes: Vec<String>,
+    /// The line the puzzle was read from, kept so the set can be saved again.
+    pub line: String,
+}
+
+/// Reads puzzles from CSV or EPD text, one per line. Understood layouts:
+///
+/// - Lichess CSV: `PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,...`
+///   with UCI moves, opponent first
+/// - plain CSV: `FEN,moves[,rating]`, the solver moves first
+/// - EPD: `<position> bm <move>; id "<name>";`, the solver moves first
+///
+/// Returns the puzzles and the number of lines that couldn't be used.
+pub fn parse_puzzles(text: &str) -> (Vec<Puzzle>, usize) {
+    let mut puzzles = Vec::new();
+    let mut skipped = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("PuzzleId") {
+            continue;
+        }
+        if puzzles.len() >= MAX_PUZZLES {
+            break;
+        }
+        let puzzle = if line.contains(';') { parse_epd(line) } else { parse_csv(line, i + 1) };
+        match puzzle.filter(is_playable) {
+            Some(p) => puzzles.push(p),
+            None => skipped += 1,
+        }
+    }
+    (puzzles, skipped)
+}
+
+fn parse_csv(line: &str, number: usize) -> Option<Puzzle> {
+    let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+    let rating = |field: Option<&&str>| field.and_then(|r| r.parse().ok()).unwrap_or(DEFAULT_RATING);
+    let words = |field: Option<&&str>| field.map(|t| t.split_whitespace().map(|w| w.to_string()).collect()).unwrap_or_default();
+
+    if fields.len() >= 3 && Board::new_from_fen(fields[1]).is_ok() {
+        return Some(Puzzle {
+            id: clean_id(fields[0]),
+            fen: fields[1].to_string(),
+            moves: fields[2].split_whitespace().map(|m| m.to_string()).collect(),
+            setup_move: true,
+            rating: rating(fields.get(3)),
+            themes: words(fields.get(7)),
+            line: line.to_string(),
+        });
+    }
+    if fields.len() >= 2 && Board::new_from_fen(fields[0]).is_ok() {
+        return Some(Puzzle {
+            id: format!("line{}", number),
+            fen: fields[0].to_string(),
+            moves: fields[1].split_whitespace().map(|m| m.to_string()).collect(),
+            setup_move: false,
+            rating: rating(fields.get(2)),
+            themes: Vec::new(),
+            line: line.to_string(),
+        });
+    }
+    None
+}
+
+fn parse_epd(line: &str) -> Option<Puzzle> {
+    let fields: Vec<&str> = line.splitn(5, ' ').collect();
+    if fields.len() < 5 {
+        return None;
+    }
+    // EPD leaves out the move counters
+    let fen = format!("{} 0 1", fields[..4].join(" "));
+
+    let mut best = None;
+    let mut id = None;
+    for op in fields[4].split(';') {
+        let op = op.trim();
+        if let Some(moves) = op.strip_prefix("bm ") {
+            best = moves.split_whitespace().next().map(|m| m.to_string());
+        } else if let Some(name) = op.strip_prefix("id ") {
+            id = Some(clean_id(name.trim_matches('"')));
+        }
+    }
+    Some(Puzzle {
+        id: id.unwrap_or_else(|| clean_id(&fen)),
+        fen,
+        moves: vec![best?],
+        setup_move: false,
+        rating: DEFAULT_RATING,
+        themes: Vec::new(),
+        line: line.to_string(),
+    })
+}
+
+/// Ids are stored space-separated in the progress file.
+fn clean_id(id: &str) -> String {
+    id.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Every solution move has to be legal, and the solver needs at least one move.
+fn is_playable(puzzle: &Puzzle) -> bool {
+    let Ok(mut board) = Board::new_from_fen(&puzzle.fen) else {
+        return false;
+    };
+    let solver_moves = if puzzle.setup_move { puzzle.moves.len().saturating_sub(1) } else { puzzle.moves.len() };
+    if solver_moves == 0 {
+        return false;
+    }
+    for text in &puzzle.moves {
+        match notation::parse_move(&board, text) {
+            Some(m) => board.make_move_unchecked(m),
+            None => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+use crate::storage;
+
+const PROGRESS_FILE: &str = "puzzle_progress.txt";
+/// A failed puzzle comes back after this many other puzzles.
+const RETRY_AFTER: usize = 5;
+const RATING_K: f32 = 32.0;
+
+/// Rating, streaks and the retry queue, kept between sessions.
+#[derive(Debug, Clone)]
+pub struct Progress
+{
+    pub rating: f32,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub solved: u32,
+    pub failed: u32,
+    /// Puzzles started, used to schedule retries.
+    pub played: usize,
+    pub attempted: HashSet<String>,
+    /// Failed puzzle ids with the `played` count at which they're due again.
+    pub retry: Vec<(String, usize)>,
+}
+impl Default for Progress
+{
+    fn default() -> Self {
+        Self {
+            rating: 1500.0,
+            streak: 0,
+            best_streak: 0,
+            solved: 0,
+            failed: 0,
+            played: 0,
+            attempted: HashSet::new(),
+            retry: Vec::new(),
+        }
+    }
+}
+impl Progress
+{
+    pub fn load() -> Self {
+        let mut progress = Self::default();
+        if let Some(text) = storage::load(PROGRESS_FILE) {
+            progress.apply_text(&text);
+        }
+        progress
+    }
+    pub fn save(&self) {
+        storage::save(PROGRESS_FILE, &self.to_text());
+    }
+
+    /// Records the first result of an attempt. Retries move the queue but not the rating.
+    pub fn record(&mut self, id: &str, puzzle_rating: f32, solved: bool, retry: bool) {
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.failed += 1;
+            self.streak = 0;
+            self.retry.push((id.to_string(), self.played + RETRY_AFTER));
+        }
+        if !retry {
+            let expected = 1.0 / (1.0 + 10f32.powf((puzzle_rating - self.rating) / 400.0));
+            let score = if solved { 1.0 } else { 0.0 };
+            self.rating += RATING_K * (score - expected);
+        }
+        self.save();
+    }
+    /// Takes the oldest retry that has come due.
+    pub fn take_due_retry(&mut self) -> Option<String> {
+        let idx = self.retry.iter().position(|(_, due)| *due <= self.played)?;
+        Some(self.retry.remove(idx).0)
+    }
+
+    fn to_text(&self) -> String {
+        let retry: Vec<String> = self.retry.iter().map(|(id, due)| format!("{}:{}", id, due)).collect();
+        let attempted: Vec<&str> = self.attempted.iter().map(|s| s.as_str()).collect();
+        format!(
+            "rating={}\nstreak={}\nbest_streak={}\nsolved={}\nfailed={}\nplayed={}\nattempted={}\nretry={}\n",
+            self.rating,
+            self.streak,
+            self.best_streak,
+            self.solved,
+            self.failed,
+            self.played,
+            attempted.join(" "),
+            retry.join(" "),
+        )
+    }
+    fn apply_text(&mut self, text: &str) {
+        for line in text.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            match name {
+                "rating" => self.rating = value.parse().unwrap_or(self.rating),
+                "streak" => self.streak = value.parse().unwrap_or(0),
+                "best_streak" => self.best_streak = value.parse().unwrap_or(0),
+                "solved" => self.solved = value.parse().unwrap_or(0),
+                "failed" => self.failed = value.parse().unwrap_or(0),
+                "played" => self.played = value.parse().unwrap_or(0),
+                "attempted" => self.attempted = value.split_whitespace().map(|s| s.to_string()).collect(),
+                "retry" => {
+                    self.retry = value
+                        .split_whitespace()
+                        .filter_map(|entry| {
+                            let (id, due) = entry.rsplit_once(':')?;
+                            Some((id.to_string(), due.parse().ok()?))
+                        })
+                        .collect();
+                }
+                _ => (),
+            }
+        }
+    }
+}
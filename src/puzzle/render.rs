@@ -0,0 +1,129 @@
+use chess_lib::Colour;
+use egui::{Color32, RichText, Ui};
+#[cfg(not(target_arch = "wasm32"))]
+use rfd::FileDialog;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+use crate::puzzle::{PuzzleStatus, PuzzleTab};
+
+impl PuzzleTab
+{
+    pub fn render_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Puzzles");
+        ui.add_space(8.0);
+
+        self.render_progress(ui);
+        ui.separator();
+
+        match self.puzzle() {
+            Some(puzzle) => {
+                let side = match self.solver_colour() {
+                    Some(Colour::White) => "White",
+                    _ => "Black",
+                };
+                ui.label(RichText::new(format!("{} to play", side)).strong());
+                if self.is_retry {
+                    ui.label(RichText::new("Retrying a missed puzzle").weak());
+                }
+                if self.status != PuzzleStatus::Solving {
+                    ui.label(format!("Puzzle {} ({:.0})", puzzle.id, puzzle.rating));
+                    if !puzzle.themes.is_empty() {
+                        ui.label(RichText::new(puzzle.themes.join(", ")).weak());
+                    }
+                }
+            }
+            None => {
+                ui.label("No puzzles loaded");
+            }
+        }
+        if let Some(feedback) = &self.feedback {
+            let colour = match self.status {
+                PuzzleStatus::Solved => Color32::from_rgb(90, 170, 90),
+                PuzzleStatus::Solving => Color32::from_rgb(200, 80, 80),
+                _ => ui.visuals().text_color(),
+            };
+            ui.label(RichText::new(feedback).color(colour));
+        }
+        ui.add_space(8.0);
+
+        ui.horizontal_wrapped(|ui| {
+            let solving = self.status == PuzzleStatus::Solving && self.current.is_some();
+            if ui.add_enabled(solving, egui::Button::new("Show solution")).clicked() {
+                self.give_up();
+            }
+            if ui.add_enabled(self.current.is_some(), egui::Button::new("Restart")).clicked() {
+                self.restart();
+            }
+            let next = if solving { "Skip" } else { "Next puzzle" };
+            if ui.button(next).clicked() {
+                self.next_puzzle();
+            }
+        });
+        ui.separator();
+
+        self.render_loading(ui);
+        ui.separator();
+
+        ui.collapsing("Generate from games", |ui| {
+            self.render_generator_panel(ui);
+        });
+    }
+
+    fn render_progress(&self, ui: &mut Ui) {
+        let p = &self.progress;
+        egui::Grid::new("puzzle_progress").show(ui, |ui| {
+            ui.label("Rating");
+            ui.label(RichText::new(format!("{:.0}", p.rating)).strong());
+            ui.end_row();
+            ui.label("Streak");
+            ui.label(format!("{} (best {})", p.streak, p.best_streak));
+            ui.end_row();
+            ui.label("Solved / failed");
+            ui.label(format!("{} / {}", p.solved, p.failed));
+            ui.end_row();
+            ui.label("Waiting for retry");
+            ui.label(p.retry.len().to_string());
+            ui.end_row();
+        });
+    }
+
+    fn render_loading(&mut self, ui: &mut Ui) {
+        ui.label(format!("{} puzzles in the current set", self.puzzles.len()));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Open puzzle file").clicked() {
+            self.open_file();
+        }
+
+        ui.collapsing("Paste puzzles", |ui| {
+            ui.label(RichText::new("Lichess CSV, FEN,moves CSV or EPD with bm").weak());
+            ui.add(
+                egui::TextEdit::multiline(&mut self.paste_text)
+                    .desired_rows(6)
+                    .code_editor(),
+            );
+            if ui.button("Load").clicked() {
+                let text = std::mem::take(&mut self.paste_text);
+                self.load_text(&text);
+            }
+        });
+        if let Some(message) = &self.load_message {
+            ui.label(message);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_file(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("Puzzles", &["csv", "epd"])
+            .pick_file()
+        else {
+            return;
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => self.load_text(&text),
+            Err(e) => self.load_message = Some(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use chess_lib::{Board, Colour, Piece, Tile};
+
+use crate::{
+    analysis::piece_value,
+    attacks::{attacks, directions, offset},
+};
+
+/// The piece on `tile` attacks two or more valuable enemy pieces, counting the king.
+fn is_fork(board: &Board, tile: Tile, colour: Colour) -> bool {
+    let Some((piece, _)) = board.get_piece_at_tile(tile) else {
+        return false;
+    };
+    let targets = attacks(board, tile)
+        .into_iter()
+        .filter_map(|t| board.get_piece_at_tile(t))
+        .filter(|&(p, c)| c != colour && (p == Piece::King || piece_value(p) > piece_value(piece).max(100)))
+        .count();
+    targets >= 2
+}
+
+/// The slider on `tile` pins an enemy piece to its king or to a more valuable piece.
+fn is_pin(board: &Board, tile: Tile, colour: Colour) -> bool {
+    let Some((piece, _)) = board.get_piece_at_tile(tile) else {
+        return false;
+    };
+    for (dx, dy) in directions(piece) {
+        let mut hits = Vec::new();
+        let mut current = tile;
+        while let Some(next) = offset(current, dx, dy) {
+            if let Some(hit) = board.get_piece_at_tile(next) {
+                hits.push(hit);
+                if hits.len() == 2 {
+                    break;
+                }
+            }
+            current = next;
+        }
+        if let [(first, c1), (second, c2)] = hits[..] {
+            let behind_is_worth_more = second == Piece::King || piece_value(second) > piece_value(first);
+            if c1 != colour && c2 != colour && first != Piece::King && behind_is_worth_more {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Lichess-style theme tags for a material-winning line starting in `position`.
+pub fn material_themes(position: &Board, first: chess_lib::Move, solver_plies: usize) -> Vec<String> {
+    let colour = position.turn;
+    let capture = position.get_piece_at_tile(first.to()).is_some();
+    let mut after = position.clone();
+    after.make_move_unchecked(first);
+
+    let mut themes = Vec::new();
+    if is_fork(&after, first.to(), colour) {
+        themes.push("fork");
+    }
+    if is_pin(&after, first.to(), colour) {
+        themes.push("pin");
+    }
+    if themes.is_empty() && capture && solver_plies == 1 {
+        themes.push("hangingPiece");
+    }
+    themes.push("advantage");
+    themes.iter().map(|t| t.to_string()).collect()
+}
@@ -0,0 +1,567 @@
+// Reads a position from a screenshot of a 2D board. The grid is found from the
+// checkerboard's edges, then every square is compared with piece templates cut
+// from the bundled atlases. Only `image` is needed, so it works offline.
+//
+// The templates are this app's own pieces and the cburnett set used by lichess
+// and Wikipedia, which together cover exported diagrams, screenshots of the app
+// and most boards on the web. Sets whose licences don't allow bundling them,
+// such as chess.com's, are read from their silhouettes alone and come out
+// uncertain more often.
+
+use chess_lib::{Colour, Piece, Tile};
+use image::{imageops::{self, FilterType}, RgbaImage};
+
+use crate::diagram::atlas_cell;
+
+/// The atlases templates are cut from, all laid out like this app's own:
+/// three columns of pieces, White's two rows above Black's.
+const ATLASES: [(&str, &[u8]); 2] = [
+    ("app", include_bytes!("../assets/PieceAtlas.png")),
+    ("cburnett", include_bytes!("../assets/pieces/cburnett.png")),
+];
+
+/// Larger screenshots are scaled down to this before looking for the grid.
+const MAX_SIDE: u32 = 1200;
+/// Side of the sample taken from each square.
+const CELL: usize = 48;
+/// Side a piece's bounding box is scaled to before comparing.
+const SHAPE: usize = 24;
+/// Brightness difference from the square's colour that counts as part of a piece.
+const INK: f32 = 0.11;
+/// Share of a square's middle that has to be covered for it to hold a piece.
+const MIN_COVER: f32 = 0.06;
+/// How far the grid's lines have to stand out from the average edge.
+const MIN_CONTRAST: f32 = 2.5;
+/// Matches scoring below this, or this close to the runner-up, are reported.
+const MIN_SCORE: f32 = 0.55;
+const MIN_MARGIN: f32 = 0.04;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Orientation
+{
+    /// Decided from where each side's pieces stand.
+    Auto,
+    WhiteBottom,
+    BlackBottom,
+}
+impl Orientation
+{
+    pub fn label(&self) -> &'static str {
+        match self {
+            Orientation::Auto => "Automatic",
+            Orientation::WhiteBottom => "White at the bottom",
+            Orientation::BlackBottom => "Black at the bottom",
+        }
+    }
+}
+
+pub struct Recognition
+{
+    pub pieces: Vec<(Piece, Colour, Tile)>,
+    /// Squares whose reading is a guess worth checking.
+    pub uncertain: Vec<Tile>,
+    /// The board's x, y, width and height in the image, or `None` when no
+    /// grid stood out and the whole image was taken as the board.
+    pub board: Option<[u32; 4]>,
+    /// Black was at the bottom of the image.
+    pub flipped: bool,
+}
+
+/// Brightness values from 0 to 1.
+#[derive(Clone)]
+struct Plane
+{
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+impl Plane
+{
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, values: vec![0.0; width * height] }
+    }
+    fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+    /// Area-averages the rectangle at (x, y) of size (w, h) into `out_w` x `out_h`.
+    fn resample(&self, x: f32, y: f32, w: f32, h: f32, out_w: usize, out_h: usize) -> Plane {
+        let mut out = Plane::new(out_w, out_h);
+        let (sx, sy) = (w / out_w as f32, h / out_h as f32);
+        for oy in 0..out_h {
+            let y0 = (y + oy as f32 * sy).floor().max(0.0) as usize;
+            let y1 = ((y + (oy + 1) as f32 * sy).ceil() as usize).clamp(y0 + 1, self.height);
+            for ox in 0..out_w {
+                let x0 = (x + ox as f32 * sx).floor().max(0.0) as usize;
+                let x1 = ((x + (ox + 1) as f32 * sx).ceil() as usize).clamp(x0 + 1, self.width);
+                let (mut sum, mut count) = (0.0, 0);
+                for py in y0.min(self.height - 1)..y1 {
+                    for px in x0.min(self.width - 1)..x1 {
+                        sum += self.get(px, py);
+                        count += 1;
+                    }
+                }
+                out.values[oy * out_w + ox] = if count > 0 { sum / count as f32 } else { 0.0 };
+            }
+        }
+        out
+    }
+}
+
+/// A piece cropped to its bounding box and scaled to `SHAPE` x `SHAPE`.
+struct Shape
+{
+    /// How much of each pixel the piece covers.
+    cover: Vec<f32>,
+    luma: Vec<f32>,
+}
+
+struct Template
+{
+    piece: Piece,
+    colour: Colour,
+    shape: Shape,
+}
+
+fn luma(r: u8, g: u8, b: u8) -> f32 {
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) / 255.0
+}
+
+/// Crops `cover` and `luma` to the bounding box of the covered pixels.
+fn shape(cover: &Plane, luma: &Plane) -> Option<Shape> {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+    for y in 0..cover.height {
+        for x in 0..cover.width {
+            if cover.get(x, y) > 0.5 {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+    if min_x > max_x {
+        return None;
+    }
+    let (x, y) = (min_x as f32, min_y as f32);
+    let (w, h) = ((max_x - min_x + 1) as f32, (max_y - min_y + 1) as f32);
+    Some(Shape {
+        cover: cover.resample(x, y, w, h, SHAPE, SHAPE).values,
+        luma: luma.resample(x, y, w, h, SHAPE, SHAPE).values,
+    })
+}
+
+fn templates() -> Result<Vec<Template>, String> {
+    let mut templates = Vec::new();
+    for (name, bytes) in ATLASES {
+        let atlas = image::load_from_memory(bytes).map_err(|e| format!("Can't read the {} piece atlas: {}", name, e))?;
+        let atlas = atlas.to_rgba8();
+        let cell = atlas.width() / 3;
+        for colour in [Colour::White, Colour::Black] {
+            for piece in Piece::ALL_PIECES {
+                let (row, col) = atlas_cell(piece, colour);
+                let sprite = imageops::crop_imm(&atlas, col * cell, row * cell, cell, cell).to_image();
+                let sprite = imageops::resize(&sprite, CELL as u32, CELL as u32, FilterType::Triangle);
+                let (cover, luma) = sprite_planes(&sprite);
+                if let Some(shape) = shape(&cover, &luma) {
+                    templates.push(Template { piece, colour, shape });
+                }
+            }
+        }
+    }
+    Ok(templates)
+}
+
+fn sprite_planes(sprite: &RgbaImage) -> (Plane, Plane) {
+    let (w, h) = (sprite.width() as usize, sprite.height() as usize);
+    let mut cover = Plane::new(w, h);
+    let mut brightness = Plane::new(w, h);
+    for (i, p) in sprite.pixels().enumerate() {
+        cover.values[i] = p[3] as f32 / 255.0;
+        brightness.values[i] = luma(p[0], p[1], p[2]);
+    }
+    (cover, brightness)
+}
+
+/// Edge strength at each boundary between columns (or rows): how much the
+/// brightness both rises and falls across it, as it does along a board line
+/// where light and dark squares alternate.
+fn line_profile(plane: &Plane, columns: bool) -> Vec<f32> {
+    let (along, across) = if columns { (plane.width, plane.height) } else { (plane.height, plane.width) };
+    let mut profile = vec![0.0; along + 1];
+    for i in 0..along.saturating_sub(1) {
+        let (mut rise, mut fall) = (0.0, 0.0);
+        for j in 0..across {
+            let d = if columns {
+                plane.get(i + 1, j) - plane.get(i, j)
+            } else {
+                plane.get(j, i + 1) - plane.get(j, i)
+            };
+            if d > 0.0 { rise += d } else { fall -= d }
+        }
+        profile[i + 1] = (rise * fall).sqrt();
+    }
+    profile
+}
+
+/// The nine evenly spaced lines that best fit the profile's peaks, as
+/// (first line, spacing, contrast against the average boundary).
+fn fit_lines(profile: &[f32]) -> Option<(f32, f32, f32)> {
+    let size = profile.len() - 1;
+    let mean = profile.iter().sum::<f32>() / profile.len() as f32;
+    if mean <= 0.0 {
+        return None;
+    }
+    // Allow a pixel either way for rounding and anti-aliasing
+    let peak = |p: f32| {
+        let i = p.round() as usize;
+        (i.saturating_sub(1)..=(i + 1).min(size)).map(|j| profile[j]).fold(0.0, f32::max)
+    };
+
+    let mut best: Option<(f32, f32, f32)> = None;
+    // Squares smaller than 8 pixels can't be read anyway
+    let mut step = 8.0;
+    while step * 8.0 <= size as f32 {
+        let mut start = 0.0;
+        while start + step * 8.0 <= size as f32 {
+            let score: f32 = (0..9).map(|k| peak(start + k as f32 * step)).sum();
+            if best.is_none_or(|(_, _, s)| score > s) {
+                best = Some((start, step, score));
+            }
+            start += 1.0;
+        }
+        step += 0.5;
+    }
+    best.map(|(start, step, score)| (start, step, score / 9.0 / mean))
+}
+
+/// The board's x, y and square size, when the grid stands out in both directions.
+fn find_grid(plane: &Plane) -> Option<(f32, f32, f32, f32)> {
+    let (x, sx, cx) = fit_lines(&line_profile(plane, true))?;
+    let (y, sy, cy) = fit_lines(&line_profile(plane, false))?;
+    let square_enough = (sx / sy - 1.0).abs() < 0.15;
+    (cx >= MIN_CONTRAST && cy >= MIN_CONTRAST && square_enough).then_some((x, y, sx, sy))
+}
+
+/// The median brightness around the edge of a square sample, taken as the square's colour.
+fn background(cell: &Plane) -> f32 {
+    let n = cell.width;
+    let mut ring: Vec<f32> = (0..n * n)
+        .filter(|i| {
+            let (x, y) = (i % n, i / n);
+            x < 3 || y < 3 || x >= n - 3 || y >= n - 3
+        })
+        .map(|i| cell.values[i])
+        .collect();
+    ring.sort_by(f32::total_cmp);
+    ring[ring.len() / 2]
+}
+
+/// Pixels belonging to a piece: those off the square's colour plus everything
+/// they enclose, keeping only blobs of a useful size so coordinates drawn in
+/// the corners drop out.
+fn piece_cover(cell: &Plane, background: f32) -> Plane {
+    let n = cell.width;
+    let ink: Vec<bool> = cell.values.iter().map(|v| (v - background).abs() > INK).collect();
+
+    // Flood the background in from the edges; whatever it can't reach is piece
+    let mut outside = vec![false; n * n];
+    let mut stack: Vec<usize> = (0..n * n)
+        .filter(|i| {
+            let (x, y) = (i % n, i / n);
+            (x == 0 || y == 0 || x == n - 1 || y == n - 1) && !ink[*i]
+        })
+        .collect();
+    while let Some(i) = stack.pop() {
+        if outside[i] || ink[i] {
+            continue;
+        }
+        outside[i] = true;
+        let (x, y) = (i % n, i / n);
+        if x > 0 { stack.push(i - 1) }
+        if x < n - 1 { stack.push(i + 1) }
+        if y > 0 { stack.push(i - n) }
+        if y < n - 1 { stack.push(i + n) }
+    }
+
+    // Label the connected blobs
+    let mut blob = vec![usize::MAX; n * n];
+    let mut sizes = Vec::new();
+    for start in 0..n * n {
+        if outside[start] || blob[start] != usize::MAX {
+            continue;
+        }
+        let id = sizes.len();
+        let mut size = 0;
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            if outside[i] || blob[i] != usize::MAX {
+                continue;
+            }
+            blob[i] = id;
+            size += 1;
+            let (x, y) = (i % n, i / n);
+            if x > 0 { stack.push(i - 1) }
+            if x < n - 1 { stack.push(i + 1) }
+            if y > 0 { stack.push(i - n) }
+            if y < n - 1 { stack.push(i + n) }
+        }
+        sizes.push(size);
+    }
+    let largest = sizes.iter().copied().max().unwrap_or(0);
+
+    let mut cover = Plane::new(n, n);
+    for (i, &id) in blob.iter().enumerate() {
+        if id != usize::MAX && sizes[id] * 5 >= largest {
+            cover.values[i] = 1.0;
+        }
+    }
+    cover
+}
+
+/// Share of the middle three fifths of the square that's covered.
+fn middle_cover(cover: &Plane) -> f32 {
+    let n = cover.width;
+    let (low, high) = (n / 5, n - n / 5);
+    let covered = (low..high).flat_map(|y| (low..high).map(move |x| (x, y))).filter(|&(x, y)| cover.get(x, y) > 0.5).count();
+    covered as f32 / ((high - low) * (high - low)) as f32
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let (mean_a, mean_b) = (a.iter().sum::<f32>() / n, b.iter().sum::<f32>() / n);
+    let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (x - mean_a, y - mean_b);
+        ab += x * y;
+        aa += x * x;
+        bb += y * y;
+    }
+    if aa <= 0.0 || bb <= 0.0 { 0.0 } else { ab / (aa * bb).sqrt() }
+}
+
+/// Intersection over union of two coverages.
+fn overlap(a: &[f32], b: &[f32]) -> f32 {
+    let (mut both, mut either) = (0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        both += x.min(*y);
+        either += x.max(*y);
+    }
+    if either <= 0.0 { 0.0 } else { both / either }
+}
+
+/// Half outline (the silhouette works across piece sets), half brightness
+/// (the inner detail tells a bundled set's pieces apart).
+fn similarity(found: &Shape, template: &Shape, background: f32) -> f32 {
+    let drawn: Vec<f32> = template
+        .cover
+        .iter()
+        .zip(&template.luma)
+        .map(|(c, l)| c * l + (1.0 - c) * background)
+        .collect();
+    (correlation(&found.luma, &drawn) + overlap(&found.cover, &template.cover)) / 2.0
+}
+
+/// Average brightness of the covered pixels.
+fn brightness(shape: &Shape) -> f32 {
+    let total: f32 = shape.cover.iter().sum();
+    let lit: f32 = shape.cover.iter().zip(&shape.luma).map(|(c, l)| c * l).sum();
+    if total > 0.0 { lit / total } else { 0.0 }
+}
+
+/// Reads the position from a PNG or JPEG screenshot of a 2D board.
+pub fn recognize(bytes: &[u8], orientation: Orientation) -> Result<Recognition, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("Can't read the image: {}", e))?;
+    let mut gray = image.to_luma8();
+    let scale = (gray.width().max(gray.height()) as f32 / MAX_SIDE as f32).max(1.0);
+    if scale > 1.0 {
+        let (w, h) = ((gray.width() as f32 / scale) as u32, (gray.height() as f32 / scale) as u32);
+        gray = imageops::resize(&gray, w.max(1), h.max(1), FilterType::Triangle);
+    }
+    let plane = Plane {
+        width: gray.width() as usize,
+        height: gray.height() as usize,
+        values: gray.pixels().map(|p| p[0] as f32 / 255.0).collect(),
+    };
+    if plane.width < 64 || plane.height < 64 {
+        return Err("The image is too small to read a board from".to_string());
+    }
+
+    let grid = find_grid(&plane);
+    let (x0, y0, sx, sy) = grid.unwrap_or((0.0, 0.0, plane.width as f32 / 8.0, plane.height as f32 / 8.0));
+
+    let templates = templates()?;
+    let lightness: Vec<f32> = templates.iter().map(|t| brightness(&t.shape)).collect();
+
+    // Read the squares as they appear on screen, top-left first
+    let mut found = Vec::new();
+    for row in 0..8 {
+        for col in 0..8 {
+            // Stay clear of the grid lines and borders
+            let inset = 0.03;
+            let cell = plane.resample(
+                x0 + (col as f32 + inset) * sx,
+                y0 + (row as f32 + inset) * sy,
+                sx * (1.0 - 2.0 * inset),
+                sy * (1.0 - 2.0 * inset),
+                CELL,
+                CELL,
+            );
+            let bg = background(&cell);
+            let cover = piece_cover(&cell, bg);
+            let cover_share = middle_cover(&cover);
+            let Some(piece_shape) = shape(&cover, &cell).filter(|_| cover_share >= MIN_COVER) else {
+                // A sliver of cover near the threshold could be a small piece
+                found.push((col, row, None, cover_share > MIN_COVER / 2.0));
+                continue;
+            };
+
+            // A piece as bright as the template is likely the same colour
+            let light = brightness(&piece_shape);
+            let mut scores: Vec<(f32, Piece, Colour)> = templates
+                .iter()
+                .zip(&lightness)
+                .map(|(t, l)| (similarity(&piece_shape, &t.shape, bg) - (light - l).abs(), t.piece, t.colour))
+                .collect();
+            scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let (score, piece, colour) = scores[0];
+            // Templates of the same piece from different sets don't compete
+            let runner_up = scores.iter().find(|s| (s.1, s.2) != (piece, colour)).map_or(0.0, |s| s.0);
+            let unsure = score < MIN_SCORE || score - runner_up < MIN_MARGIN;
+            found.push((col, row, Some((piece, colour)), unsure));
+        }
+    }
+
+    let flipped = match orientation {
+        Orientation::WhiteBottom => false,
+        Orientation::BlackBottom => true,
+        Orientation::Auto => {
+            // White's pieces sitting higher up than Black's means the board is seen from Black's side
+            let mean_row = |colour: Colour| {
+                let rows: Vec<f32> = found
+                    .iter()
+                    .filter(|f| f.2.is_some_and(|(_, c)| c == colour))
+                    .map(|f| f.1 as f32)
+                    .collect();
+                (!rows.is_empty()).then(|| rows.iter().sum::<f32>() / rows.len() as f32)
+            };
+            matches!((mean_row(Colour::White), mean_row(Colour::Black)), (Some(w), Some(b)) if w < b)
+        }
+    };
+    let tile = |col: u8, row: u8| if flipped { Tile::new_xy(7 - col, row) } else { Tile::new_xy(col, 7 - row) };
+
+    let mut recognition = Recognition {
+        pieces: Vec::new(),
+        uncertain: Vec::new(),
+        board: grid.map(|(x, y, sx, sy)| {
+            [(x * scale) as u32, (y * scale) as u32, (sx * 8.0 * scale) as u32, (sy * 8.0 * scale) as u32]
+        }),
+        flipped,
+    };
+    for (col, row, piece, unsure) in found {
+        let Some(t) = tile(col, row) else {
+            continue;
+        };
+        if let Some((piece, colour)) = piece {
+            recognition.pieces.push((piece, colour, t));
+        }
+        if unsure {
+            recognition.uncertain.push(t);
+        }
+    }
+    Ok(recognition)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use chess_lib::Board;
+
+    use crate::{
+        diagram::{self, grid_position, is_light, raster, DiagramOptions, DARK_SQUARE, LIGHT_SQUARE},
+        notation::{piece_char, piece_from_char, tile_name},
+    };
+
+    const FEN: &str = "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R w KQkq - 0 5";
+
+    /// The pieces of a FEN's placement field.
+    fn placed(fen: &str) -> Vec<(Piece, Colour, Tile)> {
+        let mut pieces = Vec::new();
+        for (row, rank) in fen.split_whitespace().next().unwrap().split('/').enumerate() {
+            let mut file = 0;
+            for c in rank.chars() {
+                match (c.to_digit(10), piece_from_char(c)) {
+                    (Some(n), _) => file += n as u8,
+                    (None, Some(piece)) => {
+                        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+                        pieces.push((piece, colour, Tile::new_xy(file, 7 - row as u8).unwrap()));
+                        file += 1;
+                    }
+                    (None, None) => panic!("bad FEN"),
+                }
+            }
+        }
+        pieces
+    }
+
+    /// Pieces as sorted names such as `wNf3`, to compare readings.
+    fn names(pieces: &[(Piece, Colour, Tile)]) -> Vec<String> {
+        let mut names: Vec<String> = pieces
+            .iter()
+            .map(|&(piece, colour, tile)| format!("{}{}{}", if colour.white() { 'w' } else { 'b' }, piece_char(piece), tile_name(tile)))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// A board in the diagram colours with `pieces` drawn from one of the bundled atlases.
+    fn drawn_with(atlas: &[u8], pieces: &[(Piece, Colour, Tile)], square: u32) -> Vec<u8> {
+        let atlas = image::load_from_memory(atlas).unwrap().to_rgba8();
+        let cell = atlas.width() / 3;
+        let mut board = RgbaImage::new(square * 8, square * 8);
+        for tile in (0..8).flat_map(|y| (0..8).filter_map(move |x| Tile::new_xy(x, y))) {
+            let (col, row) = grid_position(tile, false);
+            let [r, g, b] = if is_light(tile) { LIGHT_SQUARE } else { DARK_SQUARE };
+            for y in 0..square {
+                for x in 0..square {
+                    board.put_pixel(col * square + x, row * square + y, image::Rgba([r, g, b, 255]));
+                }
+            }
+        }
+        for &(piece, colour, tile) in pieces {
+            let (row, col) = diagram::atlas_cell(piece, colour);
+            let sprite = imageops::crop_imm(&atlas, col * cell, row * cell, cell, cell).to_image();
+            let sprite = imageops::resize(&sprite, square, square, FilterType::Triangle);
+            let (x, y) = grid_position(tile, false);
+            imageops::overlay(&mut board, &sprite, (x * square) as i64, (y * square) as i64);
+        }
+        let mut png = Vec::new();
+        board.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        png
+    }
+
+    #[test]
+    fn reads_back_an_exported_diagram() {
+        let board = Board::new_from_fen(FEN).unwrap();
+        for flipped in [false, true] {
+            let options = DiagramOptions { flipped, ..DiagramOptions::default() };
+            let png = raster::board_png(&board, &options).unwrap();
+            let found = recognize(&png, Orientation::Auto).unwrap();
+            assert!(found.board.is_some());
+            assert_eq!(found.flipped, flipped);
+            assert_eq!(names(&found.pieces), names(&placed(FEN)));
+            assert!(found.uncertain.is_empty());
+        }
+    }
+
+    #[test]
+    fn reads_every_bundled_set() {
+        for (name, atlas) in ATLASES {
+            for square in [40, 64] {
+                let found = recognize(&drawn_with(atlas, &placed(FEN), square), Orientation::Auto).unwrap();
+                assert_eq!(names(&found.pieces), names(&placed(FEN)), "{} pieces at {}px", name, square);
+                assert!(!found.flipped);
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// Links to the web build that open a position or a game. `fen=` holds a
+// position with `_` for spaces, `pgn=` a game's moves in UCI packed two bytes
+// apiece (URL-safe base64) and `tab=` the tab to open. Keys are read from both
+// the query and the fragment, and written to the fragment so they never reach
+// the server.
+
+use chess_lib::{Board, Move, Tile};
+
+use crate::{base64, chess960, notation};
+
+/// First byte of packed moves, so links from a different encoding fail cleanly.
+const MOVES_VERSION: u8 = 2;
+
+/// Promotion letters in UCI, numbered from 1 in a packed move.
+const PROMOTIONS: [char; 4] = ['n', 'b', 'r', 'q'];
+
+#[derive(Debug, Clone, Default)]
+pub struct SharedLink
+{
+    pub tab: Option<String>,
+    pub fen: Option<String>,
+    /// Packed moves as written in the link.
+    pub moves: Option<String>,
+}
+impl SharedLink
+{
+    pub fn is_empty(&self) -> bool {
+        self.tab.is_none() && self.fen.is_none() && self.moves.is_none()
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Reads the share keys from a URL's query and fragment, e.g. `?tab=editor#fen=…`.
+pub fn parse_link(url: &str) -> SharedLink {
+    let mut link = SharedLink::default();
+    let params = url.split(['?', '#']).skip(1).flat_map(|part| part.split('&'));
+    for param in params {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = percent_decode(value);
+        match key {
+            "tab" => link.tab = Some(value),
+            "fen" => link.fen = Some(value.replace('_', " ")),
+            "pgn" => link.moves = Some(value),
+            _ => (),
+        }
+    }
+    link
+}
+
+/// `base` with the keys in its fragment.
+pub fn make_link(base: &str, link: &SharedLink) -> String {
+    let mut params = Vec::new();
+    if let Some(tab) = &link.tab {
+        params.push(format!("tab={}", tab));
+    }
+    if let Some(fen) = &link.fen {
+        params.push(format!("fen={}", fen.trim().replace(' ', "_")));
+    }
+    if let Some(moves) = &link.moves {
+        params.push(format!("pgn={}", moves));
+    }
+    let base = base.split('#').next().unwrap_or(base);
+    if params.is_empty() { base.to_string() } else { format!("{}#{}", base, params.join("&")) }
+}
+
+/// A UCI move as from square, to square (six bits each) and promotion piece.
+fn pack_uci(uci: &str) -> Option<[u8; 2]> {
+    let from = notation::parse_tile(uci.get(0..2)?)?;
+    let to = notation::parse_tile(uci.get(2..4)?)?;
+    let promotion = match uci.get(4..)? {
+        "" => 0,
+        p => PROMOTIONS.iter().position(|c| p.len() == 1 && p.starts_with(*c))? as u16 + 1,
+    };
+    let index = |t: Tile| {
+        let (x, y) = t.get_coords();
+        (y * 8 + x) as u16
+    };
+    Some((index(from) | index(to) << 6 | promotion << 12).to_be_bytes())
+}
+
+fn unpack_uci(bytes: [u8; 2]) -> Option<String> {
+    let packed = u16::from_be_bytes(bytes);
+    let tile = |i: u16| Tile::new_xy((i % 8) as u8, (i / 8) as u8).map(notation::tile_name);
+    let mut uci = format!("{}{}", tile(packed & 0x3F)?, tile(packed >> 6 & 0x3F)?);
+    if packed >> 12 != 0 {
+        uci.push(*PROMOTIONS.get((packed >> 12) as usize - 1)?);
+    }
+    Some(uci)
+}
+
+/// Packs moves given in UCI. Squares rather than move list indices keep links
+/// readable whatever order chess_lib generates its moves in.
+pub fn encode_moves(moves: &[String]) -> Result<String, String> {
+    let mut bytes = vec![MOVES_VERSION];
+    for (ply, uci) in moves.iter().enumerate() {
+        bytes.extend(pack_uci(uci).ok_or(format!("Move {} ({}) can't be put in a link", ply + 1, uci))?);
+    }
+    Ok(base64::encode_url(&bytes))
+}
+
+/// Unpacks moves written by `encode_moves`, in UCI. Legality is up to the caller.
+pub fn decode_moves(text: &str) -> Result<Vec<String>, String> {
+    let garbled = || "The link's moves are garbled".to_string();
+    let bytes = base64::decode_url(text).ok_or_else(garbled)?;
+    let Some((&version, packed)) = bytes.split_first() else {
+        return Ok(Vec::new());
+    };
+    if version != MOVES_VERSION {
+        return Err("The link's moves come from a different version".to_string());
+    }
+    if packed.len() % 2 != 0 {
+        return Err(garbled());
+    }
+    packed.chunks(2).map(|pair| unpack_uci([pair[0], pair[1]]).ok_or_else(garbled)).collect()
+}
+
+/// The start position and moves a link describes, as far as chess_lib can
+/// play them: castling rights it doesn't know are left out.
+pub fn link_game(link: &SharedLink) -> Result<(Board, Vec<Move>), String> {
+    let start = match &link.fen {
+        Some(fen) => Board::new_from_fen(&chess960::lib_fen(fen)).map_err(|e| format!("Invalid FEN: {}", e))?,
+        None => Board::new(),
+    };
+    let mut board = start.clone();
+    let ucis = match &link.moves {
+        Some(text) => decode_moves(text)?,
+        None => Vec::new(),
+    };
+    let mut moves = Vec::with_capacity(ucis.len());
+    for (ply, uci) in ucis.iter().enumerate() {
+        let m = notation::parse_move(&board, uci).ok_or(format!("The link's move {} ({}) is illegal", ply + 1, uci))?;
+        board.make_move_unchecked(m);
+        moves.push(m);
+    }
+    Ok((start, moves))
+}
+
+/// The page's own address, which share links are built on. Native builds
+/// only have one when built with `EGUI_CHESS_SHARE_URL` set.
+#[cfg(target_arch = "wasm32")]
+pub fn share_base() -> Option<String> {
+    let location = web_sys::window()?.location();
+    Some(format!("{}{}", location.origin().ok()?, location.pathname().ok()?))
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn share_base() -> Option<String> {
+    option_env!("EGUI_CHESS_SHARE_URL").map(|url| url.to_string())
+}
+
+/// The keys in the address the page was opened with.
+#[cfg(target_arch = "wasm32")]
+pub fn page_link() -> Option<SharedLink> {
+    let location = web_sys::window()?.location();
+    let url = format!("{}{}", location.search().unwrap_or_default(), location.hash().unwrap_or_default());
+    Some(parse_link(&url)).filter(|link| !link.is_empty())
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn page_link() -> Option<SharedLink> {
+    None
+}
@@ -0,0 +1,51 @@
+use egui::Context;
+
+pub mod record;
+mod render;
+use record::{load_games, save_games, GameRecord};
+
+pub struct StatisticsTab
+{
+    pub games: Vec<GameRecord>,
+    /// Show the last N games in the results-over-time chart.
+    pub chart_games: usize,
+    confirm_clear: bool,
+}
+
+impl Default for StatisticsTab
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl StatisticsTab
+{
+    pub fn new() -> Self {
+        Self {
+            games: load_games(),
+            chart_games: 50,
+            confirm_clear: false,
+        }
+    }
+    /// Adds finished games to the history and saves it.
+    pub fn add_games(&mut self, games: Vec<GameRecord>) {
+        if games.is_empty() {
+            return;
+        }
+        self.games.extend(games);
+        save_games(&self.games);
+    }
+    pub fn clear(&mut self) {
+        self.games.clear();
+        save_games(&self.games);
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("scroll_statistics")
+                .show(ui, |ui| {
+                    self.render_dashboard(ui);
+                });
+        });
+    }
+}
@@ -0,0 +1,158 @@
+use chess_lib::{Colour, GameState};
+
+use crate::{play::state::Termination, storage};
+
+const GAMES_FILE: &str = "games.tsv";
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum GameResult
+{
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+impl GameResult
+{
+    pub fn from_termination(termination: Termination) -> Self {
+        match termination.winner() {
+            Some(Colour::White) => GameResult::WhiteWins,
+            Some(Colour::Black) => GameResult::BlackWins,
+            None => GameResult::Draw,
+        }
+    }
+    /// The PGN result string.
+    pub fn label(&self) -> &str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+}
+
+/// How a game ended, from the player's point of view.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Outcome
+{
+    Win,
+    Draw,
+    Loss,
+}
+
+pub fn termination_label(state: GameState) -> &'static str {
+    match state {
+        GameState::Playing => "Unterminated",
+        GameState::Checkmate(_) => "Checkmate",
+        GameState::Stalemate(_) => "Stalemate",
+        GameState::InsufficientMaterial => "Insufficient material",
+        GameState::FiftyMoveRule => "Fifty-move rule",
+        GameState::ThreeRepetition => "Threefold repetition",
+    }
+}
+
+/// A finished game, as kept in the local game history.
+#[derive(Debug, Clone)]
+pub struct GameRecord
+{
+    /// Seconds since the Unix epoch when the game finished.
+    pub timestamp: u64,
+    pub result: GameResult,
+    /// The side the human played against the engine, `None` if there wasn't exactly one.
+    pub human_colour: Option<Colour>,
+    /// "White", "Black", "Both" or "Neither", as in the engine selector.
+    pub engine_side: String,
+    pub engine_depth: u8,
+    pub termination: String,
+    pub start_fen: Option<String>,
+    pub moves: Vec<String>,
+}
+impl GameRecord
+{
+    pub fn outcome(&self) -> Option<Outcome> {
+        let colour = self.human_colour?;
+        Some(match (self.result, colour) {
+            (GameResult::Draw, _) => Outcome::Draw,
+            (GameResult::WhiteWins, Colour::White) | (GameResult::BlackWins, Colour::Black) => Outcome::Win,
+            _ => Outcome::Loss,
+        })
+    }
+    /// The first three moves in PGN move-number form, e.g. `1. e4 e5 2. Nf3 Nc6 3. Bb5 a6`.
+    pub fn opening(&self) -> String {
+        let mut text = String::new();
+        for (i, san) in self.moves.iter().take(6).enumerate() {
+            if i % 2 == 0 {
+                if i > 0 {
+                    text.push(' ');
+                }
+                text.push_str(&format!("{}. ", i / 2 + 1));
+            } else {
+                text.push(' ');
+            }
+            text.push_str(san);
+        }
+        text
+    }
+    /// Length in full moves.
+    pub fn move_count(&self) -> usize {
+        self.moves.len().div_ceil(2)
+    }
+
+    fn to_line(&self) -> String {
+        let colour = match self.human_colour {
+            Some(Colour::White) => "w",
+            Some(Colour::Black) => "b",
+            None => "-",
+        };
+        [
+            self.timestamp.to_string(),
+            self.result.label().to_string(),
+            colour.to_string(),
+            self.engine_side.clone(),
+            self.engine_depth.to_string(),
+            self.termination.clone(),
+            self.start_fen.clone().unwrap_or("-".to_string()),
+            self.moves.join(" "),
+        ]
+        .join("\t")
+    }
+    fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 8 {
+            return None;
+        }
+        Some(Self {
+            timestamp: fields[0].parse().ok()?,
+            result: GameResult::parse(fields[1])?,
+            human_colour: match fields[2] {
+                "w" => Some(Colour::White),
+                "b" => Some(Colour::Black),
+                _ => None,
+            },
+            engine_side: fields[3].to_string(),
+            engine_depth: fields[4].parse().ok()?,
+            termination: fields[5].to_string(),
+            start_fen: (fields[6] != "-").then(|| fields[6].to_string()),
+            moves: fields[7].split_whitespace().map(|s| s.to_string()).collect(),
+        })
+    }
+}
+
+/// Loads every recorded game, oldest first. Unreadable lines are skipped.
+pub fn load_games() -> Vec<GameRecord> {
+    storage::load(GAMES_FILE)
+        .map(|text| text.lines().filter_map(GameRecord::from_line).collect())
+        .unwrap_or_default()
+}
+
+pub fn save_games(games: &[GameRecord]) {
+    let text: Vec<String> = games.iter().map(|g| g.to_line()).collect();
+    storage::save(GAMES_FILE, &text.join("\n"));
+}
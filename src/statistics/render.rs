@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use chess_lib::Colour;
+use egui::{Color32, Grid, Pos2, RichText, Sense, Stroke, Ui, Vec2};
+
+use crate::statistics::{
+    record::{GameRecord, GameResult, Outcome},
+    StatisticsTab,
+};
+
+const WIN_COLOUR: Color32 = Color32::from_rgb(90, 170, 90);
+const DRAW_COLOUR: Color32 = Color32::from_rgb(150, 150, 150);
+const LOSS_COLOUR: Color32 = Color32::from_rgb(200, 80, 80);
+
+/// Win/draw/loss tally from the player's side.
+#[derive(Default, Clone, Copy)]
+struct Tally
+{
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+impl Tally
+{
+    fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Draw => self.draws += 1,
+            Outcome::Loss => self.losses += 1,
+        }
+    }
+    fn total(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+    /// Points scored as a percentage, counting draws as half.
+    fn score(&self) -> f32 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        (self.wins as f32 + self.draws as f32 * 0.5) / self.total() as f32 * 100.0
+    }
+}
+
+impl StatisticsTab
+{
+    pub fn render_dashboard(&mut self, ui: &mut Ui) {
+        ui.heading("Statistics");
+        ui.add_space(8.0);
+
+        if self.games.is_empty() {
+            ui.label("No finished games yet. Games you finish in the Play tab are recorded here.");
+            return;
+        }
+
+        let vs_engine: Vec<&GameRecord> = self.games.iter().filter(|g| g.outcome().is_some()).collect();
+        ui.label(format!(
+            "{} games recorded, {} against the engine",
+            self.games.len(),
+            vs_engine.len()
+        ));
+        ui.add_space(12.0);
+
+        ui.columns(2, |cols| {
+            render_colour_table(&mut cols[0], &vs_engine);
+            render_depth_table(&mut cols[1], &vs_engine);
+        });
+        ui.add_space(16.0);
+
+        ui.heading("Results over time");
+        ui.horizontal(|ui| {
+            ui.label("Last");
+            ui.add(egui::DragValue::new(&mut self.chart_games).range(5..=500));
+            ui.label("games against the engine");
+        });
+        let start = vs_engine.len().saturating_sub(self.chart_games);
+        render_results_chart(ui, &vs_engine[start..]);
+        ui.add_space(16.0);
+
+        ui.columns(2, |cols| {
+            render_openings(&mut cols[0], &self.games);
+            render_lengths(&mut cols[1], &self.games);
+        });
+        ui.add_space(16.0);
+
+        if self.confirm_clear {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Delete every recorded game?").color(LOSS_COLOUR));
+                if ui.button("Delete").clicked() {
+                    self.clear();
+                    self.confirm_clear = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_clear = false;
+                }
+            });
+        } else if ui.button("Clear history").clicked() {
+            self.confirm_clear = true;
+        }
+    }
+}
+
+fn tally_row(ui: &mut Ui, label: &str, tally: Tally) {
+    ui.label(label);
+    ui.label(RichText::new(tally.wins.to_string()).color(WIN_COLOUR));
+    ui.label(RichText::new(tally.draws.to_string()).color(DRAW_COLOUR));
+    ui.label(RichText::new(tally.losses.to_string()).color(LOSS_COLOUR));
+    ui.label(format!("{:.0}%", tally.score()));
+    ui.end_row();
+}
+
+fn tally_header(ui: &mut Ui, first: &str) {
+    for heading in [first, "Won", "Drawn", "Lost", "Score"] {
+        ui.label(RichText::new(heading).strong());
+    }
+    ui.end_row();
+}
+
+fn render_colour_table(ui: &mut Ui, games: &[&GameRecord]) {
+    ui.label(RichText::new("By colour").strong());
+    let mut white = Tally::default();
+    let mut black = Tally::default();
+    for g in games {
+        let (Some(colour), Some(outcome)) = (g.human_colour, g.outcome()) else {
+            continue;
+        };
+        match colour {
+            Colour::White => white.add(outcome),
+            Colour::Black => black.add(outcome),
+        }
+    }
+    let mut total = white;
+    total.wins += black.wins;
+    total.draws += black.draws;
+    total.losses += black.losses;
+
+    Grid::new("stats_by_colour").striped(true).show(ui, |ui| {
+        tally_header(ui, "Colour");
+        tally_row(ui, "White", white);
+        tally_row(ui, "Black", black);
+        tally_row(ui, "Total", total);
+    });
+}
+
+fn render_depth_table(ui: &mut Ui, games: &[&GameRecord]) {
+    ui.label(RichText::new("By engine depth").strong());
+    let mut by_depth: Vec<(u8, Tally)> = Vec::new();
+    for g in games {
+        let Some(outcome) = g.outcome() else {
+            continue;
+        };
+        match by_depth.iter_mut().find(|(d, _)| *d == g.engine_depth) {
+            Some((_, tally)) => tally.add(outcome),
+            None => {
+                let mut tally = Tally::default();
+                tally.add(outcome);
+                by_depth.push((g.engine_depth, tally));
+            }
+        }
+    }
+    by_depth.sort_by_key(|(d, _)| *d);
+
+    Grid::new("stats_by_depth").striped(true).show(ui, |ui| {
+        tally_header(ui, "Depth");
+        for (depth, tally) in by_depth {
+            tally_row(ui, &depth.to_string(), tally);
+        }
+    });
+}
+
+/// One bar per game coloured by the result, with the running score drawn over it.
+fn render_results_chart(ui: &mut Ui, games: &[&GameRecord]) {
+    if games.is_empty() {
+        ui.label("No games against the engine yet.");
+        return;
+    }
+    let width = ui.available_width().min(800.0);
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, 140.0), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+
+    let bar_width = rect.width() / games.len() as f32;
+    let mut points = Vec::with_capacity(games.len());
+    let mut tally = Tally::default();
+    for (i, g) in games.iter().enumerate() {
+        let Some(outcome) = g.outcome() else {
+            continue;
+        };
+        tally.add(outcome);
+        let (height, colour) = match outcome {
+            Outcome::Win => (1.0, WIN_COLOUR),
+            Outcome::Draw => (0.5, DRAW_COLOUR),
+            Outcome::Loss => (0.25, LOSS_COLOUR),
+        };
+        let x = rect.left() + bar_width * i as f32;
+        let bar = egui::Rect::from_min_max(
+            Pos2::new(x + 1.0, rect.bottom() - rect.height() * height * 0.5),
+            Pos2::new(x + bar_width - 1.0, rect.bottom()),
+        );
+        painter.rect_filled(bar, 0.0, colour.gamma_multiply(0.6));
+
+        let y = rect.bottom() - rect.height() * tally.score() / 100.0;
+        points.push(Pos2::new(x + bar_width / 2.0, y));
+    }
+    let middle = rect.center().y;
+    painter.line_segment(
+        [Pos2::new(rect.left(), middle), Pos2::new(rect.right(), middle)],
+        Stroke::new(1.0, ui.visuals().weak_text_color()),
+    );
+    painter.add(egui::Shape::line(points, Stroke::new(2.0, ui.visuals().strong_text_color())));
+
+    ui.label(
+        RichText::new(format!("Running score {:.0}% (line), 50% marked", tally.score())).weak(),
+    );
+}
+
+fn render_openings(ui: &mut Ui, games: &[GameRecord]) {
+    ui.label(RichText::new("Most played openings").strong());
+    let mut counts: HashMap<String, (usize, [usize; 3])> = HashMap::new();
+    for g in games.iter().filter(|g| g.start_fen.is_none() && !g.moves.is_empty()) {
+        let entry = counts.entry(g.opening()).or_default();
+        entry.0 += 1;
+        match g.result {
+            GameResult::WhiteWins => entry.1[0] += 1,
+            GameResult::Draw => entry.1[1] += 1,
+            GameResult::BlackWins => entry.1[2] += 1,
+        }
+    }
+    let mut openings: Vec<(String, (usize, [usize; 3]))> = counts.into_iter().collect();
+    openings.sort_by(|a, b| b.1.0.cmp(&a.1.0).then_with(|| a.0.cmp(&b.0)));
+
+    Grid::new("stats_openings").striped(true).show(ui, |ui| {
+        for heading in ["Opening", "Games", "1-0", "½-½", "0-1"] {
+            ui.label(RichText::new(heading).strong());
+        }
+        ui.end_row();
+        for (opening, (count, results)) in openings.iter().take(10) {
+            ui.label(opening);
+            ui.label(count.to_string());
+            for r in results {
+                ui.label(r.to_string());
+            }
+            ui.end_row();
+        }
+    });
+}
+
+fn render_lengths(ui: &mut Ui, games: &[GameRecord]) {
+    ui.label(RichText::new("Average game length").strong());
+    let average = |filter: &dyn Fn(&GameRecord) -> bool| {
+        let lengths: Vec<usize> = games.iter().filter(|g| filter(g)).map(|g| g.move_count()).collect();
+        if lengths.is_empty() {
+            return "-".to_string();
+        }
+        format!("{:.1} moves", lengths.iter().sum::<usize>() as f32 / lengths.len() as f32)
+    };
+
+    Grid::new("stats_lengths").striped(true).show(ui, |ui| {
+        ui.label("All games");
+        ui.label(average(&|_| true));
+        ui.end_row();
+        ui.label("Wins");
+        ui.label(average(&|g| g.outcome() == Some(Outcome::Win)));
+        ui.end_row();
+        ui.label("Draws");
+        ui.label(average(&|g| g.result == GameResult::Draw));
+        ui.end_row();
+        ui.label("Losses");
+        ui.label(average(&|g| g.outcome() == Some(Outcome::Loss)));
+        ui.end_row();
+        ui.label("Ended in checkmate");
+        ui.label(average(&|g| g.termination == "Checkmate"));
+        ui.end_row();
+    });
+}
@@ -0,0 +1,50 @@
+// Small key/value persistence for settings and history.
+// Native builds write one file per key into the user's data directory,
+// the web build uses the browser's localStorage.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> Option<std::path::PathBuf> {
+    use std::{env, path::PathBuf};
+
+    let base = env::var_os("APPDATA")
+        .or_else(|| env::var_os("XDG_DATA_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))?;
+    Some(base.join("egui_chess"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    let path = data_dir()?.join(key);
+    std::fs::read_to_string(path).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(key: &str, value: &str) {
+    let Some(dir) = data_dir() else {
+        eprintln!("No data directory to save {} in", key);
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(dir.join(key), value)) {
+        eprintln!("Failed to save {}: {}", key, e);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(key: &str, value: &str) {
+    if let Some(storage) = local_storage() {
+        if storage.set_item(key, value).is_err() {
+            log::error!("Failed to save {}", key);
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::VecDeque;
+
+use chess_lib::{Board, Colour, GameState, MoveList, Tile};
+use egui::{Context, TextureHandle};
+use instant::Instant;
+use rand::{rngs::ThreadRng, Rng};
+
+use crate::{attacks::attacked, notation::tile_name, play::PlayTab, storage, utils::unix_time};
+
+mod render;
+
+const HISTORY_FILE: &str = "training.tsv";
+
+/// Session lengths offered, in seconds.
+pub const DURATIONS: [u32; 4] = [30, 60, 120, 300];
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Exercise
+{
+    FindSquare,
+    NameSquare,
+    KnightRoute,
+    Attacked,
+}
+impl Exercise
+{
+    pub const ALL: [Exercise; 4] = [Exercise::FindSquare, Exercise::NameSquare, Exercise::KnightRoute, Exercise::Attacked];
+
+    pub fn label(&self) -> &str {
+        match self {
+            Exercise::FindSquare => "Find the square",
+            Exercise::NameSquare => "Name the square",
+            Exercise::KnightRoute => "Knight routes",
+            Exercise::Attacked => "Is it attacked?",
+        }
+    }
+    pub fn description(&self) -> &str {
+        match self {
+            Exercise::FindSquare => "Click the square that is named",
+            Exercise::NameSquare => "Type the name of the highlighted square",
+            Exercise::KnightRoute => "Hop the knight to the target in as few moves as possible",
+            Exercise::Attacked => "Say whether the side named attacks the highlighted square",
+        }
+    }
+    /// Stable name used in the saved history.
+    fn id(&self) -> &str {
+        match self {
+            Exercise::FindSquare => "find_square",
+            Exercise::NameSquare => "name_square",
+            Exercise::KnightRoute => "knight_route",
+            Exercise::Attacked => "attacked",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Question
+{
+    Find(Tile),
+    Name(Tile),
+    /// Squares hopped through so far, starting on `from`.
+    Knight { from: Tile, to: Tile, path: Vec<Tile>, shortest: usize },
+    Attacked { board: Board, tile: Tile, by: Colour, answer: bool },
+}
+
+pub struct Session
+{
+    pub exercise: Exercise,
+    pub started: Instant,
+    pub seconds: u32,
+    pub correct: u32,
+    pub wrong: u32,
+    pub question: Question,
+}
+impl Session
+{
+    pub fn remaining(&self) -> f32 {
+        (self.seconds as f32 - self.started.elapsed().as_secs_f32()).max(0.0)
+    }
+}
+
+/// A finished session, as kept in the training history.
+#[derive(Debug, Clone)]
+pub struct SessionRecord
+{
+    /// Seconds since the Unix epoch when the session ended.
+    pub timestamp: u64,
+    pub exercise: Exercise,
+    pub seconds: u32,
+    pub correct: u32,
+    pub wrong: u32,
+}
+impl SessionRecord
+{
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}", self.timestamp, self.exercise.id(), self.seconds, self.correct, self.wrong)
+    }
+    fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 5 {
+            return None;
+        }
+        Some(Self {
+            timestamp: fields[0].parse().ok()?,
+            exercise: *Exercise::ALL.iter().find(|e| e.id() == fields[1])?,
+            seconds: fields[2].parse().ok()?,
+            correct: fields[3].parse().ok()?,
+            wrong: fields[4].parse().ok()?,
+        })
+    }
+}
+
+pub struct TrainingTab
+{
+    /// Lends its board drawing and square mapping, flipping included.
+    pub play: PlayTab,
+    empty: Board,
+    rand: ThreadRng,
+
+    pub exercise: Exercise,
+    pub seconds: u32,
+    /// Picks White or Black at the bottom afresh for every question.
+    pub random_side: bool,
+
+    pub session: Option<Session>,
+    pub answer_text: String,
+    /// Whether the last answer was right, and what it should have been.
+    pub feedback: Option<(bool, String)>,
+    /// The square last clicked or asked about, marked right or wrong.
+    pub marked: Option<(Tile, bool)>,
+    pub history: Vec<SessionRecord>,
+    /// The last session's record and whether it beat the best before it,
+    /// shown until the next one starts.
+    pub last: Option<(SessionRecord, bool)>,
+    confirm_clear: bool,
+}
+
+impl TrainingTab
+{
+    pub fn new(atlas: TextureHandle) -> Self {
+        let mut play = PlayTab::new(None, atlas);
+        play.embedded = true;
+        play.input_locked = true;
+
+        Self {
+            play,
+            empty: Board::new_empty(),
+            rand: rand::rng(),
+
+            exercise: Exercise::FindSquare,
+            seconds: 60,
+            random_side: false,
+
+            session: None,
+            answer_text: String::new(),
+            feedback: None,
+            marked: None,
+            history: load_history(),
+            last: None,
+            confirm_clear: false,
+        }
+    }
+    pub fn start(&mut self) {
+        let question = self.new_question(self.exercise);
+        self.session = Some(Session {
+            exercise: self.exercise,
+            started: Instant::now(),
+            seconds: self.seconds,
+            correct: 0,
+            wrong: 0,
+            question,
+        });
+        self.answer_text.clear();
+        self.feedback = None;
+        self.marked = None;
+        self.last = None;
+    }
+    /// Ends the session early or on time. Only sessions that ran their full
+    /// length go into the history, so scores stay comparable.
+    pub fn stop(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        if session.remaining() > 0.0 {
+            self.feedback = Some((false, "Session stopped".to_string()));
+            return;
+        }
+        let record = SessionRecord {
+            timestamp: unix_time(),
+            exercise: session.exercise,
+            seconds: session.seconds,
+            correct: session.correct,
+            wrong: session.wrong,
+        };
+        let new_best = self.best(record.exercise, record.seconds).is_none_or(|best| record.correct > best);
+        self.history.push(record.clone());
+        self.last = Some((record, new_best));
+        save_history(&self.history);
+    }
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        save_history(&self.history);
+    }
+    /// The best score among finished sessions of `exercise` at the chosen length.
+    pub fn best(&self, exercise: Exercise, seconds: u32) -> Option<u32> {
+        self.history
+            .iter()
+            .filter(|r| r.exercise == exercise && r.seconds == seconds)
+            .map(|r| r.correct)
+            .max()
+    }
+
+    fn random_tile(&mut self) -> Tile {
+        let (x, y) = (self.rand.random_range(0..8), self.rand.random_range(0..8));
+        Tile::new_xy(x, y).expect("coordinates are on the board")
+    }
+    fn new_question(&mut self, exercise: Exercise) -> Question {
+        if self.random_side {
+            self.play.flipped = self.rand.random_bool(0.5);
+        }
+        match exercise {
+            Exercise::FindSquare => Question::Find(self.random_tile()),
+            Exercise::NameSquare => Question::Name(self.random_tile()),
+            Exercise::KnightRoute => {
+                let from = self.random_tile();
+                let mut to = self.random_tile();
+                while to == from {
+                    to = self.random_tile();
+                }
+                Question::Knight { from, to, path: vec![from], shortest: knight_distance(from, to) }
+            }
+            Exercise::Attacked => {
+                let board = random_position(&mut self.rand);
+                let by = if self.rand.random_bool(0.5) { Colour::White } else { Colour::Black };
+                // Ask about attacked and unattacked squares about equally often
+                let want = self.rand.random_bool(0.5);
+                let mut tile = self.random_tile();
+                for _ in 0..32 {
+                    if attacked(&board, tile, by) == want {
+                        break;
+                    }
+                    tile = self.random_tile();
+                }
+                let answer = attacked(&board, tile, by);
+                Question::Attacked { board, tile, by, answer }
+            }
+        }
+    }
+    /// Scores an answer and moves on to the next question.
+    fn answer(&mut self, right: bool, feedback: String) {
+        let Some(exercise) = self.session.as_ref().map(|s| s.exercise) else {
+            return;
+        };
+        let question = self.new_question(exercise);
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if right {
+            session.correct += 1;
+        } else {
+            session.wrong += 1;
+        }
+        session.question = question;
+        self.feedback = Some((right, feedback));
+        self.answer_text.clear();
+    }
+    /// A click on `tile` during a Find or Knight question.
+    pub fn click(&mut self, tile: Tile) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        match &mut session.question {
+            Question::Find(target) => {
+                let target = *target;
+                self.marked = Some((tile, tile == target));
+                if tile == target {
+                    self.answer(true, format!("{} it is", tile_name(target)));
+                } else {
+                    self.answer(false, format!("That was {}, not {}", tile_name(tile), tile_name(target)));
+                }
+            }
+            Question::Knight { to, path, shortest, .. } => {
+                let (to, shortest) = (*to, *shortest);
+                let last = *path.last().expect("a route starts on its first square");
+                if path.len() > 1 && tile == last {
+                    // Clicking the knight steps back a hop
+                    path.pop();
+                    return;
+                }
+                if !knight_hop(last, tile) {
+                    self.feedback = Some((false, format!("A knight can't go from {} to {}", tile_name(last), tile_name(tile))));
+                    return;
+                }
+                path.push(tile);
+                let hops = path.len() - 1;
+                if tile == to {
+                    self.marked = Some((tile, hops == shortest));
+                    if hops == shortest {
+                        self.answer(true, format!("{} in {}", tile_name(to), hops));
+                    } else {
+                        self.answer(false, format!("{} hops, but {} would do", hops, shortest));
+                    }
+                } else if hops >= shortest {
+                    self.marked = Some((tile, false));
+                    self.answer(false, format!("{} can be reached in {} hops", tile_name(to), shortest));
+                }
+            }
+            Question::Name(_) | Question::Attacked { .. } => (),
+        }
+    }
+    /// The typed square name for a Name question.
+    pub fn submit_name(&mut self) {
+        let Some(Question::Name(target)) = self.session.as_ref().map(|s| &s.question) else {
+            return;
+        };
+        let target = *target;
+        let text = self.answer_text.trim().to_ascii_lowercase();
+        if text.is_empty() {
+            return;
+        }
+        self.marked = None;
+        if text == tile_name(target) {
+            self.answer(true, format!("{} it is", tile_name(target)));
+        } else {
+            self.answer(false, format!("That was {}, not {}", tile_name(target), text));
+        }
+    }
+    /// A yes or no for an Attacked question.
+    pub fn submit_attacked(&mut self, yes: bool) {
+        let Some(Question::Attacked { tile, by, answer, .. }) = self.session.as_ref().map(|s| &s.question) else {
+            return;
+        };
+        let (tile, by, answer) = (*tile, *by, *answer);
+        let side = if by.white() { "White" } else { "Black" };
+        let verdict = if answer { "attacks" } else { "doesn't attack" };
+        self.marked = None;
+        self.answer(yes == answer, format!("{} {} {}", side, verdict, tile_name(tile)));
+    }
+    /// The board a question is asked on.
+    pub fn question_board(&self) -> &Board {
+        match self.session.as_ref().map(|s| &s.question) {
+            Some(Question::Attacked { board, .. }) => board,
+            _ => &self.empty,
+        }
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        if self.session.as_ref().is_some_and(|s| s.remaining() <= 0.0) {
+            self.stop();
+        }
+        egui::SidePanel::right("training_panel")
+            .resizable(false)
+            .min_width(260.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("scroll_training")
+                    .show(ui, |ui| {
+                        self.render_panel(ui);
+                    });
+            });
+        self.render_board(ctx);
+        if self.session.is_some() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+fn knight_hop(from: Tile, to: Tile) -> bool {
+    let (fx, fy) = from.get_coords();
+    let (tx, ty) = to.get_coords();
+    let (dx, dy) = (fx.abs_diff(tx), fy.abs_diff(ty));
+    (dx == 1 && dy == 2) || (dx == 2 && dy == 1)
+}
+
+/// Fewest knight hops from `from` to `to`, by breadth-first search.
+fn knight_distance(from: Tile, to: Tile) -> usize {
+    let index = |t: Tile| {
+        let (x, y) = t.get_coords();
+        (y * 8 + x) as usize
+    };
+    let mut distance = [usize::MAX; 64];
+    distance[index(from)] = 0;
+    let mut queue = VecDeque::from([from]);
+    while let Some(tile) = queue.pop_front() {
+        if tile == to {
+            break;
+        }
+        let (x, y) = tile.get_coords();
+        for (dx, dy) in [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)] {
+            let next = Tile::new_xy((x as i8 + dx) as u8, (y as i8 + dy) as u8);
+            if let Some(next) = next.filter(|n| distance[index(*n)] == usize::MAX) {
+                distance[index(next)] = distance[index(tile)] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    distance[index(to)]
+}
+
+/// A middlegame-ish position reached by random moves from the start.
+fn random_position(rand: &mut ThreadRng) -> Board {
+    loop {
+        let mut board = Board::new();
+        for _ in 0..rand.random_range(10..40) {
+            let mut moves = MoveList::new();
+            board.generate_legal_moves(board.turn, &mut moves);
+            if moves.is_empty() {
+                break;
+            }
+            board.make_move_unchecked(moves[rand.random_range(0..moves.len())]);
+        }
+        if board.get_state() == GameState::Playing {
+            return board;
+        }
+    }
+}
+
+fn load_history() -> Vec<SessionRecord> {
+    let Some(text) = storage::load(HISTORY_FILE) else {
+        return Vec::new();
+    };
+    text.lines().filter_map(SessionRecord::from_line).collect()
+}
+
+fn save_history(history: &[SessionRecord]) {
+    let lines: Vec<String> = history.iter().map(|r| r.to_line()).collect();
+    storage::save(HISTORY_FILE, &lines.join("\n"));
+}
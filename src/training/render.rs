@@ -0,0 +1,231 @@
+use chess_lib::{Colour, Piece, Tile};
+use egui::{Color32, ComboBox, Context, Grid, Key, Painter, Pos2, RichText, Sense, Stroke, Ui, Vec2};
+
+use crate::{
+    notation::tile_name,
+    training::{Exercise, Question, TrainingTab, DURATIONS},
+    utils::pgn_date,
+};
+
+const RIGHT_COLOUR: Color32 = Color32::from_rgb(90, 170, 90);
+const WRONG_COLOUR: Color32 = Color32::from_rgb(200, 80, 80);
+const ASKED: Color32 = Color32::from_rgba_premultiplied(150, 130, 0, 140);
+
+/// Sessions listed in the recent history.
+const RECENT: usize = 15;
+
+impl TrainingTab
+{
+    pub fn render_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Training");
+        ui.add_space(8.0);
+
+        let running = self.session.is_some();
+        ui.add_enabled_ui(!running, |ui| {
+            ComboBox::from_id_salt("training_exercise")
+                .selected_text(self.exercise.label())
+                .show_ui(ui, |ui| {
+                    for e in Exercise::ALL {
+                        ui.selectable_value(&mut self.exercise, e, e.label());
+                    }
+                });
+            ui.label(RichText::new(self.exercise.description()).weak());
+            ui.horizontal(|ui| {
+                ui.label("Length");
+                ComboBox::from_id_salt("training_seconds")
+                    .selected_text(format!("{} s", self.seconds))
+                    .show_ui(ui, |ui| {
+                        for s in DURATIONS {
+                            ui.selectable_value(&mut self.seconds, s, format!("{} s", s));
+                        }
+                    });
+            });
+        });
+        ui.add_enabled(!self.random_side, egui::Checkbox::new(&mut self.play.flipped, "Black at the bottom"));
+        ui.checkbox(&mut self.random_side, "Random side each question");
+        ui.add_space(8.0);
+
+        if running {
+            if ui.button("Stop").on_hover_text("Stopped sessions aren't recorded").clicked() {
+                self.stop();
+            }
+        } else if ui.button("Start").clicked() {
+            self.start();
+        }
+        if let Some(session) = &self.session {
+            ui.label(RichText::new(format!("Time left: {:.0} s", session.remaining().ceil())).strong());
+            ui.label(format!("Correct {}, wrong {}", session.correct, session.wrong));
+        }
+        if let Some((right, text)) = &self.feedback {
+            ui.label(RichText::new(text).color(if *right { RIGHT_COLOUR } else { WRONG_COLOUR }));
+        }
+        if let Some((last, new_best)) = &self.last {
+            let note = if *new_best { ", a new best" } else { "" };
+            ui.label(RichText::new(format!("Time! {} correct, {} wrong{}", last.correct, last.wrong, note)).strong());
+        }
+        ui.separator();
+
+        self.render_history(ui);
+    }
+
+    fn render_history(&mut self, ui: &mut Ui) {
+        ui.label(RichText::new(format!("Best scores over {} s", self.seconds)).strong());
+        Grid::new("training_best").striped(true).show(ui, |ui| {
+            for e in Exercise::ALL {
+                ui.label(e.label());
+                ui.label(self.best(e, self.seconds).map(|b| b.to_string()).unwrap_or("-".to_string()));
+                ui.end_row();
+            }
+        });
+        ui.add_space(8.0);
+
+        if self.history.is_empty() {
+            ui.label("No finished sessions yet");
+            return;
+        }
+        ui.label(RichText::new("Recent sessions").strong());
+        Grid::new("training_history").striped(true).show(ui, |ui| {
+            for record in self.history.iter().rev().take(RECENT) {
+                ui.label(pgn_date(record.timestamp));
+                ui.label(record.exercise.label());
+                ui.label(format!("{} s", record.seconds));
+                ui.label(RichText::new(record.correct.to_string()).color(RIGHT_COLOUR));
+                ui.label(RichText::new(record.wrong.to_string()).color(WRONG_COLOUR));
+                ui.end_row();
+            }
+        });
+        ui.add_space(8.0);
+
+        if self.confirm_clear {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Delete the training history?").color(WRONG_COLOUR));
+                if ui.button("Delete").clicked() {
+                    self.clear_history();
+                    self.confirm_clear = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_clear = false;
+                }
+            });
+        } else if ui.button("Clear history").clicked() {
+            self.confirm_clear = true;
+        }
+    }
+
+    fn fill_tile(&self, painter: &Painter, origin: Pos2, tile: Tile, colour: Color32) {
+        let (x, y) = tile.get_coords();
+        painter.rect_filled(self.play.tile_to_screen(x as f32, y as f32, origin), 0.0, colour);
+    }
+    fn render_question(&self, painter: &Painter, origin: Pos2) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let square = self.play.board_size / 8.0;
+        match &session.question {
+            Question::Find(_) => (),
+            Question::Name(tile) | Question::Attacked { tile, .. } => self.fill_tile(painter, origin, *tile, ASKED),
+            Question::Knight { to, path, .. } => {
+                let centre = |t: Tile| {
+                    let (x, y) = t.get_coords();
+                    self.play.tile_to_screen(x as f32, y as f32, origin).center()
+                };
+                painter.circle_stroke(centre(*to), square * 0.4, Stroke::new(square * 0.08, ASKED));
+                for pair in path.windows(2) {
+                    painter.line_segment([centre(pair[0]), centre(pair[1])], Stroke::new(square * 0.05, ASKED));
+                }
+                if let Some(&knight) = path.last() {
+                    let (x, y) = knight.get_coords();
+                    let rect = self.play.tile_to_screen(x as f32, y as f32, origin);
+                    painter.image(self.play.atlas.id(), rect, self.play.atlas_uv(&Piece::Knight, Colour::White), Color32::WHITE);
+                }
+            }
+        }
+        if let Some((tile, right)) = self.marked {
+            let colour = if right { RIGHT_COLOUR } else { WRONG_COLOUR };
+            self.fill_tile(painter, origin, tile, colour.gamma_multiply(0.6));
+        }
+    }
+    /// The prompt under the board, with the answer box or buttons it needs.
+    fn render_prompt(&mut self, ui: &mut Ui) {
+        let Some(session) = &self.session else {
+            ui.label("Pick an exercise and press Start");
+            return;
+        };
+        let size = (self.play.board_size * 0.04).max(16.0);
+        match &session.question {
+            Question::Find(tile) => {
+                ui.label(RichText::new(format!("Find {}", tile_name(*tile))).size(size).strong());
+            }
+            Question::Name(_) => {
+                ui.label(RichText::new("Name the highlighted square").size(size).strong());
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.answer_text)
+                        .hint_text("e4")
+                        .desired_width(80.0),
+                );
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    self.submit_name();
+                }
+                // Keep the box focused so answers can be typed back to back
+                response.request_focus();
+            }
+            Question::Knight { from, to, path, shortest } => {
+                let text = format!("Knight from {} to {}", tile_name(*from), tile_name(*to));
+                ui.label(RichText::new(text).size(size).strong());
+                ui.label(format!("Hops {} of {}. Click the knight to step back", path.len() - 1, shortest));
+            }
+            Question::Attacked { tile, by, .. } => {
+                let side = if by.white() { "White" } else { "Black" };
+                let text = format!("Does {} attack {}?", side, tile_name(*tile));
+                ui.label(RichText::new(text).size(size).strong());
+                let (yes, no) = ui.input(|i| (i.key_pressed(Key::Y), i.key_pressed(Key::N)));
+                let (yes, no) = ui
+                    .horizontal(|ui| (ui.button("Yes (Y)").clicked() || yes, ui.button("No (N)").clicked() || no))
+                    .inner;
+                if yes {
+                    self.submit_attacked(true);
+                } else if no {
+                    self.submit_attacked(false);
+                }
+            }
+        }
+    }
+    pub fn render_board(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let available_size = ui.available_size();
+            self.play.board_size = available_size.x.min(available_size.y) * 0.8;
+
+            let horizontal_margin = (available_size.x - self.play.board_size) / 2.0;
+            let vertical_margin = (available_size.y - self.play.board_size) / 2.0;
+            if vertical_margin > 0.0 {
+                ui.add_space(vertical_margin);
+            }
+
+            ui.horizontal(|ui| {
+                if horizontal_margin > 0.0 {
+                    ui.add_space(horizontal_margin);
+                }
+                let (response, painter) = ui.allocate_painter(Vec2::splat(self.play.board_size), Sense::click());
+                let origin = response.rect.min;
+
+                let board = self.question_board();
+                self.play.render_tiles(&painter, origin, board);
+                self.play.render_pieces(&painter, origin, board, None);
+                self.render_question(&painter, origin);
+
+                let clicked = response.clicked().then(|| response.interact_pointer_pos()).flatten();
+                if let Some(pos) = clicked {
+                    let (x, y) = self.play.screen_to_tile(pos, origin);
+                    if let Some(tile) = Tile::new_xy(x as u8, y as u8) {
+                        self.click(tile);
+                    }
+                }
+            });
+
+            ui.add_space(12.0);
+            ui.vertical_centered(|ui| {
+                self.render_prompt(ui);
+            });
+        });
+    }
+}
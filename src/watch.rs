@@ -0,0 +1,420 @@
+use std::time::Duration;
+
+use chess_lib::{Board, Colour, GameState, Move};
+use egui::{Context, TextureHandle};
+use instant::Instant;
+
+use crate::{
+    analysis::{material_balance, LineSearch},
+    background::Background,
+    net::{
+        client::{Connection, NetEvent},
+        protocol::{ClientMsg, ServerMsg, DEFAULT_PORT},
+    },
+    notation, pgn,
+    play::{state::PlayState, PlayTab},
+};
+
+mod render;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const FILE_POLL: Duration = Duration::from_secs(1);
+/// How far the engine line behind the evaluation is played out.
+const EVAL_PLIES: usize = 6;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum WatchSource
+{
+    /// A room on the relay server, followed as a spectator.
+    Live,
+    /// A PGN file another program keeps appending to.
+    Broadcast,
+}
+
+/// Where the engine's line from a position ends. chess_engine gives moves but
+/// no score, so this is what stands in for one.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Score
+{
+    /// White's material minus Black's, in centipawns, at the end of the line.
+    Material(i32),
+    /// Moves until the line mates, positive when White mates. Only mates the
+    /// line itself runs into are found.
+    Mate(i32),
+}
+
+#[derive(Debug, Clone)]
+pub struct Eval
+{
+    /// FEN of the position this was computed for.
+    pub fen: String,
+    pub best: Option<String>,
+    pub score: Score,
+}
+impl Eval
+{
+    /// Reads the evaluation off the engine's finished line from `board`.
+    fn from_line(fen: String, board: &Board, line: &[Move], end: &Board) -> Self {
+        let best = line.first().and_then(|&best| notation::legal_moves(board).into_iter().find(|m| m.mv == best));
+        let score = match end.get_state() {
+            GameState::Checkmate(loser) => {
+                let moves = line.len().div_ceil(2) as i32;
+                Score::Mate(if loser == Colour::White { -moves } else { moves })
+            }
+            GameState::Playing => Score::Material(material_balance(end)),
+            _ => Score::Material(0),
+        };
+        Self { fen, best: best.map(|m| m.san), score }
+    }
+}
+
+pub struct WatchTab
+{
+    pub play: PlayTab,
+    pub source: WatchSource,
+    connection: Option<Connection>,
+
+    pub host: String,
+    pub port: u16,
+    pub room: String,
+    pub pgn_path: String,
+    /// Length and modification time of the PGN file when it was last read.
+    #[cfg(not(target_arch = "wasm32"))]
+    file_stamp: Option<(u64, std::time::SystemTime)>,
+    next_poll: Option<Instant>,
+    /// Moves taken from the followed PGN game, as written there.
+    pgn_moves: Vec<String>,
+
+    pub names: [String; 2],
+    /// White's and Black's time in milliseconds as of `clock_time`, if known.
+    clocks: Option<[i64; 2]>,
+    clock_time: Instant,
+    /// The relay's clocks tick between moves, clocks read from PGN comments don't.
+    clocks_running: bool,
+    pub started: bool,
+    pub result: Option<String>,
+    pub status: String,
+    reconnect_at: Option<Instant>,
+
+    pub show_eval: bool,
+    pub eval_depth: u8,
+    pub eval: Option<Eval>,
+    /// The line being searched for the evaluation, with the FEN and depth it's for.
+    pending_eval: Option<((String, u8), Background<LineSearch>)>,
+}
+
+impl WatchTab
+{
+    pub fn new(atlas: TextureHandle) -> Self {
+        let mut play = PlayTab::new(None, atlas);
+        play.embedded = true;
+        play.input_locked = true;
+
+        Self {
+            play,
+            source: WatchSource::Live,
+            connection: None,
+
+            host: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            room: "lobby".to_string(),
+            pgn_path: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            file_stamp: None,
+            next_poll: None,
+            pgn_moves: Vec::new(),
+
+            names: [String::new(), String::new()],
+            clocks: None,
+            clock_time: Instant::now(),
+            clocks_running: false,
+            started: false,
+            result: None,
+            status: "Not watching".to_string(),
+            reconnect_at: None,
+
+            show_eval: true,
+            eval_depth: 3,
+            eval: None,
+            pending_eval: None,
+        }
+    }
+    pub fn following(&self) -> bool {
+        self.connection.is_some() || self.reconnect_at.is_some() || self.next_poll.is_some()
+    }
+    fn clear_game(&mut self) {
+        self.play.set_position(Board::new());
+        self.pgn_moves.clear();
+        self.names = [String::new(), String::new()];
+        self.clocks = None;
+        self.clocks_running = false;
+        self.started = false;
+        self.result = None;
+        self.eval = None;
+        self.pending_eval = None;
+    }
+    /// Joins the room as a spectator.
+    pub fn connect(&mut self) {
+        match Connection::connect(&self.host, self.port) {
+            Ok(mut connection) => {
+                connection.send(&ClientMsg::Watch { room: self.room.clone() });
+                self.connection = Some(connection);
+                self.reconnect_at = None;
+                self.status = "Waiting for the game to start".to_string();
+            }
+            Err(e) => {
+                self.status = e;
+                self.reconnect_at = Some(Instant::now() + RECONNECT_DELAY);
+            }
+        }
+    }
+    pub fn start(&mut self) {
+        self.stop();
+        self.clear_game();
+        match self.source {
+            WatchSource::Live => self.connect(),
+            WatchSource::Broadcast => {
+                self.status = format!("Following {}", self.pgn_path);
+                self.next_poll = Some(Instant::now());
+            }
+        }
+    }
+    pub fn stop(&mut self) {
+        self.connection = None;
+        self.reconnect_at = None;
+        self.next_poll = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.file_stamp = None;
+        }
+        self.status = "Not watching".to_string();
+    }
+    /// Time left for White and Black, counting down the side to move on a live game.
+    pub fn clocks(&self) -> Option<[i64; 2]> {
+        let mut clocks = self.clocks?;
+        if self.clocks_running && self.started && self.result.is_none() {
+            let turn = if self.play.board.turn.white() { 0 } else { 1 };
+            clocks[turn] -= self.clock_time.elapsed().as_millis() as i64;
+        }
+        Some(clocks.map(|c| c.max(0)))
+    }
+    fn set_clocks(&mut self, white_ms: i64, black_ms: i64) {
+        self.clocks = Some([white_ms, black_ms]);
+        self.clock_time = Instant::now();
+    }
+    /// Plays a move on the followed game without pulling the viewer off an earlier position.
+    fn apply_move(&mut self, text: &str) -> bool {
+        let Some(m) = notation::parse_move(&self.play.board, text) else {
+            return false;
+        };
+        let viewing = self.play.state;
+        self.play.play_move(m);
+        if let PlayState::Viewing(pos) = viewing {
+            self.play.state = PlayState::Viewing(pos);
+        } else {
+            self.play.view_board = self.play.board.clone();
+        }
+        true
+    }
+    fn handle_message(&mut self, msg: ServerMsg) {
+        match msg {
+            ServerMsg::Start { white, black, increment_ms: _ } => {
+                self.names = [white, black];
+                self.started = true;
+                self.clocks_running = true;
+                self.status = "Game in progress".to_string();
+            }
+            ServerMsg::Sync { white_ms, black_ms, moves } => {
+                self.play.set_position(Board::new());
+                for uci in &moves {
+                    if !self.apply_move(uci) {
+                        self.status = format!("The server sent an illegal move {}", uci);
+                        break;
+                    }
+                }
+                self.set_clocks(white_ms, black_ms);
+            }
+            ServerMsg::Move { uci, white_ms, black_ms } => {
+                self.set_clocks(white_ms, black_ms);
+                if !self.apply_move(&uci) {
+                    self.status = format!("Move {} doesn't fit our board, resyncing", uci);
+                    if let Some(connection) = &mut self.connection {
+                        connection.send(&ClientMsg::Sync);
+                    }
+                }
+            }
+            ServerMsg::GameOver { result, reason } => {
+                self.status = format!("Game over: {} ({})", result, reason.replace('_', " "));
+                self.result = Some(result);
+            }
+            ServerMsg::OpponentLeft => self.status = "A player disconnected".to_string(),
+            ServerMsg::OpponentBack => self.status = "Game in progress".to_string(),
+            ServerMsg::Error(text) => self.status = text,
+            ServerMsg::Welcome { .. } | ServerMsg::DrawOffered | ServerMsg::DrawDeclined => (),
+        }
+    }
+    fn update_live(&mut self) {
+        let events = self.connection.as_mut().map(|c| c.poll()).unwrap_or_default();
+        for event in events {
+            match event {
+                NetEvent::Message(msg) => self.handle_message(msg),
+                NetEvent::Closed(reason) => {
+                    self.connection = None;
+                    if self.result.is_none() {
+                        self.status = format!("{}, reconnecting", reason);
+                        self.reconnect_at = Some(Instant::now() + RECONNECT_DELAY);
+                    } else {
+                        self.status = reason;
+                    }
+                    break;
+                }
+            }
+        }
+        if self.reconnect_at.is_some_and(|t| Instant::now() >= t) {
+            self.connect();
+        }
+    }
+    /// Re-reads the PGN file when its size or modification time changes.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_file(&mut self) {
+        let stamp = std::fs::metadata(&self.pgn_path).and_then(|m| Ok((m.len(), m.modified()?)));
+        let stamp = match stamp {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                self.status = format!("Can't read {}: {}", self.pgn_path, e);
+                return;
+            }
+        };
+        if self.file_stamp == Some(stamp) {
+            return;
+        }
+        match std::fs::read_to_string(&self.pgn_path) {
+            Ok(text) => {
+                self.file_stamp = Some(stamp);
+                self.follow_pgn(&text);
+            }
+            Err(e) => self.status = format!("Can't read {}: {}", self.pgn_path, e),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn poll_file(&mut self) {
+        self.status = "Following a file needs the desktop app".to_string();
+        self.next_poll = None;
+    }
+    /// Catches up with the last game in `text`, starting over when it isn't a
+    /// continuation of the one being shown.
+    pub fn follow_pgn(&mut self, text: &str) {
+        let Some(game) = pgn::parse_games(text).pop() else {
+            return;
+        };
+        let continues = game.moves.starts_with(&self.pgn_moves) && !self.pgn_moves.is_empty();
+        if !continues {
+            let board = match game.tag("FEN").map(Board::new_from_fen) {
+                Some(Ok(board)) => board,
+                Some(Err(e)) => {
+                    self.status = format!("Bad FEN tag: {}", e);
+                    return;
+                }
+                None => Board::new(),
+            };
+            self.play.set_position(board);
+            self.pgn_moves.clear();
+        }
+        for san in &game.moves[self.pgn_moves.len()..] {
+            if !self.apply_move(san) {
+                self.status = format!("Stopped at illegal move {}", san);
+                break;
+            }
+            self.pgn_moves.push(san.clone());
+        }
+
+        let name = |tag: &str| game.tag(tag).filter(|n| *n != "?").unwrap_or_default().to_string();
+        self.names = [name("White"), name("Black")];
+        self.started = true;
+        self.result = game.tag("Result").filter(|r| *r != "*").map(|r| r.to_string());
+
+        // The latest clock comment for each side
+        let black_first = self.play.start_fen.as_deref().is_some_and(|f| f.contains(" b "));
+        let mut clocks = [None, None];
+        for (i, comment) in game.comments.iter().enumerate().take(self.pgn_moves.len()) {
+            if let Some(ms) = pgn::comment_clock(comment) {
+                clocks[(i + black_first as usize) % 2] = Some(ms);
+            }
+        }
+        self.clocks = match clocks {
+            [None, None] => None,
+            [white, black] => Some([white.unwrap_or(0), black.unwrap_or(0)]),
+        };
+        self.clocks_running = false;
+        if self.result.is_none() && self.next_poll.is_some() {
+            self.status = format!("Following {}", self.pgn_path);
+        }
+    }
+    /// The position on screen: the one being viewed, or the latest.
+    fn shown_board(&self) -> &Board {
+        if let PlayState::Viewing(_) = self.play.state { &self.play.view_board } else { &self.play.board }
+    }
+    /// Evaluates the shown position once, when it changes. The engine line
+    /// runs in the background, one at a time: a position left before its line
+    /// is done gets evaluated afresh once it finishes.
+    fn update_eval(&mut self, ctx: &Context) {
+        let board = self.shown_board().clone();
+        let fen = board.to_fen();
+        if let Some((key, search)) = &mut self.pending_eval {
+            let finished = search.poll(ctx).pop();
+            if finished.is_none() && !search.finished() {
+                return;
+            }
+            if let Some((line, end)) = finished.filter(|_| *key == (fen.clone(), self.eval_depth)) {
+                self.eval = Some(Eval::from_line(fen.clone(), &board, &line, &end));
+            }
+            self.pending_eval = None;
+        }
+        if self.eval.as_ref().is_some_and(|e| e.fen == fen) {
+            return;
+        }
+        if board.get_state() != GameState::Playing {
+            self.eval = None;
+            return;
+        }
+        let search = LineSearch::new(&board, None, self.eval_depth, EVAL_PLIES);
+        self.pending_eval = Some(((fen, self.eval_depth), Background::start(search)));
+    }
+    /// The evaluation of the position on screen, if it's been worked out.
+    pub fn shown_eval(&self) -> Option<&Eval> {
+        let fen = self.shown_board().to_fen();
+        self.eval.as_ref().filter(|e| e.fen == fen)
+    }
+    fn update(&mut self, ctx: &Context) {
+        match self.source {
+            WatchSource::Live => self.update_live(),
+            WatchSource::Broadcast => {
+                if self.next_poll.is_some_and(|t| Instant::now() >= t) {
+                    self.poll_file();
+                    self.next_poll = self.next_poll.map(|_| Instant::now() + FILE_POLL);
+                }
+            }
+        }
+        if self.show_eval {
+            self.update_eval(ctx);
+        }
+        if self.following() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+    pub fn render(&mut self, ctx: &Context) {
+        egui::SidePanel::right("watch_panel")
+            .resizable(false)
+            .min_width(280.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("scroll_watch")
+                    .show(ui, |ui| {
+                        self.render_panel(ui);
+                    });
+            });
+        self.play.sync_view_board();
+        self.play.render_board(ctx);
+        self.update(ctx);
+    }
+}
@@ -0,0 +1,183 @@
+use chess_lib::Colour;
+use egui::{Color32, DragValue, Grid, RichText, Sense, Slider, Ui, Vec2};
+#[cfg(not(target_arch = "wasm32"))]
+use rfd::FileDialog;
+
+use crate::watch::{Score, WatchSource, WatchTab, EVAL_PLIES};
+
+fn format_clock(ms: i64) -> String {
+    let secs = ms / 1000;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn score_label(score: Score) -> String {
+    match score {
+        Score::Material(cp) => format!("{:+.1} in material", cp as f32 / 100.0),
+        Score::Mate(n) if n > 0 => format!("White mates in {}", n),
+        Score::Mate(n) => format!("Black mates in {}", -n),
+    }
+}
+
+/// White's share of the evaluation bar.
+fn white_share(score: Score) -> f32 {
+    match score {
+        Score::Material(cp) => (0.5 + cp as f32 / 2000.0).clamp(0.05, 0.95),
+        Score::Mate(n) => if n > 0 { 1.0 } else { 0.0 },
+    }
+}
+
+impl WatchTab
+{
+    pub fn render_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Watch");
+        ui.add_space(8.0);
+
+        if self.following() {
+            if ui.button("Stop watching").clicked() {
+                self.stop();
+            }
+        } else {
+            self.render_source(ui);
+        }
+        ui.label(RichText::new(&self.status).weak());
+        ui.separator();
+
+        self.render_players(ui);
+        ui.separator();
+        self.render_eval(ui);
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("First position").clicked() {
+                self.play.view_start();
+            }
+            if ui.button("◀").on_hover_text("Previous move").clicked() {
+                self.play.view_previous();
+            }
+            if ui.button("▶").on_hover_text("Next move").clicked() {
+                self.play.view_next();
+            }
+            if ui.button("⏭").on_hover_text("Latest position").clicked() {
+                self.play.view_end();
+            }
+            if ui.button("Flip board").clicked() {
+                self.play.flip();
+            }
+        });
+        ui.add_space(8.0);
+        self.play.render_moves_list(ui, false);
+    }
+
+    fn render_source(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.source, WatchSource::Live, "Live room");
+            ui.radio_value(&mut self.source, WatchSource::Broadcast, "PGN file");
+        });
+        match self.source {
+            WatchSource::Live => {
+                Grid::new("watch_connect").num_columns(2).show(ui, |ui| {
+                    ui.label("Server");
+                    ui.text_edit_singleline(&mut self.host);
+                    ui.end_row();
+                    ui.label("Port");
+                    ui.add(DragValue::new(&mut self.port));
+                    ui.end_row();
+                    ui.label("Room");
+                    ui.text_edit_singleline(&mut self.room);
+                    ui.end_row();
+                });
+                if ui.button("Watch").clicked() {
+                    self.start();
+                }
+            }
+            WatchSource::Broadcast => {
+                ui.label(RichText::new("Shows the last game in the file and picks up moves as they are written").weak());
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.pgn_path);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Browse…").clicked() {
+                        self.pick_file();
+                    }
+                });
+                let ready = !self.pgn_path.trim().is_empty();
+                if ui.add_enabled(ready, egui::Button::new("Follow file")).clicked() {
+                    self.start();
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_file(&mut self) {
+        if let Some(path) = FileDialog::new().add_filter("PGN", &["pgn"]).pick_file() {
+            self.pgn_path = path.display().to_string();
+        }
+    }
+
+    fn render_player(&self, ui: &mut Ui, colour: Colour) {
+        let index = if colour.white() { 0 } else { 1 };
+        let fallback = if colour.white() { "White" } else { "Black" };
+        let name = if self.names[index].is_empty() { fallback } else { self.names[index].as_str() };
+        let to_move = self.started && self.result.is_none() && self.play.board.turn == colour;
+
+        ui.horizontal(|ui| {
+            if let Some(clocks) = self.clocks() {
+                let mut text = RichText::new(format_clock(clocks[index])).monospace().size(20.0);
+                if to_move {
+                    text = text.strong();
+                }
+                ui.label(text);
+            }
+            let name = RichText::new(name);
+            ui.label(if to_move { name.strong() } else { name });
+        });
+    }
+
+    fn render_players(&self, ui: &mut Ui) {
+        // Top of the board first
+        let (top, bottom) = if self.play.flipped { (Colour::White, Colour::Black) } else { (Colour::Black, Colour::White) };
+        self.render_player(ui, top);
+        self.render_player(ui, bottom);
+        if let Some(result) = &self.result {
+            ui.label(RichText::new(format!("Result: {}", result)).strong());
+        }
+    }
+
+    fn render_eval(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.show_eval, "Engine evaluation");
+        if !self.show_eval {
+            return;
+        }
+        if ui.add(Slider::new(&mut self.eval_depth, 1..=6).text("Depth")).changed() {
+            self.eval = None;
+        }
+        let Some(eval) = self.shown_eval() else {
+            if self.pending_eval.is_some() {
+                ui.label(RichText::new("Evaluating…").weak());
+            }
+            return;
+        };
+
+        let (rect, _) = ui.allocate_exact_size(Vec2::new(ui.available_width(), 14.0), Sense::hover());
+        let split = rect.left() + rect.width() * white_share(eval.score);
+        let painter = ui.painter();
+        painter.rect_filled(rect, 2.0, Color32::from_gray(40));
+        painter.rect_filled(egui::Rect::from_min_max(rect.min, egui::pos2(split, rect.bottom())), 2.0, Color32::from_gray(230));
+
+        ui.label(RichText::new(score_label(eval.score)).strong());
+        if let Some(best) = &eval.best {
+            ui.label(format!("Best move: {}", best));
+        }
+        ui.label(
+            RichText::new(format!(
+                "Material once the engine has played {} plies from here. Mates only show when its line reaches them",
+                EVAL_PLIES
+            ))
+            .weak(),
+        );
+    }
+}